@@ -0,0 +1,321 @@
+//! Pilots that fly the dragon without a human: simple rules, or a small
+//! neural network evolved with a genetic algorithm.
+use crate::simulation::{Dragon, Simulation};
+use crate::walls::{WallSchedule, TILE_SIZE, WALL_SPAWN_X};
+use crate::{CEILING, DRAGON_X, FLOOR, TICKS_PER_SECOND};
+use my_library::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+
+/// Where the game keeps the best trained genome, in its data folder.
+pub const GENOME_FILE: &str = "autopilot.ron";
+
+/// Half the dragon's width: a wall is behind the dragon once it is this
+/// far (plus half a tile) to the left.
+const DRAGON_HALF_WIDTH: f32 = 30.0;
+
+/// What a pilot knows about the world, all in pixels and seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Observation {
+    /// The dragon's height above the middle of the screen.
+    pub height: f32,
+    /// The dragon's vertical speed (up is positive).
+    pub velocity: f32,
+    /// How far ahead the next wall is.
+    pub distance: f32,
+    /// How far the middle of the next gap is above the dragon.
+    pub gap: f32,
+    /// The height of the next gap.
+    pub gap_size: f32,
+}
+
+impl Observation {
+    /// Looks at the first wall the dragon hasn't got past yet.
+    pub fn new(height: f32, velocity: f32, walls: &WallSchedule) -> Self {
+        let next = walls
+            .columns()
+            .find(|column| column.x + TILE_SIZE / 2.0 + DRAGON_HALF_WIDTH >= DRAGON_X);
+        let (distance, middle, gap_size) = match next {
+            Some(column) => {
+                let (middle, size) = column.gap();
+                (column.x - DRAGON_X, middle, size)
+            }
+            None => (WALL_SPAWN_X - DRAGON_X, 0.0, CEILING - FLOOR),
+        };
+        Self { height, velocity, distance, gap: middle - height, gap_size }
+    }
+}
+
+/// Something that can fly the dragon.
+pub trait Pilot {
+    /// Should the dragon flap this tick?
+    fn flap(&self, observation: &Observation) -> bool;
+}
+
+/// Flaps whenever the dragon is falling and has dropped below its
+/// target: a little below the middle of the next gap.
+#[derive(Clone, Copy, Debug)]
+pub struct RuleBased {
+    /// How far below the middle of the gap to flap, in pixels.
+    pub aim_below: f32,
+}
+
+impl Default for RuleBased {
+    fn default() -> Self {
+        Self { aim_below: 60.0 }
+    }
+}
+
+impl Pilot for RuleBased {
+    fn flap(&self, observation: &Observation) -> bool {
+        let aim_below = self.aim_below.min(observation.gap_size / 4.0);
+        observation.velocity <= 0.0 && observation.gap > aim_below
+    }
+}
+
+const INPUTS: usize = 6;
+const HIDDEN: usize = 8;
+/// The number of weights in a genome: inputs (with a bias) to each hidden
+/// neuron, then the hidden neurons (with a bias) to the output.
+pub const GENOME_LENGTH: usize = INPUTS * HIDDEN + HIDDEN + 1;
+
+/// The weights of a [`NeuralNet`], and how well they flew.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Genome {
+    pub weights: Vec<f32>,
+    /// Higher is better; see [`fitness`].
+    #[serde(default)]
+    pub fitness: f32,
+}
+
+impl Genome {
+    /// A genome with small random weights.
+    pub fn random(rng: &RandomNumberGenerator) -> Self {
+        let weights = (0..GENOME_LENGTH).map(|_| rng.range(-1.0..1.0)).collect();
+        Self { weights, fitness: 0.0 }
+    }
+
+    /// Takes each weight from one parent or the other.
+    fn crossover(&self, other: &Genome, rng: &RandomNumberGenerator) -> Genome {
+        let weights = self
+            .weights
+            .iter()
+            .zip(&other.weights)
+            .map(|(a, b)| if rng.range(0..2) == 0 { *a } else { *b })
+            .collect();
+        Genome { weights, fitness: 0.0 }
+    }
+
+    /// Nudges each weight, with probability `rate`, by a normally
+    /// distributed amount with standard deviation `strength`.
+    fn mutate(&mut self, rate: f32, strength: f32, rng: &RandomNumberGenerator) {
+        for weight in self.weights.iter_mut() {
+            if rng.range(0.0..1.0) < rate {
+                *weight += gaussian(rng) * strength;
+            }
+        }
+    }
+}
+
+/// A normally distributed number, with mean 0 and standard deviation 1.
+fn gaussian(rng: &RandomNumberGenerator) -> f32 {
+    let (u, v): (f32, f32) = (rng.range(f32::EPSILON..1.0), rng.range(0.0..1.0));
+    (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+}
+
+/// A tiny feed-forward network: the observation, one hidden layer and a
+/// single output that says whether to flap.
+#[derive(Clone, Debug)]
+pub struct NeuralNet {
+    weights: Vec<f32>,
+}
+
+impl NeuralNet {
+    /// Builds the network a genome describes. Fails if the genome is the
+    /// wrong size, for example because it was trained for an older
+    /// network.
+    pub fn new(genome: &Genome) -> Result<Self, String> {
+        if genome.weights.len() != GENOME_LENGTH {
+            return Err(format!(
+                "a genome needs {GENOME_LENGTH} weights, this one has {}",
+                genome.weights.len()
+            ));
+        }
+        Ok(Self { weights: genome.weights.clone() })
+    }
+
+    /// The network's output: positive means flap.
+    fn output(&self, observation: &Observation) -> f32 {
+        let inputs: [f32; INPUTS] = [
+            observation.height / CEILING,
+            observation.velocity / 300.0,
+            observation.distance / (WALL_SPAWN_X - DRAGON_X),
+            observation.gap / CEILING,
+            observation.gap_size / (CEILING - FLOOR),
+            1.0,
+        ];
+        let (hidden_weights, output_weights) = self.weights.split_at(INPUTS * HIDDEN);
+        let hidden = hidden_weights.chunks(INPUTS).map(|neuron| {
+            neuron.iter().zip(&inputs).map(|(w, x)| w * x).sum::<f32>().tanh()
+        });
+        hidden.zip(output_weights).map(|(h, w)| h * w).sum::<f32>() + output_weights[HIDDEN]
+    }
+}
+
+impl Pilot for NeuralNet {
+    fn flap(&self, observation: &Observation) -> bool {
+        self.output(observation) > 0.0
+    }
+}
+
+/// How well a dragon flew: seconds in the air, plus a point per wall,
+/// less how far (as a fraction of the screen) it was from the gap when it
+/// crashed.
+pub fn fitness(dragon: &Dragon) -> f32 {
+    dragon.ticks as f32 / TICKS_PER_SECOND as f32 + dragon.score as f32
+        - dragon.miss.abs() / (CEILING - FLOOR)
+}
+
+/// Settings for evolving pilots.
+#[derive(Clone, Debug)]
+pub struct Evolution {
+    /// Genomes in each generation.
+    pub population: usize,
+    /// Courses each genome flies per generation. New courses are drawn
+    /// every generation, so pilots can't learn one course by heart.
+    pub courses: usize,
+    /// A flight ends after this many ticks, even if the dragon is still
+    /// going.
+    pub max_ticks: u32,
+    /// The best genomes carried into the next generation unchanged.
+    pub elite: usize,
+    /// The chance of each weight mutating.
+    pub mutation_rate: f32,
+    /// The standard deviation of a mutation.
+    pub mutation_strength: f32,
+}
+
+impl Default for Evolution {
+    fn default() -> Self {
+        Self {
+            population: 200,
+            courses: 4,
+            max_ticks: 64 * 180,
+            elite: 4,
+            mutation_rate: 0.1,
+            mutation_strength: 0.5,
+        }
+    }
+}
+
+/// How one generation did.
+#[derive(Clone, Debug)]
+pub struct Generation {
+    pub number: usize,
+    pub best: Genome,
+    pub mean_fitness: f32,
+    /// The most walls any dragon got past.
+    pub best_score: u32,
+}
+
+impl Evolution {
+    /// Flies every genome through the same courses, one thread per course,
+    /// and sets each genome's fitness to its average. Returns the most
+    /// walls any dragon got past.
+    pub fn evaluate(&self, genomes: &mut [Genome], seeds: &[u64]) -> u32 {
+        let nets: Vec<NeuralNet> = genomes
+            .iter()
+            .map(|genome| NeuralNet::new(genome).expect("genomes are always the right size"))
+            .collect();
+        let results: Vec<Vec<Dragon>> = std::thread::scope(|scope| {
+            let runs: Vec<_> = seeds
+                .iter()
+                .map(|&seed| {
+                    let nets = &nets;
+                    scope.spawn(move || {
+                        let mut simulation = Simulation::new(seed, nets.len());
+                        simulation.run(nets, self.max_ticks);
+                        simulation.dragons().to_vec()
+                    })
+                })
+                .collect();
+            runs.into_iter().map(|run| run.join().expect("a simulation panicked")).collect()
+        });
+
+        for (index, genome) in genomes.iter_mut().enumerate() {
+            let total: f32 = results.iter().map(|dragons| fitness(&dragons[index])).sum();
+            genome.fitness = total / seeds.len() as f32;
+        }
+        results.iter().flatten().map(|dragon| dragon.score).max().unwrap_or(0)
+    }
+
+    /// Breeds the next generation: the elite survive, and the rest are
+    /// children of parents picked by tournament.
+    fn breed(&self, ranked: &[Genome], rng: &RandomNumberGenerator) -> Vec<Genome> {
+        let pick = || {
+            (0..3)
+                .map(|_| &ranked[rng.range(0..ranked.len())])
+                .max_by(|a, b| a.fitness.total_cmp(&b.fitness))
+                .expect("tournaments have entrants")
+        };
+        let mut next: Vec<Genome> = ranked.iter().take(self.elite).cloned().collect();
+        while next.len() < self.population {
+            let mut child = pick().crossover(pick(), rng);
+            child.mutate(self.mutation_rate, self.mutation_strength, rng);
+            next.push(child);
+        }
+        next
+    }
+
+    /// Evolves pilots for `generations` generations, calling `report`
+    /// after each one. Returns the best genome of the last generation.
+    pub fn run(
+        &self,
+        rng: &RandomNumberGenerator,
+        generations: usize,
+        mut report: impl FnMut(&Generation),
+    ) -> Genome {
+        let mut population: Vec<Genome> = (0..self.population).map(|_| Genome::random(rng)).collect();
+        let mut best = population[0].clone();
+        for number in 1..=generations {
+            let seeds: Vec<u64> = (0..self.courses).map(|_| rng.next()).collect();
+            let best_score = self.evaluate(&mut population, &seeds);
+            population.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+            best = population[0].clone();
+            let mean_fitness =
+                population.iter().map(|genome| genome.fitness).sum::<f32>() / population.len() as f32;
+            report(&Generation { number, best: best.clone(), mean_fitness, best_score });
+            if number < generations {
+                population = self.breed(&population, rng);
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_get_past_walls() {
+        let mut simulation = Simulation::new(7, 1);
+        simulation.run(&[RuleBased::default()], 64 * 60);
+        assert!(simulation.score() >= 10, "scored {}", simulation.score());
+    }
+
+    #[test]
+    fn test_evolution_improves_pilots() {
+        let evolution = Evolution { population: 40, courses: 2, max_ticks: 64 * 30, ..Evolution::default() };
+        let rng = RandomNumberGenerator::seeded(3);
+        let mut history = Vec::new();
+        let best = evolution.run(&rng, 8, |generation| history.push(generation.best.fitness));
+        assert!(history.last() > history.first(), "{history:?}");
+        assert!(NeuralNet::new(&best).is_ok());
+    }
+
+    #[test]
+    fn test_genome_size_is_checked() {
+        let genome = Genome { weights: vec![0.0; 3], fitness: 0.0 };
+        assert!(NeuralNet::new(&genome).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! Evolves an autopilot for Flappy Dragon, without opening a window.
+//!
+//! ```text
+//! cargo run --release -p flappy --bin train -- --generations 50
+//! ```
+//!
+//! The best genome is saved where the game looks for it, so the trained
+//! autopilot is ready to fly the next time the game starts (press F2 twice).
+use flappy::ai::{Evolution, GENOME_FILE};
+use flappy::TICKS_PER_SECOND;
+use my_library::{persistence, RandomNumberGenerator};
+use std::path::PathBuf;
+
+const USAGE: &str = "Usage: train [options]
+
+Options:
+  --generations N   how many generations to evolve (default 30)
+  --population N    genomes per generation (default 200)
+  --courses N       courses each genome flies per generation (default 4)
+  --seconds N       longest flight, in seconds (default 180)
+  --seed N          seed for the genetic algorithm (default: random)
+  --out FILE        where to save the best genome (default: the game's data folder)";
+
+struct Options {
+    generations: usize,
+    evolution: Evolution,
+    seed: Option<u64>,
+    out: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options { generations: 30, evolution: Evolution::default(), seed: None, out: None };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        let number = |text: String| text.parse::<u64>().map_err(|_| format!("{arg}: {text} isn't a number"));
+        match arg.as_str() {
+            "--generations" => options.generations = number(value()?)? as usize,
+            "--population" => options.evolution.population = number(value()?)? as usize,
+            "--courses" => options.evolution.courses = number(value()?)? as usize,
+            "--seconds" => options.evolution.max_ticks = number(value()?)? as u32 * TICKS_PER_SECOND as u32,
+            "--seed" => options.seed = Some(number(value()?)?),
+            "--out" => options.out = Some(PathBuf::from(value()?)),
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ => return Err(format!("Unknown option {arg}\n\n{USAGE}")),
+        }
+    }
+    if options.evolution.population <= options.evolution.elite || options.evolution.courses == 0 {
+        return Err(format!(
+            "The population must be bigger than {} and there must be at least one course",
+            options.evolution.elite
+        ));
+    }
+    Ok(options)
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{message}");
+            std::process::exit(2);
+        }
+    };
+    let rng = match options.seed {
+        Some(seed) => RandomNumberGenerator::seeded(seed),
+        None => RandomNumberGenerator::new(),
+    };
+    println!(
+        "Evolving {} pilots for {} generations (seed {})",
+        options.evolution.population,
+        options.generations,
+        rng.seed()
+    );
+
+    let best = options.evolution.run(&rng, options.generations, |generation| {
+        println!(
+            "Generation {:>3}: best fitness {:>7.2}, mean {:>7.2}, most walls {}",
+            generation.number, generation.best.fitness, generation.mean_fitness, generation.best_score
+        );
+    });
+
+    let saved = match &options.out {
+        Some(path) => persistence::save_file(path, &best).map(|_| path.clone()),
+        None => persistence::save("flappy", GENOME_FILE, &best)
+            .and_then(|_| persistence::data_dir("flappy"))
+            .map(|dir| dir.join(GENOME_FILE)),
+    };
+    match saved {
+        Ok(path) => println!("Saved the best genome to {}", path.display()),
+        Err(e) => {
+            eprintln!("Couldn't save the best genome: {e}");
+            std::process::exit(1);
+        }
+    }
+}
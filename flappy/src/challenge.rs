@@ -0,0 +1,42 @@
+//! The daily challenge: one course a day, the same for every player.
+use my_library::{civil_from_days, unix_time};
+
+/// Today's date, as days since 1970-01-01 (UTC).
+pub fn today() -> u32 {
+    (unix_time() / 86_400) as u32
+}
+
+/// The seed for the walls on `day`. Neighbouring days get unrelated
+/// courses.
+pub fn daily_seed(day: u32) -> u64 {
+    // SplitMix64's finaliser.
+    let mut z = (day as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// `day` as YYYY-MM-DD.
+pub fn date(day: u32) -> String {
+    let (year, month, day) = civil_from_days(day as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(11_017), "2000-03-01");
+        assert_eq!(date(19_782), "2024-02-29");
+        assert_eq!(date(20_745), "2026-10-19");
+    }
+
+    #[test]
+    fn test_each_day_has_its_own_course() {
+        assert_eq!(daily_seed(20_745), daily_seed(20_745));
+        assert_ne!(daily_seed(20_745), daily_seed(20_746));
+    }
+}
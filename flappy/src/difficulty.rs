@@ -0,0 +1,217 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::{fmt, path::Path};
+
+/// How hard the game is at one moment.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct Difficulty {
+    /// How fast the walls move left, in pixels per second.
+    pub scroll_speed: f32,
+    /// The height of the gap in each wall, in tiles.
+    pub gap_size: f32,
+    /// How far the gap may be from the middle of the screen, in tiles.
+    pub gap_variance: f32,
+    /// The distance between walls, in pixels.
+    pub wall_spacing: f32,
+    /// The chance that a wall's gap moves up and down, from 0 to 1.
+    pub moving_gap: f32,
+    /// The chance of two walls close together.
+    pub double_wall: f32,
+    /// The chance of a hazard hanging from the ceiling instead of a wall.
+    pub ceiling_hazard: f32,
+}
+
+impl Difficulty {
+    fn lerp(&self, other: &Difficulty, t: f32) -> Difficulty {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Difficulty {
+            scroll_speed: mix(self.scroll_speed, other.scroll_speed),
+            gap_size: mix(self.gap_size, other.gap_size),
+            gap_variance: mix(self.gap_variance, other.gap_variance),
+            wall_spacing: mix(self.wall_spacing, other.wall_spacing),
+            moving_gap: mix(self.moving_gap, other.moving_gap),
+            double_wall: mix(self.double_wall, other.double_wall),
+            ceiling_hazard: mix(self.ceiling_hazard, other.ceiling_hazard),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Level {
+    score: u32,
+    scroll_speed: f32,
+    gap_size: f32,
+    gap_variance: f32,
+    wall_spacing: f32,
+    #[serde(default)]
+    moving_gap: f32,
+    #[serde(default)]
+    double_wall: f32,
+    #[serde(default)]
+    ceiling_hazard: f32,
+}
+
+/// Difficulty by score, read from `assets/difficulty.ron` when the game
+/// starts. Settings are blended between the levels in the file.
+#[derive(Resource, Deserialize)]
+pub struct DifficultyCurve {
+    levels: Vec<Level>,
+}
+
+/// Why a difficulty curve was rejected.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CurveError {
+    /// The text isn't a difficulty curve.
+    Parse(ron::error::SpannedError),
+    /// There are no levels to play.
+    NoLevels,
+    /// The level for this score has a negative gap variance.
+    NegativeVariance(u32),
+    /// The level for this score has a gap smaller than one tile.
+    GapTooSmall(u32),
+}
+
+impl fmt::Display for CurveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(e) => write!(f, "{e}"),
+            Self::NoLevels => write!(f, "the curve has no levels"),
+            Self::NegativeVariance(score) => write!(f, "the gap variance at score {score} is negative"),
+            Self::GapTooSmall(score) => write!(f, "the gap at score {score} is less than a tile"),
+        }
+    }
+}
+
+impl std::error::Error for CurveError {}
+
+impl DifficultyCurve {
+    pub fn from_ron(text: &str) -> Result<Self, CurveError> {
+        let mut curve: DifficultyCurve = ron::from_str(text).map_err(CurveError::Parse)?;
+        if curve.levels.is_empty() {
+            return Err(CurveError::NoLevels);
+        }
+        for level in &curve.levels {
+            if level.gap_variance < 0.0 {
+                return Err(CurveError::NegativeVariance(level.score));
+            }
+            if level.gap_size < 1.0 {
+                return Err(CurveError::GapTooSmall(level.score));
+            }
+        }
+        curve.levels.sort_by_key(|level| level.score);
+        Ok(curve)
+    }
+
+    /// The difficulty once the player has scored `score`.
+    pub fn at(&self, score: u32) -> Difficulty {
+        let index = self.levels.partition_point(|level| level.score <= score);
+        match (self.levels.get(index.wrapping_sub(1)), self.levels.get(index)) {
+            (Some(below), Some(above)) => {
+                let t = (score - below.score) as f32 / (above.score - below.score) as f32;
+                below.difficulty().lerp(&above.difficulty(), t)
+            }
+            (Some(level), None) | (None, Some(level)) => level.difficulty(),
+            (None, None) => panic!("The difficulty curve has no levels"),
+        }
+    }
+}
+
+impl Level {
+    fn difficulty(&self) -> Difficulty {
+        Difficulty {
+            scroll_speed: self.scroll_speed,
+            gap_size: self.gap_size,
+            gap_variance: self.gap_variance,
+            wall_spacing: self.wall_spacing,
+            moving_gap: self.moving_gap,
+            double_wall: self.double_wall,
+            ceiling_hazard: self.ceiling_hazard,
+        }
+    }
+}
+
+impl DifficultyCurve {
+    /// Reads the curve from `path`, so it can be tuned without rebuilding
+    /// the game. Falls back to the copy built into the game if the file is
+    /// missing or isn't a valid curve.
+    pub fn load(path: &Path) -> Self {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("Couldn't read {}: {e}", path.display());
+                return Self::default();
+            }
+        };
+        Self::from_ron(&text).unwrap_or_else(|e| {
+            warn!("{} isn't a valid difficulty curve: {e}", path.display());
+            Self::default()
+        })
+    }
+}
+
+/// The curve the game was built with.
+impl Default for DifficultyCurve {
+    fn default() -> Self {
+        Self::from_ron(include_str!("../assets/difficulty.ron"))
+            .expect("assets/difficulty.ron should be a valid difficulty curve")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVE: &str = "(levels: [
+        (score: 10, scroll_speed: 200.0, gap_size: 6.0, gap_variance: 4.0, wall_spacing: 400.0),
+        (score: 0, scroll_speed: 100.0, gap_size: 8.0, gap_variance: 2.0, wall_spacing: 800.0),
+    ])";
+
+    #[test]
+    fn test_curve_blends_between_levels() {
+        let curve = DifficultyCurve::from_ron(CURVE).unwrap();
+        assert_eq!(curve.at(0).scroll_speed, 100.0);
+        let middle = curve.at(5);
+        assert_eq!(middle.scroll_speed, 150.0);
+        assert_eq!(middle.gap_size, 7.0);
+        assert_eq!(middle.wall_spacing, 600.0);
+        assert_eq!(curve.at(100).gap_variance, 4.0);
+    }
+
+    #[test]
+    fn test_bad_curves_are_rejected() {
+        let level = |gap_size: f32, gap_variance: f32| format!(
+            "(levels: [(score: 5, scroll_speed: 100.0, gap_size: {gap_size:?}, gap_variance: {gap_variance:?}, wall_spacing: 800.0)])"
+        );
+        assert!(DifficultyCurve::from_ron(&level(8.0, 2.0)).is_ok());
+        assert_eq!(DifficultyCurve::from_ron("(levels: [])").err(), Some(CurveError::NoLevels));
+        assert_eq!(DifficultyCurve::from_ron(&level(8.0, -1.0)).err(), Some(CurveError::NegativeVariance(5)));
+        assert_eq!(DifficultyCurve::from_ron(&level(0.5, 2.0)).err(), Some(CurveError::GapTooSmall(5)));
+        assert!(matches!(DifficultyCurve::from_ron("(levels: 3)"), Err(CurveError::Parse(_))));
+    }
+
+    #[test]
+    fn test_curve_is_loaded_at_runtime() {
+        let dir = std::env::temp_dir().join(format!("flappy-curve-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("difficulty.ron");
+        std::fs::write(&path, CURVE).unwrap();
+        assert_eq!(DifficultyCurve::load(&path).at(0).scroll_speed, 100.0);
+
+        // A broken or missing file plays the built-in curve.
+        let built_in = DifficultyCurve::default().at(0);
+        std::fs::write(&path, "(levels: [])").unwrap();
+        assert_eq!(DifficultyCurve::load(&path).at(0), built_in);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(DifficultyCurve::load(&path).at(0), built_in);
+    }
+
+    #[test]
+    fn test_shipped_curve_gets_harder() {
+        let curve = DifficultyCurve::default();
+        let (easy, hard) = (curve.at(0), curve.at(1000));
+        assert!(hard.scroll_speed > easy.scroll_speed);
+        assert!(hard.gap_size < easy.gap_size);
+        assert_eq!(easy.moving_gap + easy.double_wall + easy.ceiling_hazard, 0.0);
+        assert!(hard.moving_gap + hard.double_wall + hard.ceiling_hazard <= 1.0);
+    }
+}
@@ -0,0 +1,163 @@
+//! Ghost runs: the best flight on each course, replayed behind the player
+//! by a translucent dragon.
+use crate::{Assets, Course, Flappy, Flight, Position};
+use bevy::prelude::*;
+use flappy::DRAGON_X;
+use my_library::persistence::DataDir;
+use serde::{Deserialize, Serialize};
+
+const GHOST_FILE: &str = "ghosts.ron";
+/// Only the most recently improved courses keep their ghosts: about a
+/// month of daily challenges.
+const MAX_GHOSTS: usize = 30;
+
+/// The best flight on one course: the dragon's height on every tick.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GhostRun {
+    pub seed: u64,
+    pub score: u32,
+    pub heights: Vec<f32>,
+}
+
+impl GhostRun {
+    /// Is this a better run than `other`? More walls wins, then a longer
+    /// flight.
+    fn beats(&self, other: &GhostRun) -> bool {
+        (self.score, self.heights.len()) > (other.score, other.heights.len())
+    }
+}
+
+/// Every saved ghost, oldest first.
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct Ghosts {
+    runs: Vec<GhostRun>,
+}
+
+impl Ghosts {
+    pub fn load(dir: &DataDir) -> Self {
+        match dir.load::<Ghosts>("flappy", GHOST_FILE) {
+            Ok(ghosts) => ghosts.unwrap_or_default(),
+            Err(e) => {
+                warn!("Couldn't load ghost runs: {e}");
+                Self::default()
+            }
+        }
+    }
+
+    /// The best run on the course with `seed`.
+    pub fn get(&self, seed: u64) -> Option<&GhostRun> {
+        self.runs.iter().find(|run| run.seed == seed)
+    }
+
+    /// Keeps `run` if it beats the ghost for its course. Returns whether
+    /// it did.
+    pub fn offer(&mut self, run: GhostRun) -> bool {
+        if self.get(run.seed).is_some_and(|best| !run.beats(best)) {
+            return false;
+        }
+        self.runs.retain(|best| best.seed != run.seed);
+        self.runs.push(run);
+        if self.runs.len() > MAX_GHOSTS {
+            self.runs.drain(..self.runs.len() - MAX_GHOSTS);
+        }
+        true
+    }
+}
+
+/// The current flight's heights, one per tick.
+#[derive(Resource, Default)]
+pub struct Recording(pub Vec<f32>);
+
+/// The dragon replaying the best run.
+#[derive(Component)]
+pub struct Ghost;
+
+/// Adds a ghost, if the course has one.
+pub fn spawn(commands: &mut Commands, ghosts: &Ghosts, seed: u64, assets: &Assets) {
+    let Some(run) = ghosts.get(seed) else {
+        return;
+    };
+    let y = run.heights.first().copied().unwrap_or(0.0);
+    commands
+        .spawn(SpriteBundle {
+            texture: assets.dragon.clone(),
+            sprite: Sprite { color: Color::srgba(1.0, 1.0, 1.0, 0.35), ..default() },
+            transform: Transform::from_xyz(DRAGON_X, y, 0.9),
+            ..default()
+        })
+        .insert(TextureAtlas { layout: assets.dragon_atlas.clone(), index: 0 })
+        .insert(Position::new(DRAGON_X, y))
+        .insert(Ghost);
+}
+
+/// Notes where the dragon is, once per tick.
+pub fn record(dragon: Query<&Position, With<Flappy>>, mut recording: ResMut<Recording>) {
+    if let Ok(position) = dragon.get_single() {
+        recording.0.push(position.current.y);
+    }
+}
+
+/// Moves the ghost to where the best run was on this tick. The ghost
+/// vanishes where that run crashed.
+pub fn replay(
+    mut commands: Commands,
+    recording: Res<Recording>,
+    ghosts: Res<Ghosts>,
+    course: Res<Course>,
+    mut ghost: Query<(Entity, &mut Position), With<Ghost>>,
+) {
+    let (Ok((entity, mut position)), Some(run)) = (ghost.get_single_mut(), ghosts.get(course.seed)) else {
+        return;
+    };
+    match recording.0.len().checked_sub(1).and_then(|tick| run.heights.get(tick)) {
+        Some(&y) => position.current.y = y,
+        None => commands.entity(entity).despawn(),
+    }
+}
+
+/// Saves a daily challenge flight as the course's ghost, if it was the
+/// best yet. Free flights never see their course again, so they aren't
+/// kept, and flights the autopilot helped with don't count.
+pub fn keep_best(
+    flight: Res<Flight>,
+    course: Res<Course>,
+    mut recording: ResMut<Recording>,
+    mut ghosts: ResMut<Ghosts>,
+    dir: Res<DataDir>,
+) {
+    if flight.autopiloted || course.day.is_none() {
+        return;
+    }
+    let run = GhostRun { seed: course.seed, score: flight.score, heights: std::mem::take(&mut recording.0) };
+    if ghosts.offer(run) {
+        if let Err(e) = dir.save("flappy", GHOST_FILE, &*ghosts) {
+            warn!("Couldn't save the ghost run: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(seed: u64, score: u32, ticks: usize) -> GhostRun {
+        GhostRun { seed, score, heights: vec![0.0; ticks] }
+    }
+
+    #[test]
+    fn test_only_better_runs_replace_ghosts() {
+        let mut ghosts = Ghosts::default();
+        assert!(ghosts.offer(run(1, 3, 100)));
+        assert!(!ghosts.offer(run(1, 2, 500)));
+        assert!(!ghosts.offer(run(1, 3, 100)));
+        assert!(ghosts.offer(run(1, 3, 120)));
+        assert!(ghosts.offer(run(2, 0, 10)));
+        assert_eq!(ghosts.get(1), Some(&run(1, 3, 120)));
+
+        for seed in 10..10 + MAX_GHOSTS as u64 {
+            ghosts.offer(run(seed, 1, 1));
+        }
+        assert_eq!(ghosts.runs.len(), MAX_GHOSTS);
+        assert!(ghosts.get(1).is_none());
+    }
+}
@@ -0,0 +1,52 @@
+//! The parts of Flappy Dragon that don't need a window: the difficulty
+//! curve, wall planning, physics, a headless simulation, the autopilot
+//! and the daily challenge. The game and the `train` tool both build on
+//! them.
+use bevy::prelude::*;
+use my_library::Collider;
+
+pub mod ai;
+pub mod challenge;
+pub mod difficulty;
+pub mod simulation;
+pub mod walls;
+
+/// The physics runs this many times a second, in the game and in
+/// training alike.
+pub const TICKS_PER_SECOND: f64 = 64.0;
+
+/// The dragon stays at this x position; the walls come to it.
+pub const DRAGON_X: f32 = -490.0;
+/// The dragon can't fly above the top of the screen...
+pub const CEILING: f32 = 384.0;
+/// ...and crashes if it falls below the bottom.
+pub const FLOOR: f32 = -384.0;
+
+/// The dragon's shape, for collisions.
+pub fn dragon_collider() -> Collider {
+    Collider::horizontal_capsule(60.0, 36.0)
+}
+
+/// Tunable physics, in pixels and seconds.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct PhysicsConfig {
+    /// Downward acceleration, in pixels per second squared.
+    pub gravity: f32,
+    /// Upward speed after a flap, in pixels per second.
+    pub flap_velocity: f32,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self { gravity: 360.0, flap_velocity: 300.0 }
+    }
+}
+
+impl PhysicsConfig {
+    /// Lets gravity act on a dragon at height `y`, moving at `velocity`,
+    /// for `delta` seconds. Returns its new height and velocity.
+    pub fn fall(&self, y: f32, velocity: f32, delta: f32) -> (f32, f32) {
+        let velocity = velocity - self.gravity * delta;
+        (y + velocity * delta, velocity)
+    }
+}
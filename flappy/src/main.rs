@@ -1,41 +1,160 @@
+use bevy::asset::io::file::FileAssetReader;
 use bevy::prelude::*;
+use flappy::ai::{Genome, NeuralNet, Observation, Pilot, RuleBased, GENOME_FILE};
+use flappy::challenge;
+use flappy::difficulty::DifficultyCurve;
+use flappy::*;
 use my_library::*;
+use my_library::persistence::DataDir;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+mod ghost;
+use ghost::{Ghosts, Recording};
+mod spawner;
+use spawner::WallSpawner;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default, States)]
 enum GamePhase {
-    // #[default]
-    MainMenu,
+    /// Where the game opens.
     #[default]
+    MainMenu,
     Flapping,
     GameOver,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+enum FlappyAction {
+    Flap,
+    RebindFlap,
+    ToggleCollisionDebug,
+    ToggleAutopilot,
+    DailyChallenge,
+}
+
+#[derive(Component)]
+struct RebindPrompt;
+
+#[derive(Component)]
+struct DailyPrompt;
+
+/// Whether flights are the daily challenge. Pressing D on the main menu
+/// starts the challenge, and it lasts until the player goes back to the
+/// menu.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum ChallengeMode {
+    #[default]
+    Free,
+    Daily,
+}
+
+/// The course being flown: the walls come from `seed`.
+#[derive(Resource)]
+struct Course {
+    seed: u64,
+    /// The day of the daily challenge, if this is one.
+    day: Option<u32>,
+    /// Plans the walls, so nothing else that's random changes them.
+    walls: RandomNumberGenerator,
+}
+
+impl Course {
+    fn new(seed: u64, day: Option<u32>) -> Self {
+        Self { seed, day, walls: RandomNumberGenerator::seeded(seed) }
+    }
+}
+
+/// The dragon. `velocity` is vertical, in pixels per second (up is
+/// positive).
 #[derive(Component)]
 struct Flappy {
-    gravity: f32,
+    velocity: f32,
 }
 
+/// Who flies the dragon.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Autopilot {
+    #[default]
+    Off,
+    Rules,
+    /// The pilot evolved by the `train` tool.
+    Trained,
+}
+
+/// The best pilot the `train` tool has saved, if it has been run.
+#[derive(Resource, Default)]
+struct TrainedPilot(Option<NeuralNet>);
+
+impl TrainedPilot {
+    fn load(dir: &DataDir) -> Self {
+        match dir.load::<Genome>("flappy", GENOME_FILE) {
+            Ok(genome) => Self(genome.and_then(|genome| {
+                NeuralNet::new(&genome)
+                    .map_err(|e| warn!("Ignoring the trained autopilot: {e}"))
+                    .ok()
+            })),
+            Err(e) => {
+                warn!("Couldn't load the trained autopilot: {e}");
+                Self(None)
+            }
+        }
+    }
+}
+
+/// Where an entity is in the simulation, which runs on a fixed timestep.
+/// Its `Transform` is interpolated between the last two steps, so
+/// movement looks smooth at any frame rate.
 #[derive(Component)]
-struct Obstacle;
+struct Position {
+    previous: Vec2,
+    current: Vec2,
+}
+
+impl Position {
+    fn new(x: f32, y: f32) -> Self {
+        Self { previous: Vec2::new(x, y), current: Vec2::new(x, y) }
+    }
+}
+
+impl CollisionPosition for Position {
+    fn collision_position(&self) -> Vec2 {
+        self.current
+    }
+}
+
+const DRAGON_GROUP: u32 = 1;
+const WALL_GROUP: u32 = 2;
 
 #[derive(Component)]
-struct FlappyElement;
+struct ScoreText;
+
+/// How the current flight is going. The score is one point per wall
+/// passed.
+#[derive(Resource, Default)]
+struct Flight {
+    seconds: f32,
+    score: u32,
+    /// Did the autopilot fly at all? If so, the score isn't a high score.
+    autopiloted: bool,
+}
 
 #[derive(Resource)]
 struct Assets {
     dragon: Handle<Image>,
+    dragon_atlas: Handle<TextureAtlasLayout>,
+    dragon_animations: Arc<AnimationLibrary>,
     wall: Handle<Image>,
 }
 
+/// The dragon sheet has four frames, from wings up to wings down.
+fn dragon_animations() -> AnimationLibrary {
+    AnimationLibrary::new()
+        .with("glide", SpriteClip::still(0))
+        .with("flap", SpriteClip::new(1..=3, 16.0, PlayMode::Once).then("glide"))
+}
+
 fn main() {
     let mut app = App::new();
-
-    add_phase!(app, GamePhase, GamePhase::Flapping,
-        start => [ setup ],
-        run   => [ gravity, flap, clamp, move_walls, hit_wall ],
-        exit  => [ cleanup::<FlappyElement> ]
-    );
-
     app.add_plugins(DefaultPlugins.set(WindowPlugin {
         primary_window: Some(Window {
             title: "Flappy Dragon - Bevy Edition".to_string(),
@@ -43,122 +162,507 @@ fn main() {
             ..default()
         }),
         ..default()
-        }))
+        }));
+    add_game(&mut app);
+    app.run();
+}
+
+/// Adds everything but the window and renderer, so tests can run the game
+/// headless.
+fn add_game(app: &mut App) {
+    app.add_systems(Update, (start_rebind, show_rebind_prompt, start_daily_challenge)
+        .run_if(in_state(GamePhase::MainMenu)));
+    app.add_systems(OnEnter(GamePhase::MainMenu), show_daily_prompt)
+        .add_systems(OnExit(GamePhase::MainMenu), cleanup::<DailyPrompt>);
+    app.add_systems(Update, toggle_autopilot);
+
+    let dir = DataDir::from_app(app);
+    app.insert_resource(Time::<Fixed>::from_hz(TICKS_PER_SECOND))
+        .insert_resource(TrainedPilot::load(&dir))
+        .insert_resource(Ghosts::load(&dir))
+        .init_resource::<ChallengeMode>()
+        .init_resource::<Autopilot>()
+        .init_resource::<PhysicsConfig>()
+        .insert_resource(DifficultyCurve::load(
+            &FileAssetReader::new("assets").root_path().join("difficulty.ron"),
+        ))
         .add_plugins(RandomPlugin)
+        .add_plugins(SpriteAnimationPlugin)
+        .add_plugins(HighScorePlugin::new("flappy"))
+        .add_plugins(CollisionPlugin::<Position>::new().in_schedule(FixedUpdate))
+        .add_plugins(ActionPlugin::new(default_bindings()).persist("flappy", "controls.ron"))
         .add_plugins(GameStatePlugin::new(
             GamePhase::MainMenu,
             GamePhase::Flapping,
             GamePhase::GameOver,
+        )
+        .with_transition(Transition::FadeToBlack { duration: 0.6 })
+        .with_transition_between(
+            GamePhase::Flapping,
+            GamePhase::GameOver,
+            Transition::Crossfade { duration: 0.8 },
         ))
-        .run();
+        .add_phase_graph(
+            PhaseGraph::new()
+                .phase(GamePhase::Flapping, |phase| phase
+                    .on_enter(setup)
+                    .fixed_update((
+                        remember_positions,
+                        fly_autopilot,
+                        flap,
+                        gravity,
+                        clamp,
+                        spawner::scroll_walls,
+                        spawner::spawn_walls,
+                        spawner::move_gaps,
+                        ghost::record,
+                        ghost::replay,
+                    ).chain().before(CollisionSystems))
+                    .fixed_update((hit_wall, time_flight).after(CollisionSystems))
+                    .update((interpolate, show_score, toggle_collision_debug))
+                    .on_exit((publish_results, ghost::keep_best)))
+                .transition(GamePhase::Flapping, GamePhase::GameOver)
+                .scope_resource::<Assets>()
+                .scope_resource::<Flight>()
+                .scope_resource::<WallSpawner>()
+                .scope_resource::<Recording>()
+                .scope_resource::<Course>(),
+        );
+}
+
+fn default_bindings() -> InputMap<FlappyAction> {
+    InputMap::new()
+        .bind(FlappyAction::Flap, InputBinding::Key(KeyCode::Space))
+        .bind(FlappyAction::Flap, InputBinding::Mouse(MouseButton::Left))
+        .bind(FlappyAction::Flap, InputBinding::GamepadButton(GamepadButtonType::South))
+        .bind(FlappyAction::RebindFlap, InputBinding::Key(KeyCode::F1))
+        .bind(FlappyAction::DailyChallenge, InputBinding::Key(KeyCode::KeyD))
+        .bind(FlappyAction::ToggleAutopilot, InputBinding::Key(KeyCode::F2))
+        .bind(FlappyAction::ToggleCollisionDebug, InputBinding::Key(KeyCode::F3))
+}
+
+fn start_rebind(
+    actions: Res<ActionState<FlappyAction>>,
+    mut rebinding: ResMut<Rebinding<FlappyAction>>,
+) {
+    if actions.just_pressed(FlappyAction::RebindFlap) {
+        rebinding.0 = Some(FlappyAction::Flap);
+    }
+}
+
+fn show_rebind_prompt(
+    mut commands: Commands,
+    rebinding: Res<Rebinding<FlappyAction>>,
+    prompt: Query<Entity, With<RebindPrompt>>,
+) {
+    match (rebinding.0, prompt.get_single()) {
+        (Some(_), Err(_)) => {
+            commands
+                .spawn(Text2dBundle {
+                    text: Text::from_section(
+                        "Press the key or button you want to flap with",
+                        TextStyle { font_size: 32.0, ..default() },
+                    ),
+                    transform: Transform::from_xyz(0.0, -300.0, 2.0),
+                    ..default()
+                })
+                .insert(RebindPrompt);
+        }
+        (None, Ok(entity)) => commands.entity(entity).despawn(),
+        _ => {}
+    }
+}
+
+fn show_daily_prompt(mut commands: Commands, mut mode: ResMut<ChallengeMode>) {
+    *mode = ChallengeMode::Free;
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(
+                format!("D - Daily challenge ({})", challenge::date(challenge::today())),
+                TextStyle { font_size: 28.0, ..default() },
+            ),
+            transform: Transform::from_xyz(0.0, -345.0, 2.0),
+            ..default()
+        })
+        .insert(DailyPrompt);
+}
+
+fn start_daily_challenge(
+    actions: Res<ActionState<FlappyAction>>,
+    mut mode: ResMut<ChallengeMode>,
+    mut state: ResMut<NextState<GamePhase>>,
+) {
+    if actions.just_pressed(FlappyAction::DailyChallenge) {
+        *mode = ChallengeMode::Daily;
+        state.set(GamePhase::Flapping);
+    }
+}
+
+/// Picks the course: today's for the daily challenge, otherwise a new one.
+fn choose_course(mode: ChallengeMode, rng: &RandomNumberGenerator) -> Course {
+    match mode {
+        ChallengeMode::Daily => {
+            let day = challenge::today();
+            Course::new(challenge::daily_seed(day), Some(day))
+        }
+        ChallengeMode::Free => Course::new(rng.next(), None),
+    }
 }
 
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    mut layouts: ResMut<bevy::asset::Assets<TextureAtlasLayout>>,
     rng: Res<RandomNumberGenerator>,
+    mode: Res<ChallengeMode>,
+    ghosts: Res<Ghosts>,
 ) {
     let assets = Assets {
         dragon: asset_server.load("flappy_dragon.png"),
+        dragon_atlas: layouts.add(TextureAtlasLayout::from_grid(UVec2::new(65, 45), 4, 1, None, None)),
+        dragon_animations: Arc::new(dragon_animations()),
         wall: asset_server.load("wall.png"),
     };
+    let course = choose_course(*mode, &rng);
+    ghost::spawn(&mut commands, &ghosts, course.seed, &assets);
 
     commands
-        .spawn(Camera2dBundle::default())
-        .insert(FlappyElement);
+        .spawn(Camera2dBundle::default());
     commands
         .spawn(SpriteBundle {
             texture: assets.dragon.clone(),
-            transform: Transform::from_xyz(-490.0, 0.0, 1.0),
+            transform: Transform::from_xyz(DRAGON_X, 0.0, 1.0),
             ..default()
         })
-        .insert(Flappy { gravity: 0.0 })
-        .insert(FlappyElement);
+        .insert(TextureAtlas { layout: assets.dragon_atlas.clone(), index: 0 })
+        .insert(SpriteAnimation::new(assets.dragon_animations.clone(), "glide"))
+        .insert(Flappy { velocity: 0.0 })
+        .insert(Position::new(DRAGON_X, 0.0))
+        .insert(dragon_collider())
+        .insert(CollisionGroups::new(DRAGON_GROUP, WALL_GROUP));
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section("Score: 0", TextStyle { font_size: 40.0, ..default() }),
+            transform: Transform::from_xyz(0.0, 340.0, 5.0),
+            ..default()
+        })
+        .insert(ScoreText);
 
-    build_wall(&mut commands, assets.wall.clone(), rng.range(-5..5));
     commands.insert_resource(assets);
+    commands.insert_resource(Flight::default());
+    commands.insert_resource(WallSpawner::default());
+    commands.insert_resource(Recording::default());
+    commands.insert_resource(course);
+}
+
+/// Runs first in every fixed step, so interpolation knows where each
+/// entity started the step.
+fn remember_positions(mut query: Query<&mut Position>) {
+    for mut position in query.iter_mut() {
+        position.previous = position.current;
+    }
 }
 
-fn build_wall(
-    commands: &mut Commands,
-    wall_sprite: Handle<Image>,
-    gap_y: i32,
+/// Places each `Transform` between the last two simulation steps.
+fn interpolate(time: Res<Time<Fixed>>, mut query: Query<(&Position, &mut Transform)>) {
+    let blend = time.overstep_fraction();
+    for (position, mut transform) in query.iter_mut() {
+        let z = transform.translation.z;
+        transform.translation = position.previous.lerp(position.current, blend).extend(z);
+    }
+}
+
+fn gravity(
+    time: Res<Time>,
+    physics: Res<PhysicsConfig>,
+    mut query: Query<(&mut Flappy, &mut Position)>,
 ) {
-    for y in -12..=12 {
-        if y < gap_y - 4 || y > gap_y + 4 {
-            commands
-                .spawn(SpriteBundle {
-                    texture: wall_sprite.clone(),
-                    transform: Transform::from_xyz(512.0, y as f32 * 32.0, 1.0),
-                    ..default()
-                })
-                .insert(Obstacle)
-                .insert(FlappyElement);
+    if let Ok((mut flappy, mut position)) = query.get_single_mut() {
+        (position.current.y, flappy.velocity) =
+            physics.fall(position.current.y, flappy.velocity, time.delta_seconds());
+    }
+}
+
+/// F2 hands the dragon to the rule-based autopilot, then to the trained
+/// one (if `train` has saved one), then back to the player.
+fn toggle_autopilot(
+    actions: Res<ActionState<FlappyAction>>,
+    trained: Res<TrainedPilot>,
+    mut autopilot: ResMut<Autopilot>,
+) {
+    if !actions.just_pressed(FlappyAction::ToggleAutopilot) {
+        return;
     }
-  }
+    *autopilot = match *autopilot {
+        Autopilot::Off => Autopilot::Rules,
+        Autopilot::Rules if trained.0.is_some() => Autopilot::Trained,
+        Autopilot::Rules | Autopilot::Trained => Autopilot::Off,
+    };
+    info!("Autopilot: {:?}", *autopilot);
 }
 
-fn gravity(mut query: Query<(&mut Flappy, &mut Transform)>) {
-    if let Ok((mut flappy, mut transform)) = query.get_single_mut() {
-        flappy.gravity += 0.1;
-        transform.translation.y -= flappy.gravity;
+/// Lets the autopilot flap, if it is flying.
+fn fly_autopilot(
+    autopilot: Res<Autopilot>,
+    trained: Res<TrainedPilot>,
+    physics: Res<PhysicsConfig>,
+    spawner: Res<WallSpawner>,
+    mut flight: ResMut<Flight>,
+    mut query: Query<(&mut Flappy, &mut SpriteAnimation, &Position)>,
+) {
+    let rules = RuleBased::default();
+    let pilot: &dyn Pilot = match (*autopilot, &trained.0) {
+        (Autopilot::Rules, _) => &rules,
+        (Autopilot::Trained, Some(net)) => net,
+        _ => return,
+    };
+    if !flight.autopiloted {
+        flight.autopiloted = true;
+    }
+    if let Ok((mut flappy, mut animation, position)) = query.get_single_mut() {
+        let observation = Observation::new(position.current.y, flappy.velocity, &spawner.schedule);
+        if pilot.flap(&observation) {
+            flappy.velocity = physics.flap_velocity;
+            animation.play("flap");
+        }
     }
 }
 
-fn flap(keyboard: Res<ButtonInput<KeyCode>>, mut query: Query<&mut Flappy>) {
-    if keyboard.pressed(KeyCode::Space) {
-        if let Ok(mut flappy) = query.get_single_mut() {
-            flappy.gravity = -5.0;
+fn flap(
+    actions: Res<ActionState<FlappyAction>>,
+    physics: Res<PhysicsConfig>,
+    mut query: Query<(&mut Flappy, &mut SpriteAnimation)>,
+) {
+    if actions.pressed(FlappyAction::Flap) {
+        if let Ok((mut flappy, mut animation)) = query.get_single_mut() {
+            flappy.velocity = physics.flap_velocity;
+            animation.play("flap");
         }
     }
 }
 
 fn clamp(
-    mut query: Query<&mut Transform, With<Flappy>>,
+    mut query: Query<&mut Position, With<Flappy>>,
     mut state: ResMut<NextState<GamePhase>>,
 ) {
-    if let Ok(mut transform) = query.get_single_mut() {
-        if transform.translation.y > 384.0 {
-            transform.translation.y = 384.0;
-        } else if transform.translation.y < -384.0 {
+    if let Ok(mut position) = query.get_single_mut() {
+        if position.current.y > CEILING {
+            position.current.y = CEILING;
+        } else if position.current.y < FLOOR {
             state.set(GamePhase::GameOver);
         }
     }
 }
 
-fn move_walls(
-    mut commands: Commands,
-    mut query: Query<&mut Transform, With<Obstacle>>,
-    delete: Query<Entity, With<Obstacle>>,
-    assets: Res<Assets>,
-    rng: Res<RandomNumberGenerator>,
+fn show_score(
+    flight: Res<Flight>,
+    autopilot: Res<Autopilot>,
+    course: Res<Course>,
+    mut text: Query<&mut Text, With<ScoreText>>,
 ) {
-    let mut rebuild = false;
-    for mut transform in query.iter_mut() {
-        transform.translation.x -= 4.0;
-        if transform.translation.x < -530.0 {
-            rebuild = true;
-        }
+    if !flight.is_changed() && !autopilot.is_changed() {
+        return;
     }
-    if rebuild {
-        for entity in delete.iter() {
-            commands.entity(entity).despawn();
-        }
-        build_wall(&mut commands, assets.wall.clone(), rng.range(-5..5));
+    let pilot = match *autopilot {
+        Autopilot::Off => "",
+        Autopilot::Rules => "  (autopilot)",
+        Autopilot::Trained => "  (trained autopilot)",
+    };
+    let daily = course.day.map(|day| format!("Daily {}  ", challenge::date(day))).unwrap_or_default();
+    for mut text in text.iter_mut() {
+        text.sections[0].value = format!("{daily}Score: {}{pilot}", flight.score);
     }
 }
 
+/// The dragon and walls only collide with each other, so any collision
+/// ends the game.
 fn hit_wall(
-    player: Query<&Transform, With<Flappy>>,
-    walls: Query<&Transform, With<Obstacle>>,
+    mut collisions: EventReader<CollisionEvent>,
     mut state: ResMut<NextState<GamePhase>>,
 ) {
-    if let Ok(player) = player.get_single() {
-        for wall in walls.iter() {
-            let distance = player.translation.distance(wall.translation);
-            if distance < 32.0 {
-                state.set(GamePhase::GameOver);
-            }
+    if collisions.read().count() > 0 {
+        state.set(GamePhase::GameOver);
+    }
+}
+
+fn toggle_collision_debug(
+    actions: Res<ActionState<FlappyAction>>,
+    mut debug: ResMut<CollisionDebug>,
+) {
+    if actions.just_pressed(FlappyAction::ToggleCollisionDebug) {
+        debug.0 = !debug.0;
+    }
+}
+
+fn time_flight(time: Res<Time>, mut flight: ResMut<Flight>) {
+    flight.seconds += time.delta_seconds();
+}
+
+fn publish_results(
+    mut commands: Commands,
+    flight: Res<Flight>,
+    course: Res<Course>,
+) {
+    let mut results = GameResults::new("Your dragon crashed!")
+        .line(format!("Score: {}", flight.score))
+        .line(format!("Time in the air: {:.1} seconds", flight.seconds));
+    if let Some(day) = course.day {
+        results = results.line(format!("Daily challenge: {}", challenge::date(day)));
+    }
+    commands.insert_resource(results);
+    if flight.score > 0 && !flight.autopiloted {
+        commands.insert_resource(NewScore { score: flight.score, seed: course.seed });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use my_library::testing::TestApp;
+
+    #[test]
+    fn test_crash_ends_the_game() {
+        let mut game = TestApp::new(1, add_game);
+        game.update().assert_state(GamePhase::MainMenu);
+        game.tap(KeyCode::KeyP).run_until_state(GamePhase::Flapping, 120);
+        game.assert_count::<Flappy>(1);
+        game.press(KeyCode::Space).advance_fixed(1).release(KeyCode::Space);
+        let mut dragon = game.app.world_mut().query::<(&SpriteAnimation, &TextureAtlas)>();
+        let (animation, atlas) = dragon.single(game.app.world());
+        assert_eq!(animation.clip(), "flap");
+        let layouts = game.resource::<bevy::asset::Assets<TextureAtlasLayout>>();
+        assert!(dragon_animations().check(layouts.get(&atlas.layout).unwrap()).is_ok());
+
+        // Without flapping, the dragon falls off the bottom of the screen.
+        game.run_until_state(GamePhase::GameOver, 600);
+        let results = game.resource::<GameResults>();
+        assert_eq!(results.title, "Your dragon crashed!");
+        assert!(!game.has_resource::<Flight>());
+
+        game.advance_frames(60).assert_count::<Flappy>(0);
+    }
+
+    #[test]
+    fn test_rebinding_keys_dont_leak_into_the_menu() {
+        let mut game = TestApp::new(1, add_game);
+        game.update().tap(KeyCode::F1);
+        assert_eq!(game.resource::<Rebinding<FlappyAction>>().0, Some(FlappyAction::Flap));
+
+        // P would start the game, but here it only becomes the flap key.
+        game.press(KeyCode::KeyP).advance_frames(3);
+        assert!(!game.resource::<ActionState<FlappyAction>>().pressed(FlappyAction::Flap));
+        game.release(KeyCode::KeyP).advance_frames(30).assert_state(GamePhase::MainMenu);
+        let bindings = game.resource::<InputMap<FlappyAction>>().bindings(FlappyAction::Flap);
+        assert!(bindings.contains(&InputBinding::Key(KeyCode::KeyP)));
+
+        // Once it has been let go, it flaps like any other binding.
+        game.press(KeyCode::KeyP).update();
+        assert!(game.resource::<ActionState<FlappyAction>>().pressed(FlappyAction::Flap));
+    }
+
+    #[test]
+    fn test_falls_in_units_per_second() {
+        let mut game = TestApp::new(1, add_game);
+        game.update().tap(KeyCode::KeyP).run_until_state(GamePhase::Flapping, 120);
+        let steps_per_second = 1.0 / game.resource::<Time<Fixed>>().timestep().as_secs_f32();
+        let dragon = |game: &mut TestApp| {
+            let mut query = game.app.world_mut().query::<(&Flappy, &Position)>();
+            let (flappy, position) = query.single(game.app.world());
+            (flappy.velocity, position.current.y)
+        };
+        let (velocity, start) = dragon(&mut game);
+
+        game.advance_fixed(steps_per_second as u64);
+        let (_, end) = dragon(&mut game);
+        let expected = 0.5 * PhysicsConfig::default().gravity - velocity;
+        assert!((start - end - expected).abs() < 5.0, "fell {} in one second", start - end);
+    }
+
+    #[test]
+    fn test_wall_tiles_are_recycled() {
+        let mut game = TestApp::new(1, add_game);
+        game.update().tap(KeyCode::KeyP).run_until_state(GamePhase::Flapping, 120);
+        // A dragon that neither falls nor collides lets the walls run.
+        game.app.insert_resource(PhysicsConfig { gravity: 0.0, flap_velocity: 0.0 });
+        let mut dragon = game.app.world_mut().query_filtered::<Entity, With<Flappy>>();
+        let dragon = dragon.single(game.app.world());
+        game.app.world_mut().entity_mut(dragon).remove::<Collider>();
+        game.app.world_mut().get_mut::<Flappy>(dragon).unwrap().velocity = 0.0;
+
+        game.advance_fixed(2);
+        let mut tiles = game.app.world_mut().query_filtered::<Entity, With<spawner::Obstacle>>();
+        let first_wall: Vec<Entity> = tiles.iter(game.app.world()).collect();
+        assert!(!first_wall.is_empty());
+
+        game.advance_fixed(64 * 30);
+        game.assert_state(GamePhase::Flapping);
+        assert!(game.resource::<Flight>().score >= 5);
+        // The first wall is long gone, but its tiles are back in play.
+        let reused = tiles.iter(game.app.world()).filter(|tile| first_wall.contains(tile)).count();
+        assert!(reused > 0);
+    }
+
+    #[test]
+    fn test_autopilot_matches_simulation() {
+        let mut game = TestApp::new(7, add_game);
+        game.update().tap(KeyCode::F2).assert_state(GamePhase::MainMenu);
+        assert_eq!(*game.resource::<Autopilot>(), Autopilot::Rules);
+        game.tap(KeyCode::KeyP).run_until_state(GamePhase::Flapping, 120);
+        game.advance_fixed(64 * 20);
+
+        // Fly the same course outside the game, for as many ticks as the
+        // game has run while flapping.
+        let ticks = (game.resource::<Flight>().seconds * TICKS_PER_SECOND as f32).round() as u32;
+        let seed = game.resource::<Course>().seed;
+        let mut simulation = flappy::simulation::Simulation::new(seed, 1);
+        for _ in 0..ticks {
+            simulation.step(&[RuleBased::default()]);
         }
+        let mut dragon = game.app.world_mut().query_filtered::<&Position, With<Flappy>>();
+        let y = dragon.single(game.app.world()).current.y;
+        assert!(game.resource::<Flight>().score > 0);
+        assert_eq!(game.resource::<Flight>().score, simulation.score());
+        assert_eq!(y, simulation.dragons()[0].y);
+    }
+
+    #[test]
+    fn test_free_flights_leave_no_ghost() {
+        let mut game = TestApp::new(3, add_game);
+        game.update().tap(KeyCode::KeyP).run_until_state(GamePhase::Flapping, 120);
+        let seed = game.resource::<Course>().seed;
+        game.run_until_state(GamePhase::GameOver, 600);
+        assert!(game.resource::<Ghosts>().get(seed).is_none());
+    }
+
+    #[test]
+    fn test_daily_challenge_ghost() {
+        let mut game = TestApp::new(3, add_game);
+        game.update().tap(KeyCode::KeyD).run_until_state(GamePhase::Flapping, 120);
+        let day = challenge::today();
+        assert_eq!(game.resource::<Course>().seed, challenge::daily_seed(day));
+        // The course has its own generator, and leaves the game's alone.
+        assert_eq!(game.resource::<RandomNumberGenerator>().seed(), 3);
+        game.assert_count::<ghost::Ghost>(0);
+
+        // Fall, then try again: the first flight comes back as a ghost.
+        game.run_until_state(GamePhase::GameOver, 600);
+        let first = game.resource::<Ghosts>().get(challenge::daily_seed(day)).unwrap().clone();
+        assert!(!first.heights.is_empty());
+        game.advance_frames(60).tap(KeyCode::KeyR).run_until_state(GamePhase::Flapping, 120);
+        assert_eq!(game.resource::<Course>().day, Some(day));
+        game.assert_count::<ghost::Ghost>(1);
+
+        // The ghost falls exactly as the dragon does...
+        game.advance_fixed(first.heights.len() as u64 - 10);
+        let mut heights = game.app.world_mut()
+            .query_filtered::<&Position, Or<(With<Flappy>, With<ghost::Ghost>)>>();
+        let heights: Vec<f32> = heights.iter(game.app.world()).map(|p| p.current.y).collect();
+        assert_eq!(heights.len(), 2);
+        assert_eq!(heights[0], heights[1]);
+
+        // ...and vanishes where it crashed, while this flight carries on.
+        game.press(KeyCode::Space).advance_fixed(12);
+        game.assert_state(GamePhase::Flapping);
+        game.assert_count::<ghost::Ghost>(0);
     }
 }
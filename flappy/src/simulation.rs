@@ -0,0 +1,144 @@
+//! Flappy Dragon without Bevy: the same physics, walls and collisions as
+//! the game, fast enough to fly hundreds of dragons at once.
+use crate::ai::{Observation, Pilot};
+use crate::difficulty::DifficultyCurve;
+use crate::walls::{WallSchedule, TILE_SIZE};
+use crate::{dragon_collider, PhysicsConfig, CEILING, DRAGON_X, FLOOR, TICKS_PER_SECOND};
+use bevy::math::Vec2;
+use my_library::{Collider, RandomNumberGenerator};
+
+/// One dragon in a simulation.
+#[derive(Clone, Debug, Default)]
+pub struct Dragon {
+    pub y: f32,
+    pub velocity: f32,
+    pub alive: bool,
+    /// How many ticks it has been flying.
+    pub ticks: u32,
+    /// How many walls it got past.
+    pub score: u32,
+    /// How far the middle of the next gap was above it when it crashed.
+    pub miss: f32,
+}
+
+/// Many dragons flying through the same walls. Every dragon is at the
+/// same x position, so they never get in each other's way.
+pub struct Simulation {
+    physics: PhysicsConfig,
+    curve: DifficultyCurve,
+    rng: RandomNumberGenerator,
+    walls: WallSchedule,
+    score: u32,
+    dragons: Vec<Dragon>,
+}
+
+impl Simulation {
+    /// A new flight for `dragons` dragons. The walls depend only on
+    /// `seed`, and match the game's walls for the same seed.
+    pub fn new(seed: u64, dragons: usize) -> Self {
+        Self {
+            physics: PhysicsConfig::default(),
+            curve: DifficultyCurve::default(),
+            rng: RandomNumberGenerator::seeded(seed),
+            walls: WallSchedule::default(),
+            score: 0,
+            dragons: vec![Dragon { alive: true, ..Dragon::default() }; dragons],
+        }
+    }
+
+    pub fn dragons(&self) -> &[Dragon] {
+        &self.dragons
+    }
+
+    /// How many walls the dragons still flying have got past.
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    /// How many dragons are still flying.
+    pub fn alive(&self) -> usize {
+        self.dragons.iter().filter(|dragon| dragon.alive).count()
+    }
+
+    /// Advances one tick, in the same order as the game's systems:
+    /// flapping, gravity, the edges of the screen, walls, then collisions.
+    /// `pilots[i]` flies dragon `i`.
+    pub fn step<P: Pilot>(&mut self, pilots: &[P]) {
+        let delta = (1.0 / TICKS_PER_SECOND) as f32;
+        for (dragon, pilot) in self.dragons.iter_mut().zip(pilots) {
+            if !dragon.alive {
+                continue;
+            }
+            let observation = Observation::new(dragon.y, dragon.velocity, &self.walls);
+            if pilot.flap(&observation) {
+                dragon.velocity = self.physics.flap_velocity;
+            }
+            (dragon.y, dragon.velocity) = self.physics.fall(dragon.y, dragon.velocity, delta);
+            dragon.ticks += 1;
+            if dragon.y > CEILING {
+                dragon.y = CEILING;
+            } else if dragon.y < FLOOR {
+                dragon.alive = false;
+                dragon.miss = observation.gap;
+            }
+        }
+
+        let difficulty = self.curve.at(self.score);
+        self.score += self.walls.scroll(difficulty.scroll_speed * delta);
+        self.walls.spawn(&difficulty, &self.rng);
+
+        let dragon_shape = dragon_collider();
+        let tile = Collider::rectangle(TILE_SIZE, TILE_SIZE);
+        let near: Vec<Vec2> = self
+            .walls
+            .columns()
+            .filter(|column| (column.x - DRAGON_X).abs() < TILE_SIZE * 2.0)
+            .flat_map(|column| column.tiles())
+            .collect();
+        for dragon in self.dragons.iter_mut().filter(|dragon| dragon.alive) {
+            let position = Vec2::new(DRAGON_X, dragon.y);
+            if near.iter().any(|&tile_position| dragon_shape.overlaps(position, &tile, tile_position)) {
+                dragon.alive = false;
+                dragon.miss = Observation::new(dragon.y, dragon.velocity, &self.walls).gap;
+            }
+            if dragon.alive {
+                dragon.score = self.score;
+            }
+        }
+    }
+
+    /// Steps until every dragon has crashed, or `max_ticks` ticks have
+    /// passed.
+    pub fn run<P: Pilot>(&mut self, pilots: &[P], max_ticks: u32) {
+        for _ in 0..max_ticks {
+            if self.alive() == 0 {
+                break;
+            }
+            self.step(pilots);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Never;
+
+    impl Pilot for Never {
+        fn flap(&self, _: &Observation) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_dragons_fall_without_flapping() {
+        let mut simulation = Simulation::new(1, 3);
+        simulation.run(&[Never, Never, Never], 64 * 10);
+        assert_eq!(simulation.alive(), 0);
+        // Falling 384 pixels at 360 pixels per second squared takes a
+        // little under a second and a half.
+        let ticks = simulation.dragons()[0].ticks;
+        assert!((88..=96).contains(&ticks), "fell for {ticks} ticks");
+    }
+}
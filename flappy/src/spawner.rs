@@ -0,0 +1,113 @@
+use crate::{Assets, Course, Flight, Position, DRAGON_GROUP, WALL_GROUP};
+use bevy::prelude::*;
+use flappy::difficulty::DifficultyCurve;
+use flappy::walls::{gap_offset, Column, WallSchedule, TILE_SIZE, WALL_RECYCLE_X};
+use my_library::*;
+
+/// A wall tile in play. Tiles waiting in the pool don't have it.
+#[derive(Component)]
+pub struct Obstacle;
+
+/// A tile in a wall whose gap moves up and down. `base_y` is where the
+/// tile would be if the gap were still.
+#[derive(Component)]
+pub struct MovingGap {
+    base_y: f32,
+}
+
+/// Builds walls as the [`WallSchedule`] calls for them. Tiles that scroll
+/// off the left are hidden and put in a pool, and new walls reuse them
+/// before spawning more.
+#[derive(Resource, Default)]
+pub struct WallSpawner {
+    pub schedule: WallSchedule,
+    /// Hidden tiles, ready to reuse.
+    pool: Vec<Entity>,
+}
+
+impl WallSpawner {
+    /// Shows a wall tile, reusing a hidden one if there is one.
+    fn place_tile(&mut self, commands: &mut Commands, sprite: &Handle<Image>, position: Vec2, base_y: f32, moving: bool) {
+        let tile = (
+            Obstacle,
+            Transform::from_translation(position.extend(1.0)),
+            Visibility::Visible,
+            Position::new(position.x, position.y),
+            Collider::rectangle(TILE_SIZE, TILE_SIZE),
+            CollisionGroups::new(WALL_GROUP, DRAGON_GROUP),
+        );
+        let mut entity = match self.pool.pop() {
+            Some(entity) => commands.entity(entity),
+            None => commands.spawn(SpriteBundle { texture: sprite.clone(), ..default() }),
+        };
+        entity.insert(tile);
+        if moving {
+            entity.insert(MovingGap { base_y });
+        } else {
+            entity.remove::<MovingGap>();
+        }
+    }
+
+    /// Hides a tile and keeps it for later. Without a collider, it can't
+    /// be hit while it waits.
+    fn recycle(&mut self, commands: &mut Commands, entity: Entity) {
+        commands
+            .entity(entity)
+            .remove::<(Obstacle, Collider, MovingGap)>()
+            .insert(Visibility::Hidden);
+        self.pool.push(entity);
+    }
+
+    /// Builds a column of tiles.
+    fn build(&mut self, commands: &mut Commands, sprite: &Handle<Image>, column: &Column) {
+        let moving = column.plan.moving();
+        for (y, position) in column.plan.tiles().zip(column.tiles()) {
+            self.place_tile(commands, sprite, position, y as f32 * TILE_SIZE, moving);
+        }
+    }
+}
+
+/// Scrolls the walls, scores the ones the dragon gets past, and recycles
+/// the ones that have left the screen.
+pub fn scroll_walls(
+    mut commands: Commands,
+    time: Res<Time>,
+    curve: Res<DifficultyCurve>,
+    mut spawner: ResMut<WallSpawner>,
+    mut query: Query<(Entity, &mut Position), With<Obstacle>>,
+    mut flight: ResMut<Flight>,
+) {
+    let step = curve.at(flight.score).scroll_speed * time.delta_seconds();
+    for (entity, mut position) in query.iter_mut() {
+        position.current.x -= step;
+        if position.current.x < WALL_RECYCLE_X {
+            spawner.recycle(&mut commands, entity);
+        }
+    }
+    let passed = spawner.schedule.scroll(step);
+    if passed > 0 {
+        flight.score += passed;
+    }
+}
+
+/// Builds the next planned wall once there is room for it.
+pub fn spawn_walls(
+    mut commands: Commands,
+    curve: Res<DifficultyCurve>,
+    mut spawner: ResMut<WallSpawner>,
+    assets: Res<Assets>,
+    course: Res<Course>,
+    flight: Res<Flight>,
+) {
+    let columns = spawner.schedule.spawn(&curve.at(flight.score), &course.walls);
+    for column in &columns {
+        spawner.build(&mut commands, &assets.wall, column);
+    }
+}
+
+/// Moves the gaps of moving walls up and down.
+pub fn move_gaps(mut query: Query<(&MovingGap, &mut Position), With<Obstacle>>) {
+    for (gap, mut position) in query.iter_mut() {
+        position.current.y = gap.base_y + gap_offset(position.current.x);
+    }
+}
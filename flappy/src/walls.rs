@@ -0,0 +1,303 @@
+//! Planning walls and keeping track of them as they scroll past.
+use crate::difficulty::Difficulty;
+use crate::{DRAGON_X, FLOOR};
+use bevy::math::Vec2;
+use my_library::RandomNumberGenerator;
+use std::collections::VecDeque;
+
+/// New walls appear here, at the right edge of the screen.
+pub const WALL_SPAWN_X: f32 = 512.0;
+/// Walls are dropped once they are past the left edge.
+pub const WALL_RECYCLE_X: f32 = -530.0;
+/// Walls are made of tiles, from -`WALL_TILES` to `WALL_TILES`.
+pub const WALL_TILES: i32 = 12;
+/// The width and height of a wall tile, in pixels.
+pub const TILE_SIZE: f32 = 32.0;
+/// How many walls are planned ahead of the one on its way in.
+const QUEUE_LENGTH: usize = 3;
+/// The distance between the two walls of a double wall, in pixels.
+const DOUBLE_WALL_OFFSET: f32 = 4.0 * TILE_SIZE;
+/// How far a moving gap travels up and down, in tiles.
+const MOVING_GAP_AMPLITUDE: i32 = 3;
+/// How far a moving gap's wall scrolls while the gap goes up and down
+/// once, in pixels.
+const MOVING_GAP_WAVELENGTH: f32 = 640.0;
+
+/// The kinds of obstacle the spawner can build.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WallPattern {
+    /// A wall with a gap in it.
+    Single,
+    /// A wall whose gap moves up and down as it scrolls.
+    MovingGap,
+    /// Two walls close together, with the gap in the same place.
+    DoubleWall,
+    /// Spikes hanging from the ceiling down to the top of the gap, with
+    /// nothing below.
+    CeilingHazard,
+}
+
+/// A wall waiting to be built.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WallPlan {
+    pub pattern: WallPattern,
+    /// The middle of the gap, in tiles from the middle of the screen.
+    pub gap_y: i32,
+    /// The height of the gap, in tiles.
+    pub gap_size: i32,
+    /// The distance from the previous wall, in pixels.
+    pub spacing: f32,
+}
+
+impl WallPlan {
+    /// Plans a wall for the given difficulty.
+    pub fn new(difficulty: &Difficulty, rng: &RandomNumberGenerator) -> Self {
+        let roll = rng.range(0.0..1.0);
+        let pattern = if roll < difficulty.moving_gap {
+            WallPattern::MovingGap
+        } else if roll < difficulty.moving_gap + difficulty.double_wall {
+            WallPattern::DoubleWall
+        } else if roll < difficulty.moving_gap + difficulty.double_wall + difficulty.ceiling_hazard {
+            WallPattern::CeilingHazard
+        } else {
+            WallPattern::Single
+        };
+
+        let gap_size = (difficulty.gap_size.round() as i32).clamp(1, WALL_TILES * 2);
+        let variance = difficulty.gap_variance.round() as i32;
+        let gap_y = match pattern {
+            // Low enough that the spikes are in the way.
+            WallPattern::CeilingHazard => -rng.range(0..=variance),
+            _ => rng.range(-variance..=variance),
+        };
+        // Keep the whole gap on screen, wherever it moves to.
+        let margin = if pattern == WallPattern::MovingGap { MOVING_GAP_AMPLITUDE } else { 0 };
+        let lowest = -WALL_TILES + gap_size / 2 + margin;
+        let highest = WALL_TILES - gap_size + 1 + gap_size / 2 - margin;
+        let gap_y = gap_y.clamp(lowest, highest.max(lowest));
+        Self { pattern, gap_y, gap_size, spacing: difficulty.wall_spacing }
+    }
+
+    /// The tiles in one column of this wall, in tiles from the middle of
+    /// the screen.
+    pub fn tiles(&self) -> impl Iterator<Item = i32> + '_ {
+        let (gap_start, gap_end) = self.gap();
+        // A moving wall needs extra tiles, so no hole opens at either end.
+        let extra = if self.moving() { MOVING_GAP_AMPLITUDE } else { 0 };
+        (-WALL_TILES - extra..=WALL_TILES + extra).filter(move |&y| match self.pattern {
+            WallPattern::CeilingHazard => y >= gap_end,
+            _ => y < gap_start || y >= gap_end,
+        })
+    }
+
+    /// The first tile of the gap, and the first tile above it.
+    fn gap(&self) -> (i32, i32) {
+        let gap_start = self.gap_y - self.gap_size / 2;
+        (gap_start, gap_start + self.gap_size)
+    }
+
+    /// Does the gap move up and down?
+    pub fn moving(&self) -> bool {
+        self.pattern == WallPattern::MovingGap
+    }
+
+    /// The x offset of each column of this wall from the first.
+    pub fn columns(&self) -> &'static [f32] {
+        match self.pattern {
+            WallPattern::DoubleWall => &[0.0, DOUBLE_WALL_OFFSET],
+            _ => &[0.0],
+        }
+    }
+
+    /// How far this wall extends to the right of where it starts.
+    fn width(&self) -> f32 {
+        self.columns().last().copied().unwrap_or(0.0)
+    }
+}
+
+/// How far a moving gap is from where it started, at horizontal position
+/// `x`. Every tile in a wall shares `x`, so the whole wall moves together.
+pub fn gap_offset(x: f32) -> f32 {
+    let amplitude = MOVING_GAP_AMPLITUDE as f32 * TILE_SIZE;
+    amplitude * (x / MOVING_GAP_WAVELENGTH * std::f32::consts::TAU).sin()
+}
+
+/// One column of wall tiles on screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Column {
+    pub x: f32,
+    pub plan: WallPlan,
+    /// Is this the wall's first column? Only it scores.
+    pub first: bool,
+}
+
+impl Column {
+    /// How far the tiles are from where the plan put them.
+    fn offset(&self) -> f32 {
+        if self.plan.moving() { gap_offset(self.x) } else { 0.0 }
+    }
+
+    /// Where each tile in the column is now.
+    pub fn tiles(&self) -> impl Iterator<Item = Vec2> + '_ {
+        let offset = self.offset();
+        self.plan.tiles().map(move |y| Vec2::new(self.x, y as f32 * TILE_SIZE + offset))
+    }
+
+    /// The middle of the space the dragon can fly through, and its
+    /// height, in pixels. Below a ceiling hazard, that space goes all the
+    /// way to the floor.
+    pub fn gap(&self) -> (f32, f32) {
+        let (gap_start, gap_end) = self.plan.gap();
+        let top = (gap_end as f32 - 0.5) * TILE_SIZE + self.offset();
+        let bottom = match self.plan.pattern {
+            WallPattern::CeilingHazard => FLOOR,
+            _ => (gap_start as f32 - 0.5) * TILE_SIZE + self.offset(),
+        };
+        ((top + bottom) / 2.0, top - bottom)
+    }
+}
+
+/// Plans walls ahead of time and decides where and when each is built,
+/// then follows the columns as they scroll. It doesn't touch any
+/// entities, so the game and the training simulation share it.
+#[derive(Clone, Debug, Default)]
+pub struct WallSchedule {
+    /// The walls to build, in order.
+    queue: VecDeque<WallPlan>,
+    /// How much further the walls must scroll before the next one is
+    /// built, in pixels.
+    distance_to_next: f32,
+    /// The columns on screen, from left to right.
+    columns: VecDeque<Column>,
+}
+
+impl WallSchedule {
+    /// Moves every column `step` pixels left and drops the ones that have
+    /// left the screen. Returns how many walls the dragon got past.
+    pub fn scroll(&mut self, step: f32) -> u32 {
+        let mut passed = 0;
+        for column in self.columns.iter_mut() {
+            let before = column.x;
+            column.x -= step;
+            if column.first && before >= DRAGON_X && column.x < DRAGON_X {
+                passed += 1;
+            }
+        }
+        while self.columns.front().is_some_and(|column| column.x < WALL_RECYCLE_X) {
+            self.columns.pop_front();
+        }
+        self.distance_to_next -= step;
+        passed
+    }
+
+    /// Keeps the queue full and, once there is room for the next wall,
+    /// returns the columns to build for it.
+    pub fn spawn(&mut self, difficulty: &Difficulty, rng: &RandomNumberGenerator) -> Vec<Column> {
+        while self.queue.len() < QUEUE_LENGTH {
+            self.queue.push_back(WallPlan::new(difficulty, rng));
+        }
+        if self.distance_to_next > 0.0 {
+            return Vec::new();
+        }
+        let Some(plan) = self.queue.pop_front() else {
+            return Vec::new();
+        };
+        // Walls have scrolled a little past the point where this one was due.
+        let x = WALL_SPAWN_X + self.distance_to_next;
+        self.queue.push_back(WallPlan::new(difficulty, rng));
+        let next = self.queue.front().map_or(difficulty.wall_spacing, |next| next.spacing);
+        self.distance_to_next += plan.width() + next;
+
+        let built: Vec<Column> = plan
+            .columns()
+            .iter()
+            .map(|&offset| Column { x: x + offset, plan, first: offset == 0.0 })
+            .collect();
+        self.columns.extend(built.iter().copied());
+        built
+    }
+
+    /// The columns on screen, from left to right.
+    pub fn columns(&self) -> impl Iterator<Item = &Column> {
+        self.columns.iter()
+    }
+
+    /// The walls that will be built next, in order.
+    pub fn upcoming(&self) -> impl Iterator<Item = &WallPlan> {
+        self.queue.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::DifficultyCurve;
+
+    fn difficulty() -> Difficulty {
+        DifficultyCurve::default().at(0)
+    }
+
+    #[test]
+    fn test_patterns_follow_chances() {
+        let rng = RandomNumberGenerator::seeded(1);
+        let plain = WallPlan::new(&difficulty(), &rng);
+        assert_eq!(plain.pattern, WallPattern::Single);
+
+        let doubles = Difficulty { double_wall: 1.0, ..difficulty() };
+        assert_eq!(WallPlan::new(&doubles, &rng).pattern, WallPattern::DoubleWall);
+        let hazards = Difficulty { ceiling_hazard: 1.0, ..difficulty() };
+        assert_eq!(WallPlan::new(&hazards, &rng).pattern, WallPattern::CeilingHazard);
+    }
+
+    #[test]
+    fn test_gaps_stay_on_screen() {
+        let rng = RandomNumberGenerator::seeded(2);
+        let moving = Difficulty { moving_gap: 1.0, gap_variance: 20.0, ..difficulty() };
+        for _ in 0..100 {
+            let plan = WallPlan::new(&moving, &rng);
+            let top = plan.gap_y - plan.gap_size / 2 + plan.gap_size + MOVING_GAP_AMPLITUDE;
+            let bottom = plan.gap_y - plan.gap_size / 2 - MOVING_GAP_AMPLITUDE;
+            assert!(bottom >= -WALL_TILES && top <= WALL_TILES + 1, "{plan:?}");
+        }
+    }
+
+    #[test]
+    fn test_ceiling_hazard_has_no_floor() {
+        let rng = RandomNumberGenerator::seeded(3);
+        let hazards = Difficulty { ceiling_hazard: 1.0, ..difficulty() };
+        let plan = WallPlan::new(&hazards, &rng);
+        assert!(plan.tiles().all(|y| y > plan.gap_y));
+        assert_eq!(plan.tiles().max(), Some(WALL_TILES));
+    }
+
+    #[test]
+    fn test_schedule_spaces_walls() {
+        let rng = RandomNumberGenerator::seeded(4);
+        let difficulty = difficulty();
+        let mut schedule = WallSchedule::default();
+        assert_eq!(schedule.spawn(&difficulty, &rng).len(), 1);
+        assert_eq!(schedule.upcoming().count(), QUEUE_LENGTH);
+
+        let mut scrolled = 0.0;
+        while schedule.spawn(&difficulty, &rng).is_empty() {
+            schedule.scroll(10.0);
+            scrolled += 10.0;
+        }
+        assert_eq!(scrolled, difficulty.wall_spacing);
+        assert_eq!(schedule.columns().count(), 2);
+    }
+
+    #[test]
+    fn test_double_walls_score_once() {
+        let rng = RandomNumberGenerator::seeded(5);
+        let doubles = Difficulty { double_wall: 1.0, ..difficulty() };
+        let mut schedule = WallSchedule::default();
+        assert_eq!(schedule.spawn(&doubles, &rng).len(), 2);
+
+        let mut passed = 0;
+        while schedule.columns().any(|column| column.x >= DRAGON_X) {
+            passed += schedule.scroll(10.0);
+        }
+        assert_eq!(passed, 1);
+    }
+}
@@ -1,45 +1,79 @@
 use bevy::prelude::*;
+use my_library::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+enum DragonAction {
+    MoveX,
+    MoveY,
+}
 
 #[derive(Component)]
 struct Dragon;
 
+/// The dragon sheet has four frames, from wings up to wings down. The
+/// dragon beats its wings while it moves and glides when it stops.
+fn dragon_animations() -> AnimationLibrary {
+    AnimationLibrary::new()
+        .with("glide", SpriteClip::still(0))
+        .with("fly", SpriteClip::new(0..=3, 8.0, PlayMode::PingPong))
+}
+
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
 ) {
     commands.spawn(Camera2dBundle::default());
     commands
         .spawn(SpriteBundle {
-            texture: asset_server.load("dragon.png"),
+            texture: asset_server.load("flappy_dragon.png"),
+            transform: Transform::from_scale(Vec3::splat(3.0)),
             ..Default::default()
         })
+        .insert(TextureAtlas {
+            layout: layouts.add(TextureAtlasLayout::from_grid(UVec2::new(65, 45), 4, 1, None, None)),
+            index: 0,
+        })
+        .insert(SpriteAnimation::new(Arc::new(dragon_animations()), "glide"))
         .insert(Dragon);
 }
 
+fn bindings() -> InputMap<DragonAction> {
+    InputMap::new()
+        .bind(DragonAction::MoveX, InputBinding::KeyAxis {
+            negative: KeyCode::ArrowLeft,
+            positive: KeyCode::ArrowRight,
+        })
+        .bind(DragonAction::MoveX, InputBinding::GamepadAxis(GamepadAxisType::LeftStickX))
+        .bind(DragonAction::MoveY, InputBinding::KeyAxis {
+            negative: KeyCode::ArrowDown,
+            positive: KeyCode::ArrowUp,
+        })
+        .bind(DragonAction::MoveY, InputBinding::GamepadAxis(GamepadAxisType::LeftStickY))
+}
+
 fn movement(
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mut dragon_query: Query<&mut Transform, With<Dragon>>,
+    actions: Res<ActionState<DragonAction>>,
+    mut dragon_query: Query<(&mut Transform, &mut SpriteAnimation), With<Dragon>>,
 ) {
-    let delta = if keyboard.pressed(KeyCode::ArrowLeft) {
-        Vec2::new(-1.0, 0.0)
-    } else if keyboard.pressed(KeyCode::ArrowRight) {
-        Vec2::new(1.0, 0.0)
-    } else if keyboard.pressed(KeyCode::ArrowDown) {
-        Vec2::new(0.0, -1.0)
-    } else if keyboard.pressed(KeyCode::ArrowUp) {
-        Vec2::new(0.0, 1.0)
-    } else {
-        Vec2::ZERO
-    };
-
-    dragon_query.iter_mut().for_each(|mut transform| {
+    let delta = Vec2::new(
+        actions.value(DragonAction::MoveX),
+        actions.value(DragonAction::MoveY),
+    );
+
+    dragon_query.iter_mut().for_each(|(mut transform, mut animation)| {
         transform.translation += delta.extend(0.0);
+        animation.play(if delta == Vec2::ZERO { "glide" } else { "fly" });
     });
 }
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
+        .add_plugins(ActionPlugin::new(bindings()))
+        .add_plugins(SpriteAnimationPlugin)
         .add_systems(Startup, setup)
         .add_systems(Update, movement)
         .run();
@@ -0,0 +1,344 @@
+//! Sprite animation over a texture atlas.
+//!
+//! Describe each animation as an [`SpriteClip`]: a range of frames in a
+//! [`TextureAtlasLayout`], a speed and a [`PlayMode`]. Collect the clips a
+//! sprite can play in an [`AnimationLibrary`], then give the sprite a
+//! [`SpriteAnimation`] to play them. [`SpriteAnimationPlugin`] advances every
+//! animation and sends [`AnimationFinished`] when a clip that plays once
+//! comes to an end.
+//!
+//! ```
+//! use my_library::*;
+//!
+//! let library = AnimationLibrary::new()
+//!     .with("glide", SpriteClip::new(0..=0, 1.0, PlayMode::Once))
+//!     .with("flap", SpriteClip::new(1..=3, 12.0, PlayMode::Once).then("glide"));
+//! let mut animation = SpriteAnimation::new(library.into(), "glide");
+//! animation.play("flap");
+//! assert_eq!(animation.atlas_index(), 1);
+//! ```
+use bevy::{prelude::*, utils::HashMap};
+use std::{ops::RangeInclusive, sync::Arc};
+
+/// What happens when a clip reaches its last frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayMode {
+    /// Start again from the first frame.
+    Loop,
+    /// Play backwards to the first frame, then forwards again.
+    PingPong,
+    /// Stop on the last frame, then move on to the clip's next clip, if it
+    /// has one.
+    Once,
+}
+
+/// A run of frames from a texture atlas, played at `fps` frames a second.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpriteClip {
+    /// The first atlas index in the clip.
+    pub first: usize,
+    /// The last atlas index in the clip.
+    pub last: usize,
+    pub fps: f32,
+    pub mode: PlayMode,
+    /// The clip to play once a [`PlayMode::Once`] clip finishes.
+    pub next: Option<String>,
+}
+
+impl SpriteClip {
+    pub fn new(frames: RangeInclusive<usize>, fps: f32, mode: PlayMode) -> Self {
+        Self { first: *frames.start(), last: *frames.end(), fps, mode, next: None }
+    }
+
+    /// A clip that shows one frame and stays there.
+    pub fn still(frame: usize) -> Self {
+        Self::new(frame..=frame, 1.0, PlayMode::Once)
+    }
+
+    /// Plays `clip` when this clip finishes.
+    pub fn then(mut self, clip: impl Into<String>) -> Self {
+        self.next = Some(clip.into());
+        self
+    }
+
+    fn len(&self) -> usize {
+        self.last.saturating_sub(self.first) + 1
+    }
+}
+
+/// The clips a sprite can play, by name. Share one library between every
+/// sprite that uses the same atlas.
+#[derive(Clone, Debug, Default)]
+pub struct AnimationLibrary {
+    clips: HashMap<String, SpriteClip>,
+}
+
+impl AnimationLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a clip called `name`.
+    pub fn with(mut self, name: impl Into<String>, clip: SpriteClip) -> Self {
+        self.clips.insert(name.into(), clip);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SpriteClip> {
+        self.clips.get(name)
+    }
+
+    /// Checks that every clip's frames are in `layout`, and that every
+    /// clip's next clip exists.
+    pub fn check(&self, layout: &TextureAtlasLayout) -> Result<(), String> {
+        for (name, clip) in self.clips.iter() {
+            if clip.first > clip.last || clip.last >= layout.len() {
+                return Err(format!(
+                    "clip {name} uses frames {}..={}, but the atlas has {} frames",
+                    clip.first,
+                    clip.last,
+                    layout.len()
+                ));
+            }
+            if let Some(next) = clip.next.as_ref().filter(|next| self.get(next).is_none()) {
+                return Err(format!("clip {name} is followed by {next}, which doesn't exist"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Plays clips from an [`AnimationLibrary`] on a sprite's [`TextureAtlas`].
+#[derive(Component, Clone, Debug)]
+pub struct SpriteAnimation {
+    library: Arc<AnimationLibrary>,
+    clip: String,
+    /// The current frame, counting from the clip's first frame.
+    frame: usize,
+    /// Time since the frame was shown, in seconds.
+    elapsed: f32,
+    reverse: bool,
+    finished: bool,
+    /// Played after the current clip finishes, instead of its own next
+    /// clip.
+    queued: Option<String>,
+    /// How fast to play: 2.0 is double speed.
+    pub speed: f32,
+}
+
+impl SpriteAnimation {
+    /// Starts playing `clip`.
+    pub fn new(library: Arc<AnimationLibrary>, clip: &str) -> Self {
+        let mut animation = Self {
+            library,
+            clip: String::new(),
+            frame: 0,
+            elapsed: 0.0,
+            reverse: false,
+            finished: false,
+            queued: None,
+            speed: 1.0,
+        };
+        animation.start(clip);
+        animation
+    }
+
+    fn start(&mut self, clip: &str) {
+        if self.library.get(clip).is_none() {
+            warn!("There is no animation clip called {clip}");
+        }
+        self.clip = clip.to_string();
+        self.frame = 0;
+        self.elapsed = 0.0;
+        self.reverse = false;
+        self.finished = false;
+        self.queued = None;
+    }
+
+    /// Switches to `clip`. If it is already playing, it carries on rather
+    /// than starting again.
+    pub fn play(&mut self, clip: &str) {
+        if self.clip != clip || self.finished {
+            self.start(clip);
+        }
+    }
+
+    /// Plays the current clip again from the start.
+    pub fn restart(&mut self) {
+        let clip = self.clip.clone();
+        self.start(&clip);
+    }
+
+    /// Plays `clip` once the current clip finishes, instead of the clip's
+    /// own next clip.
+    pub fn queue(&mut self, clip: impl Into<String>) {
+        self.queued = Some(clip.into());
+    }
+
+    /// The clip that is playing.
+    pub fn clip(&self) -> &str {
+        &self.clip
+    }
+
+    /// Has a [`PlayMode::Once`] clip finished, with nothing to follow it?
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// The atlas index to show.
+    pub fn atlas_index(&self) -> usize {
+        self.library.get(&self.clip).map_or(0, |clip| clip.first + self.frame)
+    }
+
+    /// Advances the animation by `delta` seconds. Returns the name of the
+    /// clip that finished, if one did.
+    pub fn tick(&mut self, delta: f32) -> Option<String> {
+        let library = self.library.clone();
+        let clip = library.get(&self.clip)?;
+        if self.finished || clip.fps <= 0.0 {
+            return None;
+        }
+        self.elapsed += delta * self.speed;
+        let frame_time = 1.0 / clip.fps;
+        while self.elapsed >= frame_time {
+            self.elapsed -= frame_time;
+            if self.step(clip) {
+                let finished = self.clip.clone();
+                match self.queued.take().or_else(|| clip.next.clone()) {
+                    Some(next) => self.start(&next),
+                    None => self.finished = true,
+                }
+                return Some(finished);
+            }
+        }
+        None
+    }
+
+    /// Moves on one frame. Returns true if the clip has finished.
+    fn step(&mut self, clip: &SpriteClip) -> bool {
+        let last = clip.len() - 1;
+        match clip.mode {
+            PlayMode::Loop => self.frame = if self.frame >= last { 0 } else { self.frame + 1 },
+            PlayMode::Once if self.frame >= last => return true,
+            PlayMode::Once => self.frame += 1,
+            PlayMode::PingPong if last == 0 => {}
+            PlayMode::PingPong => {
+                if self.frame >= last {
+                    self.reverse = true;
+                } else if self.frame == 0 {
+                    self.reverse = false;
+                }
+                self.frame = if self.reverse { self.frame - 1 } else { self.frame + 1 };
+            }
+        }
+        false
+    }
+}
+
+/// Sent when a [`PlayMode::Once`] clip reaches its end.
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct AnimationFinished {
+    pub entity: Entity,
+    pub clip: String,
+}
+
+/// The system that advances animations, for ordering other systems
+/// around it.
+#[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AnimationSystems;
+
+/// Plays every [`SpriteAnimation`].
+pub struct SpriteAnimationPlugin;
+
+impl Plugin for SpriteAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AnimationFinished>()
+            .add_systems(Update, animate_sprites.in_set(AnimationSystems));
+    }
+}
+
+fn animate_sprites(
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut SpriteAnimation, &mut TextureAtlas)>,
+    mut finished: EventWriter<AnimationFinished>,
+) {
+    for (entity, mut animation, mut atlas) in query.iter_mut() {
+        if let Some(clip) = animation.tick(time.delta_seconds()) {
+            finished.send(AnimationFinished { entity, clip });
+        }
+        let index = animation.atlas_index();
+        if atlas.index != index {
+            atlas.index = index;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn library() -> Arc<AnimationLibrary> {
+        AnimationLibrary::new()
+            .with("walk", SpriteClip::new(0..=2, 10.0, PlayMode::Loop))
+            .with("bounce", SpriteClip::new(3..=5, 10.0, PlayMode::PingPong))
+            .with("jump", SpriteClip::new(6..=7, 10.0, PlayMode::Once).then("walk"))
+            .into()
+    }
+
+    fn frames(animation: &mut SpriteAnimation, ticks: usize) -> Vec<usize> {
+        (0..ticks)
+            .map(|_| {
+                animation.tick(0.1);
+                animation.atlas_index()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_play_modes() {
+        let mut walk = SpriteAnimation::new(library(), "walk");
+        assert_eq!(frames(&mut walk, 5), [1, 2, 0, 1, 2]);
+        let mut bounce = SpriteAnimation::new(library(), "bounce");
+        assert_eq!(frames(&mut bounce, 6), [4, 5, 4, 3, 4, 5]);
+
+        let mut jump = SpriteAnimation::new(library(), "jump");
+        jump.queue("bounce");
+        assert_eq!(jump.tick(0.1), None);
+        assert_eq!(jump.tick(0.1), Some("jump".to_string()));
+        assert_eq!(jump.clip(), "bounce");
+        assert!(!jump.is_finished());
+    }
+
+    #[test]
+    fn test_finished_events() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, SpriteAnimationPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(50)));
+        let entity = app
+            .world_mut()
+            .spawn((SpriteAnimation::new(library(), "jump"), TextureAtlas::default()))
+            .id();
+        let mut finished = Vec::new();
+        for _ in 0..200 {
+            app.update();
+            let events = app.world().resource::<Events<AnimationFinished>>();
+            finished.extend(events.get_reader().read(events).cloned());
+            if !finished.is_empty() {
+                break;
+            }
+        }
+        assert_eq!(finished, [AnimationFinished { entity, clip: "jump".to_string() }]);
+        let animation = app.world().get::<SpriteAnimation>(entity).unwrap();
+        assert_eq!(animation.clip(), "walk");
+    }
+
+    #[test]
+    fn test_check_against_layout() {
+        let layout = TextureAtlasLayout::from_grid(UVec2::splat(16), 8, 1, None, None);
+        assert!(library().check(&layout).is_ok());
+        let small = TextureAtlasLayout::from_grid(UVec2::splat(16), 4, 1, None, None);
+        assert!(library().check(&small).is_err());
+    }
+}
@@ -1,9 +1,45 @@
 use super::{MenuAssets, MenuResource};
+use crate::{ActionState, InputBinding, InputMap};
 use bevy::{app::AppExit, prelude::*, state::state::FreelyMutableState};
+use serde::{Deserialize, Serialize};
 
 #[derive(Component)]
 pub(crate) struct MenuElement;
 
+/// Actions available on the main menu and game over screens. The bindings
+/// live in the `InputMap<MenuAction>` resource, and may be changed like any
+/// other action.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+pub enum MenuAction {
+    Play,
+    /// Start another game from the game over screen.
+    Retry,
+    Continue,
+    MainMenu,
+    Leaderboard,
+    Quit,
+}
+
+impl MenuAction {
+    pub(crate) fn default_bindings() -> InputMap<MenuAction> {
+        InputMap::new()
+            .bind(MenuAction::Play, InputBinding::Key(KeyCode::KeyP))
+            .bind(MenuAction::Play, InputBinding::GamepadButton(GamepadButtonType::Start))
+            .bind(MenuAction::Retry, InputBinding::Key(KeyCode::KeyR))
+            // Not South, which a game may use for play: a player still
+            // pressing it as the game ends would start another at once.
+            .bind(MenuAction::Retry, InputBinding::GamepadButton(GamepadButtonType::RightTrigger))
+            .bind(MenuAction::Continue, InputBinding::Key(KeyCode::KeyC))
+            .bind(MenuAction::Continue, InputBinding::GamepadButton(GamepadButtonType::West))
+            .bind(MenuAction::MainMenu, InputBinding::Key(KeyCode::KeyM))
+            .bind(MenuAction::MainMenu, InputBinding::GamepadButton(GamepadButtonType::East))
+            .bind(MenuAction::Leaderboard, InputBinding::Key(KeyCode::KeyL))
+            .bind(MenuAction::Leaderboard, InputBinding::GamepadButton(GamepadButtonType::North))
+            .bind(MenuAction::Quit, InputBinding::Key(KeyCode::KeyQ))
+            .bind(MenuAction::Quit, InputBinding::GamepadButton(GamepadButtonType::Select))
+    }
+}
+
 pub(crate)fn setup<S>(
     state: Res<State<S>>,
     mut commands: Commands,
@@ -32,7 +68,7 @@ pub(crate)fn setup<S>(
 }
 
 pub(crate) fn run<S>(
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState<MenuAction>>,
     mut exit: EventWriter<AppExit>,
     current_state: Res<State<S>>,
     mut state: ResMut<NextState<S>>,
@@ -42,16 +78,36 @@ pub(crate) fn run<S>(
 {
     let current_state = current_state.get().clone();
     if current_state == menu_state.menu_state {
-        if keyboard.just_pressed(KeyCode::KeyP) {
+        if actions.just_pressed(MenuAction::Play) {
             state.set(menu_state.game_start_state.clone());
-        } else if keyboard.just_pressed(KeyCode::KeyQ) {
+        } else if actions.just_pressed(MenuAction::Quit) {
             exit.send(AppExit::Success);
         }
     } else if current_state == menu_state.game_end_state {
-        if keyboard.just_pressed(KeyCode::KeyM) {
-            state.set(menu_state.game_end_state.clone());
-        } else if keyboard.just_pressed(KeyCode::KeyQ) {
+        if actions.just_pressed(MenuAction::Retry) {
+            state.set(menu_state.game_start_state.clone());
+        } else if actions.just_pressed(MenuAction::MainMenu) {
+            state.set(menu_state.menu_state.clone());
+        } else if actions.just_pressed(MenuAction::Quit) {
             exit.send(AppExit::Success);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_menu_actions_have_their_own_inputs() {
+        use MenuAction::*;
+        let map = MenuAction::default_bindings();
+        let mut seen = Vec::new();
+        for action in [Play, Retry, Continue, MainMenu, Leaderboard, Quit] {
+            for binding in map.bindings(action) {
+                assert!(!seen.contains(binding), "{binding:?} is bound twice");
+                seen.push(*binding);
+            }
+        }
+    }
+}
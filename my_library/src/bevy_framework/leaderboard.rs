@@ -0,0 +1,166 @@
+use super::game_menus::{MenuAction, MenuElement};
+use crate::{persistence::DataDir, unix_time, ActionState, HighScoreEntry, HighScores, NewScore};
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    prelude::*,
+};
+
+const MAX_NAME_LENGTH: usize = 12;
+
+#[derive(Component)]
+pub(crate) struct LeaderboardView;
+
+#[derive(Component)]
+pub(crate) struct NameEntryText;
+
+/// Present while the player is typing their name for a new high score.
+/// Menu actions are ignored until it is removed.
+#[derive(Resource, Default)]
+pub(crate) struct NameEntry {
+    name: String,
+}
+
+fn leaderboard_text(scores: &HighScores, highlight: Option<usize>) -> String {
+    let mut text = String::from("HIGH SCORES\n\n");
+    if scores.entries().is_empty() {
+        text.push_str("No scores yet!\n");
+    }
+    for (rank, entry) in scores.entries().iter().enumerate() {
+        let marker = if Some(rank) == highlight { ">" } else { " " };
+        text.push_str(&format!(
+            "{marker}{:>2}. {:<12} {:>6}  {}\n",
+            rank + 1,
+            entry.name,
+            entry.score,
+            entry.date_string(),
+        ));
+    }
+    text
+}
+
+/// Where a panel sits on screen: centre height, panel height and font size.
+type PanelLayout = (f32, f32, f32);
+
+/// The leaderboard fills the middle of the main menu.
+const MENU_PANEL: PanelLayout = (0.0, 480.0, 28.0);
+
+/// On the game over screen, name entry sits below the game's results.
+const GAME_OVER_PANEL: PanelLayout = (-150.0, 400.0, 22.0);
+
+fn spawn_panel(
+    commands: &mut Commands,
+    text: String,
+    marker: impl Component,
+    (y, height, font_size): PanelLayout,
+) {
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::srgba(0.0, 0.0, 0.0, 0.8),
+                custom_size: Some(Vec2::new(640.0, height)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, y, 2.0),
+            ..default()
+        })
+        .insert(LeaderboardView)
+        .insert(MenuElement);
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(text, TextStyle { font_size, ..default() }),
+            transform: Transform::from_xyz(0.0, y, 3.0),
+            ..default()
+        })
+        .insert(marker)
+        .insert(MenuElement);
+}
+
+pub(crate) fn toggle(
+    mut commands: Commands,
+    actions: Res<ActionState<MenuAction>>,
+    scores: Res<HighScores>,
+    view: Query<Entity, With<LeaderboardView>>,
+) {
+    if !actions.just_pressed(MenuAction::Leaderboard) {
+        return;
+    }
+    if view.is_empty() {
+        spawn_panel(&mut commands, leaderboard_text(&scores, None), LeaderboardView, MENU_PANEL);
+    } else {
+        view.iter().for_each(|entity| commands.entity(entity).despawn());
+    }
+}
+
+pub(crate) fn start_name_entry(
+    mut commands: Commands,
+    scores: Res<HighScores>,
+    new_score: Res<NewScore>,
+) {
+    if scores.qualifies(new_score.score) {
+        commands.init_resource::<NameEntry>();
+        spawn_panel(&mut commands, name_prompt(new_score.score, ""), NameEntryText, GAME_OVER_PANEL);
+    } else {
+        commands.remove_resource::<NewScore>();
+    }
+}
+
+fn name_prompt(score: u32, name: &str) -> String {
+    format!("NEW HIGH SCORE: {score}\n\nEnter your name:\n{name}_\n\nPress Enter when done")
+}
+
+pub(crate) fn enter_name(
+    mut commands: Commands,
+    mut keys: EventReader<KeyboardInput>,
+    mut entry: ResMut<NameEntry>,
+    mut scores: ResMut<HighScores>,
+    dir: Res<DataDir>,
+    new_score: Res<NewScore>,
+    mut text: Query<&mut Text, With<NameEntryText>>,
+) {
+    let mut finished = false;
+    for key in keys.read().filter(|k| k.state == ButtonState::Pressed) {
+        match &key.logical_key {
+            Key::Character(c) => {
+                for ch in c.chars().filter(|ch| ch.is_alphanumeric() || *ch == ' ') {
+                    if entry.name.chars().count() < MAX_NAME_LENGTH {
+                        entry.name.push(ch);
+                    }
+                }
+            }
+            Key::Space if entry.name.chars().count() < MAX_NAME_LENGTH => entry.name.push(' '),
+            Key::Backspace => {
+                entry.name.pop();
+            }
+            Key::Enter => finished = true,
+            _ => {}
+        }
+    }
+
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    if !finished {
+        text.sections[0].value = name_prompt(new_score.score, &entry.name);
+        return;
+    }
+
+    let name = match entry.name.trim() {
+        "" => "Anonymous".to_string(),
+        name => name.to_string(),
+    };
+    let rank = scores.insert(HighScoreEntry {
+        name,
+        score: new_score.score,
+        date: unix_time(),
+        seed: new_score.seed,
+    });
+    if let Err(e) = scores.save(&dir) {
+        warn!("Unable to save high scores: {e}");
+    }
+    text.sections[0].value = leaderboard_text(&scores, rank);
+    commands.remove_resource::<NameEntry>();
+    commands.remove_resource::<NewScore>();
+}
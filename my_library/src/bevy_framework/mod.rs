@@ -1,11 +1,24 @@
+use crate::{ActionPlugin, HighScores, NewScore};
 use bevy::{prelude::*, state::state::FreelyMutableState};
 
 mod game_menus;
+pub use game_menus::MenuAction;
+pub(crate) use game_menus::MenuElement;
+mod leaderboard;
+mod phases;
+pub use phases::{AddPhaseGraph, Phase, PhaseGraph, PhaseSystems};
+mod scoping;
+pub use scoping::Persistent;
+mod results;
+pub use results::GameResults;
+mod transitions;
+pub use transitions::{CrossfadeInProgress, ScreenTransitions, Transition};
 
-pub struct GameStatePlugin<S> {
+pub struct GameStatePlugin<S: FreelyMutableState> {
     menu_state: S,
     game_start_state: S,
     game_end_state: S,
+    transitions: ScreenTransitions<S>,
 }
 
 impl<S> GameStatePlugin<S>
@@ -14,7 +27,25 @@ where
 {
     #[allow(clippy::new_without_default)]
     pub fn new(menu_state: S, game_start_state: S, game_end_state: S) -> Self {
-        Self { menu_state, game_start_state, game_end_state }
+        Self {
+            menu_state,
+            game_start_state,
+            game_end_state,
+            transitions: ScreenTransitions::new(Transition::Cut),
+        }
+    }
+
+    /// Plays `transition` whenever the state changes.
+    pub fn with_transition(mut self, transition: Transition) -> Self {
+        self.transitions.default = transition;
+        self
+    }
+
+    /// Plays `transition` when changing from `from` to `to`, instead of the
+    /// default transition.
+    pub fn with_transition_between(mut self, from: S, to: S, transition: Transition) -> Self {
+        self.transitions.set(from, to, transition);
+        self
     }
 }
 
@@ -24,23 +55,46 @@ where
 {
     fn build(&self, app: &mut App) {
         app.init_state::<S>();
-        app.add_systems(Startup, setup_menus);
+        app.add_plugins(ActionPlugin::new(MenuAction::default_bindings()));
         let start = MenuResource {
             menu_state: self.menu_state,
             game_start_state: self.game_start_state,
             game_end_state: self.game_end_state,
         };
         app.insert_resource(start);
+        app.insert_resource(self.transitions.clone());
+        app.add_systems(PreUpdate, transitions::drive_transitions::<S>);
 
         app.add_systems(OnEnter(self.menu_state), game_menus::setup::<S>);
         app.add_systems(Update, game_menus::run::<S>
             .run_if(in_state(self.menu_state)));
+        app.add_systems(Update, leaderboard::toggle
+            .run_if(in_state(self.menu_state))
+            .run_if(resource_exists::<HighScores>));
         app.add_systems(OnExit(self.menu_state), cleanup::<game_menus::MenuElement>);
 
-        app.add_systems(OnEnter(self.game_end_state), game_menus::setup::<S>);
+        app.add_systems(OnEnter(self.game_end_state), (
+            game_menus::setup::<S>,
+            results::setup,
+            leaderboard::start_name_entry
+                .run_if(resource_exists::<HighScores>)
+                .run_if(resource_exists::<NewScore>),
+        ));
         app.add_systems(Update, game_menus::run::<S>
-            .run_if(in_state(self.game_end_state)));
-        app.add_systems(OnExit(self.game_end_state), cleanup::<game_menus::MenuElement>);
+            .run_if(in_state(self.game_end_state))
+            .run_if(not(resource_exists::<leaderboard::NameEntry>)));
+        app.add_systems(Update, leaderboard::enter_name
+            .run_if(resource_exists::<leaderboard::NameEntry>));
+        app.add_systems(OnExit(self.game_end_state), (
+            cleanup::<game_menus::MenuElement>,
+            results::clear,
+        ));
+    }
+
+    fn finish(&self, app: &mut App) {
+        // The first state is entered before `Startup` runs, so the menu
+        // graphics have to be ready before then.
+        app.init_resource::<MenuAssets>();
     }
 }
 
@@ -57,23 +111,40 @@ pub(crate) struct MenuResource<S> {
     pub(crate) game_end_state: S,
 }
 
-fn setup_menus(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let assets = MenuAssets {
-        main_menu: asset_server.load("main_menu.png"),
-        game_over: asset_server.load("game_over.png"),
-    };
-    commands.insert_resource(assets);
+impl FromWorld for MenuAssets {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self {
+            main_menu: asset_server.load("main_menu.png"),
+            game_over: asset_server.load("game_over.png"),
+        }
+    }
 }
 
+/// Despawns every entity with component `S`. During a crossfade, the
+/// entities stay on screen and fade out before they are despawned.
 pub fn cleanup<S>(
     query: Query<Entity, With<S>>,
+    mut cameras: Query<&mut Camera, With<S>>,
+    crossfade: Option<Res<CrossfadeInProgress>>,
     mut commands: Commands,
 )
 where S: Component
 {
+    if crossfade.is_some() {
+        query.iter().for_each(|entity| {
+            transitions::fade_out_entity(&mut commands, entity, cameras.get_mut(entity).ok());
+        });
+        return;
+    }
     query.iter().for_each(|entity| commands.entity(entity).despawn())
 }
 
+/// Adds `OnEnter`, `Update` and `OnExit` systems for one state.
+///
+/// This is the original way of declaring phases, kept for existing code.
+/// [`PhaseGraph`] also supports `FixedUpdate` systems and checks the
+/// transitions between phases, so prefer it for new games.
 #[macro_export]
 macro_rules! add_phase {
     (
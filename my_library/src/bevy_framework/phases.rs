@@ -0,0 +1,388 @@
+use super::{scoping::*, MenuResource};
+use bevy::{
+    ecs::schedule::{InternedScheduleLabel, ScheduleLabel, SystemConfigs},
+    prelude::*,
+    state::state::{
+        last_transition, EnterSchedules, ExitSchedules, FreelyMutableState, TransitionSchedules,
+    },
+    utils::{HashMap, HashSet},
+};
+use std::any::TypeId;
+
+/// Every system added through a [`Phase`] belongs to `PhaseSystems` for
+/// that phase, so other systems can be ordered before or after it.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct PhaseSystems<S: States>(pub S);
+
+/// The systems that run during one phase of a game. Each method accepts
+/// anything `add_systems` does, so `.chain()`, `.before()` and `.after()`
+/// can be used to order systems within a phase.
+pub struct Phase<S: States> {
+    state: S,
+    systems: Vec<(InternedScheduleLabel, SystemConfigs)>,
+}
+
+impl<S: States> Phase<S> {
+    fn new(state: S) -> Self {
+        Self { state, systems: Vec::new() }
+    }
+
+    fn add<M>(mut self, schedule: impl ScheduleLabel, systems: impl IntoSystemConfigs<M>) -> Self {
+        let systems = systems.into_configs().in_set(PhaseSystems(self.state.clone()));
+        self.systems.push((schedule.intern(), systems));
+        self
+    }
+
+    /// Systems that run once, when the phase starts.
+    pub fn on_enter<M>(self, systems: impl IntoSystemConfigs<M>) -> Self {
+        let schedule = OnEnter(self.state.clone());
+        self.add(schedule, systems)
+    }
+
+    /// Systems that run every frame during the phase.
+    pub fn update<M>(self, systems: impl IntoSystemConfigs<M>) -> Self {
+        let condition = in_state(self.state.clone());
+        self.add(Update, systems.run_if(condition))
+    }
+
+    /// Systems that run on every fixed timestep tick during the phase.
+    pub fn fixed_update<M>(self, systems: impl IntoSystemConfigs<M>) -> Self {
+        let condition = in_state(self.state.clone());
+        self.add(FixedUpdate, systems.run_if(condition))
+    }
+
+    /// Systems that run once, when the phase ends.
+    pub fn on_exit<M>(self, systems: impl IntoSystemConfigs<M>) -> Self {
+        let schedule = OnExit(self.state.clone());
+        self.add(schedule, systems)
+    }
+}
+
+/// The phases that make up a game and the transitions allowed between
+/// them.
+#[derive(Resource)]
+pub(crate) struct PhaseTransitions<S> {
+    allowed: HashSet<(S, S)>,
+}
+
+impl<S: States> PhaseTransitions<S> {
+    pub(crate) fn allows(&self, from: &S, to: &S) -> bool {
+        self.allowed.contains(&(from.clone(), to.clone()))
+    }
+}
+
+/// `PhaseGraph` declares a game's phases, the systems that run in each
+/// one and the transitions between them. It replaces a list of
+/// `add_phase!` calls, and can also add `FixedUpdate` systems.
+///
+/// The graph is checked when the app starts: every transition must join
+/// declared phases, and phases that can't be reached or can't be left
+/// produce a warning. While the game runs, requesting a state change that
+/// wasn't declared logs a warning.
+///
+/// Entities belong to the phase they were spawned in, and are despawned
+/// (with their children) when the game leaves it. So do the resources
+/// registered with [`scope_resource`](PhaseGraph::scope_resource): each
+/// is removed when the game leaves the phase it was inserted in. Put
+/// phases in a [`group`](PhaseGraph::group) to keep things alive while
+/// the game moves between them. Add [`Persistent`] to keep an entity.
+///
+/// Only entities with a `Transform` are scoped, which includes sprites,
+/// text, cameras and UI. Entities without one, such as a bare entity
+/// holding a marker component, are left alone: despawn them in the
+/// phase's `on_exit`, or give them a `Transform`.
+///
+/// Any other resource, including those Bevy and other plugins insert
+/// while the game runs, is left alone. So is a registered resource that
+/// already exists once the app has started.
+///
+/// Add it to the app with [`add_phase_graph`](AddPhaseGraph::add_phase_graph),
+/// alongside `GameStatePlugin`. Its menu and game over states
+/// don't need to be declared as phases, and the moves it makes between
+/// them (starting, retrying and returning to the menu) are always allowed.
+///
+/// ## Example
+///
+/// ```ignore
+/// app.add_phase_graph(
+///     PhaseGraph::new()
+///         .phase(GamePhase::Flapping, |phase| phase
+///             .on_enter(setup)
+///             .update((flap, gravity, hit_wall).chain())
+///             .on_exit(publish_results))
+///         .transition(GamePhase::Flapping, GamePhase::GameOver),
+/// );
+/// ```
+pub struct PhaseGraph<S: States> {
+    phases: Vec<Phase<S>>,
+    declared: Vec<S>,
+    transitions: Vec<(S, S)>,
+    groups: Vec<(&'static str, Vec<S>)>,
+    scoped: HashSet<TypeId>,
+}
+
+impl<S: States> PhaseGraph<S> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            phases: Vec::new(),
+            declared: Vec::new(),
+            transitions: Vec::new(),
+            groups: Vec::new(),
+            scoped: HashSet::new(),
+        }
+    }
+
+    /// Declares `state` as a phase. `build` adds the phase's systems.
+    pub fn phase(mut self, state: S, build: impl FnOnce(Phase<S>) -> Phase<S>) -> Self {
+        if self.declared.contains(&state) {
+            panic!("Phase {state:?} is declared more than once");
+        }
+        self.declared.push(state.clone());
+        self.phases.push(build(Phase::new(state)));
+        self
+    }
+
+    /// Allows the game to move from `from` to `to`.
+    pub fn transition(mut self, from: S, to: S) -> Self {
+        self.transitions.push((from, to));
+        self
+    }
+
+    /// Names a group of phases. Entities and resources created in any of
+    /// them last until the game leaves the group.
+    pub fn group(mut self, name: &'static str, phases: impl IntoIterator<Item = S>) -> Self {
+        self.groups.push((name, phases.into_iter().collect()));
+        self
+    }
+
+    /// Removes resource `R` when the game leaves the phase (or group) it
+    /// was inserted in.
+    pub fn scope_resource<R: Resource>(mut self) -> Self {
+        self.scoped.insert(TypeId::of::<R>());
+        self
+    }
+}
+
+/// Adds a [`PhaseGraph`] to an app.
+pub trait AddPhaseGraph {
+    /// Adds each phase's systems, and checks and enforces the graph.
+    fn add_phase_graph<S: FreelyMutableState + Copy>(&mut self, graph: PhaseGraph<S>) -> &mut Self;
+}
+
+impl AddPhaseGraph for App {
+    fn add_phase_graph<S: FreelyMutableState + Copy>(&mut self, graph: PhaseGraph<S>) -> &mut Self {
+        let PhaseGraph { phases, declared, transitions, groups, scoped } = graph;
+        for phase in phases {
+            for (schedule, systems) in phase.systems {
+                self.add_systems(schedule, systems);
+            }
+        }
+        self.add_plugins(PhaseRules { declared, transitions, groups, scoped })
+    }
+}
+
+/// What's left of a [`PhaseGraph`] once its systems are added: the rules
+/// checked when the app starts and followed while it runs.
+struct PhaseRules<S: States> {
+    declared: Vec<S>,
+    transitions: Vec<(S, S)>,
+    groups: Vec<(&'static str, Vec<S>)>,
+    scoped: HashSet<TypeId>,
+}
+
+impl<S> Plugin for PhaseRules<S>
+where
+    S: FreelyMutableState + Copy,
+{
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            check_transitions::<S>.before(super::transitions::drive_transitions::<S>),
+        );
+
+        app.insert_resource(PhaseScopes::new(&self.declared, &self.groups, self.scoped.clone()))
+            .init_resource::<ScopedResources<S>>()
+            .observe(tag_entity::<S>)
+            .add_systems(
+                StateTransition,
+                (
+                    last_transition::<S>.pipe(despawn_scoped::<S>),
+                    last_transition::<S>.pipe(remove_scoped_resources::<S>),
+                )
+                    .after(ExitSchedules::<S>::default())
+                    .before(TransitionSchedules::<S>::default()),
+            )
+            .add_systems(
+                StateTransition,
+                ignore_existing_resources::<S>
+                    .run_if(run_once())
+                    .before(EnterSchedules::<S>::default()),
+            )
+            .add_systems(PostStartup, ignore_existing_resources::<S>)
+            .add_systems(StateTransition, track_resources::<S>.after(EnterSchedules::<S>::default()))
+            .add_systems(Last, track_resources::<S>);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let mut states = self.declared.clone();
+        let mut allowed: HashSet<(S, S)> = self.transitions.iter().copied().collect();
+        if let Some(menus) = app.world().get_resource::<MenuResource<S>>() {
+            states.extend([menus.menu_state, menus.game_end_state]);
+            allowed.extend([
+                (menus.menu_state, menus.game_start_state),
+                (menus.game_end_state, menus.game_start_state),
+                (menus.game_end_state, menus.menu_state),
+            ]);
+        }
+
+        for (name, group) in self.groups.iter() {
+            if let Some(state) = group.iter().find(|s| !self.declared.contains(s)) {
+                panic!("Group {name} uses undeclared phase {state:?}");
+            }
+        }
+        for (from, to) in allowed.iter() {
+            for state in [from, to] {
+                if !states.contains(state) {
+                    panic!("Transition {from:?} -> {to:?} uses undeclared phase {state:?}");
+                }
+            }
+        }
+
+        let mut exits: HashMap<S, Vec<S>> = HashMap::new();
+        for (from, to) in allowed.iter() {
+            exits.entry(*from).or_default().push(*to);
+        }
+        if let Some(initial) = app.world().get_resource::<State<S>>().map(|s| *s.get()) {
+            let mut reached = HashSet::from([initial]);
+            let mut open = vec![initial];
+            while let Some(state) = open.pop() {
+                for next in exits.get(&state).into_iter().flatten() {
+                    if reached.insert(*next) {
+                        open.push(*next);
+                    }
+                }
+            }
+            for state in states.iter().filter(|s| !reached.contains(*s)) {
+                warn!("Phase {state:?} can't be reached from {initial:?}");
+            }
+        }
+        for state in self.declared.iter().filter(|s| !exits.contains_key(*s)) {
+            warn!("Phase {state:?} has no transitions out of it");
+        }
+
+        app.insert_resource(PhaseTransitions { allowed });
+    }
+}
+
+/// Runs before screen transitions hold back a pending state change, so
+/// every request is seen.
+fn check_transitions<S: FreelyMutableState>(
+    current: Res<State<S>>,
+    next: Res<NextState<S>>,
+    graph: Res<PhaseTransitions<S>>,
+) {
+    if let NextState::Pending(to) = next.as_ref() {
+        if to != current.get() && !graph.allows(current.get(), to) {
+            warn!("Undeclared phase transition {:?} -> {to:?}", current.get());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::state::app::StatesPlugin;
+
+    #[derive(States, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+    enum Phases {
+        #[default]
+        A,
+        B,
+        C,
+    }
+
+    #[derive(Resource, Default)]
+    struct Log(Vec<&'static str>);
+
+    #[test]
+    fn test_phase_schedules() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .init_state::<Phases>()
+            .init_resource::<Log>()
+            .add_phase_graph(
+                PhaseGraph::new()
+                    .phase(Phases::A, |phase| phase
+                        .on_enter(|mut log: ResMut<Log>| log.0.push("enter a"))
+                        .update(|mut state: ResMut<NextState<Phases>>| state.set(Phases::B))
+                        .on_exit(|mut log: ResMut<Log>| log.0.push("exit a")))
+                    .phase(Phases::B, |phase| phase
+                        .on_enter(|mut log: ResMut<Log>| log.0.push("enter b")))
+                    .transition(Phases::A, Phases::B)
+                    .transition(Phases::B, Phases::A),
+            );
+        app.finish();
+        app.update();
+        app.update();
+        assert_eq!(app.world().resource::<Log>().0, ["enter a", "exit a", "enter b"]);
+        let graph = app.world().resource::<PhaseTransitions<Phases>>();
+        assert!(graph.allows(&Phases::A, &Phases::B));
+    }
+
+    #[derive(Resource)]
+    struct Scoped;
+
+    #[derive(Resource)]
+    struct Unscoped;
+
+    #[test]
+    fn test_phase_scoping() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .init_state::<Phases>()
+            .add_phase_graph(
+                PhaseGraph::new()
+                    .phase(Phases::A, |phase| phase.on_enter(|mut commands: Commands| {
+                        commands.spawn(SpatialBundle::default()).with_children(|parent| {
+                            parent.spawn(SpatialBundle::default());
+                        });
+                        commands.spawn((SpatialBundle::default(), Persistent));
+                        commands.insert_resource(Scoped);
+                        commands.insert_resource(Unscoped);
+                    }))
+                    .phase(Phases::B, |phase| phase)
+                    .phase(Phases::C, |phase| phase)
+                    .group("play", [Phases::A, Phases::B])
+                    .transition(Phases::A, Phases::B)
+                    .transition(Phases::B, Phases::C)
+                    .transition(Phases::C, Phases::A)
+                    .scope_resource::<Scoped>(),
+            );
+        app.finish();
+        app.update();
+        let count = |app: &mut App| app.world_mut().query::<&Transform>().iter(app.world()).count();
+        assert_eq!(count(&mut app), 3);
+
+        app.world_mut().resource_mut::<NextState<Phases>>().set(Phases::B);
+        app.update();
+        assert_eq!(count(&mut app), 3);
+        assert!(app.world().contains_resource::<Scoped>());
+
+        app.world_mut().resource_mut::<NextState<Phases>>().set(Phases::C);
+        app.update();
+        assert_eq!(count(&mut app), 1);
+        assert!(!app.world().contains_resource::<Scoped>());
+        assert!(app.world().contains_resource::<Unscoped>());
+    }
+
+    #[test]
+    #[should_panic(expected = "undeclared phase")]
+    fn test_undeclared_phase() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .init_state::<Phases>()
+            .add_phase_graph(PhaseGraph::new().phase(Phases::A, |p| p).transition(Phases::A, Phases::B));
+        app.finish();
+    }
+}
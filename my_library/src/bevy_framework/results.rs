@@ -0,0 +1,111 @@
+use super::game_menus::{MenuAction, MenuElement};
+use crate::InputMap;
+use bevy::prelude::*;
+
+/// What happened in the game that just finished. Insert `GameResults`
+/// before moving to the game over state, and the game over screen shows
+/// it on top of the background. It is removed when the game over screen
+/// closes.
+///
+/// ## Example
+///
+/// ```
+/// use my_library::GameResults;
+///
+/// let results = GameResults::new("Game Over")
+///     .line("Player: 102")
+///     .line("CPU: 87")
+///     .winner("Player");
+/// assert_eq!(results.lines.len(), 2);
+/// ```
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct GameResults {
+    /// The heading, for example "Game Over" or "You crashed!".
+    pub title: String,
+    /// Statistics, one per line.
+    pub lines: Vec<String>,
+    /// Who won, if the game has a winner.
+    pub winner: Option<String>,
+}
+
+impl GameResults {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self { title: title.into(), ..default() }
+    }
+
+    /// Adds a line of statistics.
+    pub fn line(mut self, line: impl Into<String>) -> Self {
+        self.lines.push(line.into());
+        self
+    }
+
+    /// Names the winner.
+    pub fn winner(mut self, winner: impl Into<String>) -> Self {
+        self.winner = Some(winner.into());
+        self
+    }
+}
+
+/// The keys for the game over screen, as currently bound.
+fn options(map: &InputMap<MenuAction>) -> String {
+    format!(
+        "{} - Play again     {} - Main menu     {} - Quit",
+        map.describe(MenuAction::Retry),
+        map.describe(MenuAction::MainMenu),
+        map.describe(MenuAction::Quit)
+    )
+}
+
+pub(crate) fn setup(
+    mut commands: Commands,
+    map: Res<InputMap<MenuAction>>,
+    results: Option<Res<GameResults>>,
+) {
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_section(options(&map), TextStyle { font_size: 24.0, ..default() }),
+            transform: Transform::from_xyz(0.0, -365.0, 3.0),
+            ..default()
+        })
+        .insert(MenuElement);
+
+    let Some(results) = results else {
+        return;
+    };
+    commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::srgba(0.0, 0.0, 0.0, 0.8),
+                custom_size: Some(Vec2::new(640.0, 300.0)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 210.0, 2.0),
+            ..default()
+        })
+        .insert(MenuElement);
+
+    let mut sections = vec![TextSection::new(
+        format!("{}\n\n", results.title),
+        TextStyle { font_size: 40.0, ..default() },
+    )];
+    sections.extend(results.lines.iter().map(|line| {
+        TextSection::new(format!("{line}\n"), TextStyle { font_size: 28.0, ..default() })
+    }));
+    if let Some(winner) = &results.winner {
+        sections.push(TextSection::new(
+            format!("\n{winner} wins!"),
+            TextStyle { font_size: 32.0, color: Color::srgb(1.0, 0.85, 0.2), ..default() },
+        ));
+    }
+    commands
+        .spawn(Text2dBundle {
+            text: Text::from_sections(sections).with_justify(JustifyText::Center),
+            transform: Transform::from_xyz(0.0, 210.0, 3.0),
+            ..default()
+        })
+        .insert(MenuElement);
+}
+
+pub(crate) fn clear(mut commands: Commands) {
+    commands.remove_resource::<GameResults>();
+}
@@ -0,0 +1,180 @@
+use super::transitions::{fade_out_entity, FadingOut};
+use super::CrossfadeInProgress;
+use bevy::{
+    ecs::component::{ComponentId, ComponentInfo},
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use std::any::TypeId;
+
+/// Add `Persistent` to an entity to keep it when the phase it was spawned
+/// in ends.
+#[derive(Component)]
+pub struct Persistent;
+
+/// The phase an entity was spawned in.
+#[derive(Component)]
+pub(crate) struct SpawnedIn<S>(S);
+
+/// For each declared phase, the phases its entities and resources
+/// survive into: the phase itself, plus every group it belongs to. Only
+/// the `scoped` resource types are tracked.
+#[derive(Resource)]
+pub(crate) struct PhaseScopes<S> {
+    scopes: HashMap<S, HashSet<S>>,
+    scoped: HashSet<TypeId>,
+}
+
+impl<S: States> PhaseScopes<S> {
+    pub(crate) fn new(phases: &[S], groups: &[(&'static str, Vec<S>)], scoped: HashSet<TypeId>) -> Self {
+        let scopes = phases
+            .iter()
+            .map(|phase| {
+                let mut scope = HashSet::from([phase.clone()]);
+                for (_, group) in groups.iter().filter(|(_, group)| group.contains(phase)) {
+                    scope.extend(group.iter().cloned());
+                }
+                (phase.clone(), scope)
+            })
+            .collect();
+        Self { scopes, scoped }
+    }
+
+    fn is_phase(&self, state: &S) -> bool {
+        self.scopes.contains_key(state)
+    }
+
+    /// Does something from `spawned_in` live on into `entered`?
+    fn survives(&self, spawned_in: &S, entered: Option<&S>) -> bool {
+        entered.is_some_and(|entered| {
+            self.scopes.get(spawned_in).is_some_and(|scope| scope.contains(entered))
+        })
+    }
+
+    /// Did the game ask for this resource to be removed with its phase?
+    fn scopes_resource(&self, info: &ComponentInfo) -> bool {
+        info.type_id().is_some_and(|id| self.scoped.contains(&id))
+    }
+}
+
+/// Scoped resources that appeared during a phase, and the phase they
+/// appeared in.
+#[derive(Resource)]
+pub(crate) struct ScopedResources<S> {
+    seen: HashSet<ComponentId>,
+    owners: HashMap<ComponentId, S>,
+}
+
+impl<S> Default for ScopedResources<S> {
+    fn default() -> Self {
+        Self { seen: HashSet::new(), owners: HashMap::new() }
+    }
+}
+
+/// Tags new entities with the phase they are spawned in. Only entities with
+/// a `Transform` are seen: an entity without one isn't scoped, and lives
+/// until something despawns it.
+pub(crate) fn tag_entity<S: States>(
+    trigger: Trigger<OnAdd, Transform>,
+    state: Option<Res<State<S>>>,
+    scopes: Res<PhaseScopes<S>>,
+    mut commands: Commands,
+) {
+    let Some(state) = state.filter(|state| scopes.is_phase(state.get())) else {
+        return;
+    };
+    commands
+        .entity(trigger.entity())
+        .try_insert(SpawnedIn(state.get().clone()));
+}
+
+type ScopedQuery<'w, 's, S> = Query<
+    'w,
+    's,
+    (Entity, &'static SpawnedIn<S>, Option<&'static Parent>),
+    (Without<Persistent>, Without<FadingOut>),
+>;
+
+/// Despawns entities whose phase has ended, along with their children.
+/// During a crossfade they fade out first, like [`cleanup`](crate::cleanup).
+pub(crate) fn despawn_scoped<S: States>(
+    transition: In<Option<StateTransitionEvent<S>>>,
+    scopes: Res<PhaseScopes<S>>,
+    entities: ScopedQuery<S>,
+    mut cameras: Query<&mut Camera>,
+    crossfade: Option<Res<CrossfadeInProgress>>,
+    mut commands: Commands,
+) {
+    let Some(transition) = transition.0 else {
+        return;
+    };
+    let doomed: HashSet<Entity> = entities
+        .iter()
+        .filter(|(_, spawned_in, _)| !scopes.survives(&spawned_in.0, transition.entered.as_ref()))
+        .map(|(entity, ..)| entity)
+        .collect();
+
+    // Children go with their parents, so only despawn the top of each tree.
+    for (entity, _, parent) in entities.iter_many(doomed.iter()) {
+        if parent.is_some_and(|parent| doomed.contains(&parent.get())) {
+            continue;
+        }
+        if crossfade.is_some() {
+            fade_out_entity(&mut commands, entity, cameras.get_mut(entity).ok());
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Removes resources whose phase has ended.
+pub(crate) fn remove_scoped_resources<S: States>(
+    transition: In<Option<StateTransitionEvent<S>>>,
+    world: &mut World,
+) {
+    let Some(transition) = transition.0 else {
+        return;
+    };
+    world.resource_scope(|world, mut tracked: Mut<ScopedResources<S>>| {
+        let scopes = world.resource::<PhaseScopes<S>>();
+        let ended: Vec<ComponentId> = tracked
+            .owners
+            .iter()
+            .filter(|(_, owner)| !scopes.survives(owner, transition.entered.as_ref()))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ended {
+            world.remove_resource_by_id(id);
+            tracked.owners.remove(&id);
+            tracked.seen.remove(&id);
+        }
+    });
+}
+
+/// Treats every resource that exists so far as global. Runs before the
+/// first phase is entered, and again after startup.
+pub(crate) fn ignore_existing_resources<S: States>(world: &mut World) {
+    let existing: Vec<ComponentId> = world.iter_resources().map(|(info, _)| info.id()).collect();
+    world.resource_mut::<ScopedResources<S>>().seen.extend(existing);
+}
+
+/// Notes which scoped resources have appeared since the last check, and
+/// which phase they belong to.
+pub(crate) fn track_resources<S: States>(world: &mut World) {
+    let current = world.get_resource::<State<S>>().map(|state| state.get().clone());
+    world.resource_scope(|world, mut tracked: Mut<ScopedResources<S>>| {
+        let scopes = world.resource::<PhaseScopes<S>>();
+        let phase = current.filter(|state| scopes.is_phase(state));
+        let tracked = tracked.as_mut();
+        tracked.seen.retain(|id| world.get_resource_by_id(*id).is_some());
+        tracked.owners.retain(|id, _| tracked.seen.contains(id));
+        for (info, _) in world.iter_resources() {
+            if !tracked.seen.insert(info.id()) || !scopes.scopes_resource(info) {
+                continue;
+            }
+            if let Some(phase) = &phase {
+                tracked.owners.insert(info.id(), phase.clone());
+            }
+        }
+    });
+}
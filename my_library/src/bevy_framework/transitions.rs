@@ -0,0 +1,253 @@
+use bevy::{
+    prelude::*,
+    render::view::RenderLayers,
+    state::state::FreelyMutableState,
+    utils::HashMap,
+};
+
+/// Render layer used by the outgoing scene during a crossfade.
+const FADE_LAYER: usize = 31;
+
+/// How the screen changes between two states.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum Transition {
+    /// Switch instantly.
+    #[default]
+    Cut,
+    /// Fade to black, change state, then fade back in. `duration` is the
+    /// length of the whole transition in seconds.
+    FadeToBlack { duration: f32 },
+    /// Change state immediately, and fade the outgoing scene out over
+    /// the top of the incoming one.
+    Crossfade { duration: f32 },
+    /// Sweep a black panel across the screen from left to right, change
+    /// state, then sweep it away.
+    Wipe { duration: f32 },
+}
+
+impl Transition {
+    fn duration(&self) -> f32 {
+        match *self {
+            Transition::Cut => 0.0,
+            Transition::FadeToBlack { duration }
+            | Transition::Crossfade { duration }
+            | Transition::Wipe { duration } => duration.max(0.0),
+        }
+    }
+}
+
+/// The transitions used when `GameStatePlugin` changes state. Change it at
+/// runtime to use different effects.
+#[derive(Resource, Clone)]
+pub struct ScreenTransitions<S: FreelyMutableState> {
+    /// Used between any two states that don't have their own entry.
+    pub default: Transition,
+    between: HashMap<(S, S), Transition>,
+}
+
+impl<S: FreelyMutableState> ScreenTransitions<S> {
+    pub(crate) fn new(default: Transition) -> Self {
+        Self { default, between: HashMap::new() }
+    }
+
+    /// Uses `transition` when moving from `from` to `to`.
+    pub fn set(&mut self, from: S, to: S, transition: Transition) {
+        self.between.insert((from, to), transition);
+    }
+
+    /// The transition used when moving from `from` to `to`.
+    pub fn get(&self, from: &S, to: &S) -> Transition {
+        self.between
+            .get(&(from.clone(), to.clone()))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Stage {
+    /// Covering up the outgoing state. The state change is on hold.
+    Out,
+    /// Revealing the incoming state.
+    In,
+}
+
+/// Present while a transition is running.
+#[derive(Resource)]
+pub(crate) struct ActiveTransition<S> {
+    to: S,
+    queued: Option<S>,
+    kind: Transition,
+    stage: Stage,
+    elapsed: f32,
+}
+
+/// Marks the full-screen panel used by fades and wipes.
+#[derive(Component)]
+pub(crate) struct TransitionOverlay;
+
+/// Entities cleaned up during a crossfade are kept on screen, fading out,
+/// until the transition completes.
+#[derive(Component)]
+pub(crate) struct FadingOut;
+
+/// Present while a crossfade is running. Entities removed with
+/// [`cleanup`](crate::cleanup) during a crossfade fade out before they are
+/// despawned.
+#[derive(Resource)]
+pub struct CrossfadeInProgress;
+
+type FadingQuery<'w, 's> =
+    Query<'w, 's, (Entity, Option<&'static mut Sprite>, Option<&'static mut Text>), With<FadingOut>>;
+
+/// Runs in `PreUpdate`, before state transitions are applied. State
+/// changes requested with `NextState` are held back while the outgoing
+/// part of a transition plays.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn drive_transitions<S: FreelyMutableState + Copy>(
+    mut commands: Commands,
+    time: Res<Time>,
+    current: Res<State<S>>,
+    mut next: ResMut<NextState<S>>,
+    settings: Res<ScreenTransitions<S>>,
+    active: Option<ResMut<ActiveTransition<S>>>,
+    mut overlay: Query<(Entity, &mut Style, &mut BackgroundColor), With<TransitionOverlay>>,
+    mut fading: FadingQuery,
+) {
+    let requested = match next.as_ref() {
+        NextState::Pending(to) if to != current.get() => Some(*to),
+        _ => None,
+    };
+
+    let Some(mut active) = active else {
+        let Some(to) = requested else {
+            return;
+        };
+        let kind = settings.get(current.get(), &to);
+        if kind.duration() <= 0.0 {
+            return;
+        }
+        let stage = match kind {
+            Transition::Crossfade { .. } => {
+                commands.insert_resource(CrossfadeInProgress);
+                Stage::In
+            }
+            _ => {
+                next.reset();
+                spawn_overlay(&mut commands);
+                Stage::Out
+            }
+        };
+        commands.insert_resource(ActiveTransition { to, queued: None, kind, stage, elapsed: 0.0 });
+        return;
+    };
+
+    if let Some(to) = requested {
+        if active.stage == Stage::Out || to != active.to {
+            active.queued = Some(to);
+            next.reset();
+        }
+    }
+
+    active.elapsed += time.delta_seconds();
+    let half = active.kind.duration() / 2.0;
+    let progress = match (active.kind, active.stage) {
+        (Transition::Crossfade { .. }, _) => (active.elapsed / (half * 2.0)).min(1.0),
+        _ => (active.elapsed / half).min(1.0),
+    };
+
+    match active.kind {
+        Transition::Crossfade { .. } => {
+            for (_, sprite, text) in fading.iter_mut() {
+                let alpha = 1.0 - progress;
+                if let Some(mut sprite) = sprite {
+                    let a = sprite.color.alpha().min(alpha);
+                    sprite.color.set_alpha(a);
+                }
+                if let Some(mut text) = text {
+                    for section in text.sections.iter_mut() {
+                        let a = section.style.color.alpha().min(alpha);
+                        section.style.color.set_alpha(a);
+                    }
+                }
+            }
+        }
+        Transition::FadeToBlack { .. } => {
+            let alpha = match active.stage {
+                Stage::Out => progress,
+                Stage::In => 1.0 - progress,
+            };
+            for (_, _, mut color) in overlay.iter_mut() {
+                color.0 = Color::srgba(0.0, 0.0, 0.0, alpha);
+            }
+        }
+        Transition::Wipe { .. } => {
+            for (_, mut style, _) in overlay.iter_mut() {
+                match active.stage {
+                    Stage::Out => {
+                        style.left = Val::Percent(0.0);
+                        style.width = Val::Percent(progress * 100.0);
+                    }
+                    Stage::In => {
+                        style.left = Val::Percent(progress * 100.0);
+                        style.width = Val::Percent((1.0 - progress) * 100.0);
+                    }
+                }
+            }
+        }
+        Transition::Cut => {}
+    }
+
+    if progress < 1.0 {
+        return;
+    }
+    match active.stage {
+        Stage::Out => {
+            // Fully covered: let the state change (and its cleanup) happen.
+            next.set(active.to);
+            active.stage = Stage::In;
+            active.elapsed = 0.0;
+        }
+        Stage::In => {
+            overlay.iter().for_each(|(entity, _, _)| commands.entity(entity).despawn());
+            fading.iter().for_each(|(entity, ..)| commands.entity(entity).despawn_recursive());
+            if let Some(queued) = active.queued {
+                next.set(queued);
+            }
+            commands.remove_resource::<ActiveTransition<S>>();
+            commands.remove_resource::<CrossfadeInProgress>();
+        }
+    }
+}
+
+fn spawn_overlay(commands: &mut Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(0.0),
+                top: Val::Percent(0.0),
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.0)),
+            z_index: ZIndex::Global(i32::MAX),
+            ..default()
+        })
+        .insert(TransitionOverlay)
+        .insert(super::Persistent);
+}
+
+/// Instead of despawning, moves `entity` to the crossfade layer so it can
+/// fade out over the incoming scene. Cameras keep drawing the outgoing
+/// scene, on top of the new one.
+pub(crate) fn fade_out_entity(commands: &mut Commands, entity: Entity, camera: Option<Mut<Camera>>) {
+    if let Some(mut camera) = camera {
+        camera.order = 1;
+        camera.clear_color = ClearColorConfig::None;
+    }
+    commands
+        .entity(entity)
+        .insert((FadingOut, RenderLayers::layer(FADE_LAYER)));
+}
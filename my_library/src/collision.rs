@@ -0,0 +1,376 @@
+use bevy::{
+    ecs::schedule::{InternedScheduleLabel, ScheduleLabel},
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use std::marker::PhantomData;
+
+/// The shape of an entity for collision detection, centred on its
+/// position. Rotation and scale are ignored.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub enum Collider {
+    /// An axis-aligned box.
+    Aabb { half_extents: Vec2 },
+    /// A circle.
+    Circle { radius: f32 },
+    /// A line segment from `start` to `end` (relative to the position),
+    /// thickened by `radius`.
+    Capsule { start: Vec2, end: Vec2, radius: f32 },
+}
+
+impl Collider {
+    /// A box `width` by `height`.
+    pub fn rectangle(width: f32, height: f32) -> Self {
+        Collider::Aabb { half_extents: Vec2::new(width, height) / 2.0 }
+    }
+
+    /// A circle of radius `radius`.
+    pub fn circle(radius: f32) -> Self {
+        Collider::Circle { radius }
+    }
+
+    /// A horizontal capsule `width` by `height`, with rounded ends.
+    pub fn horizontal_capsule(width: f32, height: f32) -> Self {
+        let radius = height / 2.0;
+        let half_length = (width / 2.0 - radius).max(0.0);
+        Collider::Capsule {
+            start: Vec2::new(-half_length, 0.0),
+            end: Vec2::new(half_length, 0.0),
+            radius,
+        }
+    }
+
+    /// The smallest axis-aligned box around the collider at `position`,
+    /// as (min, max).
+    pub fn bounds(&self, position: Vec2) -> (Vec2, Vec2) {
+        match *self {
+            Collider::Aabb { half_extents } => (position - half_extents, position + half_extents),
+            Collider::Circle { radius } => (position - radius, position + radius),
+            Collider::Capsule { start, end, radius } => (
+                position + start.min(end) - radius,
+                position + start.max(end) + radius,
+            ),
+        }
+    }
+
+    /// Do `self` at `position` and `other` at `other_position` overlap?
+    /// Shapes that only touch at the edges don't count.
+    pub fn overlaps(&self, position: Vec2, other: &Collider, other_position: Vec2) -> bool {
+        use Collider::*;
+        match (*self, *other) {
+            (Aabb { half_extents: a }, Aabb { half_extents: b }) => {
+                let gap = (position - other_position).abs() - (a + b);
+                gap.x < 0.0 && gap.y < 0.0
+            }
+            (Aabb { half_extents }, round) => {
+                let (start, end, radius) = round.segment(other_position);
+                segment_box_distance(start, end, position - half_extents, position + half_extents) < radius
+            }
+            (round, Aabb { .. }) => other.overlaps(other_position, &round, position),
+            (a, b) => {
+                let (a_start, a_end, a_radius) = a.segment(position);
+                let (b_start, b_end, b_radius) = b.segment(other_position);
+                segment_distance(a_start, a_end, b_start, b_end) < a_radius + b_radius
+            }
+        }
+    }
+
+    /// Circles and capsules as a world-space segment and radius. A circle
+    /// is a capsule of zero length.
+    fn segment(&self, position: Vec2) -> (Vec2, Vec2, f32) {
+        match *self {
+            Collider::Circle { radius } => (position, position, radius),
+            Collider::Capsule { start, end, radius } => (position + start, position + end, radius),
+            Collider::Aabb { .. } => unreachable!("boxes are handled separately"),
+        }
+    }
+}
+
+fn closest_point_on_segment(point: Vec2, start: Vec2, end: Vec2) -> Vec2 {
+    let along = end - start;
+    let length_squared = along.length_squared();
+    if length_squared == 0.0 {
+        return start;
+    }
+    let t = ((point - start).dot(along) / length_squared).clamp(0.0, 1.0);
+    start + along * t
+}
+
+fn segments_intersect(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let side = |p: Vec2, q: Vec2, r: Vec2| (q - p).perp_dot(r - p);
+    let (d1, d2) = (side(c, d, a), side(c, d, b));
+    let (d3, d4) = (side(a, b, c), side(a, b, d));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// The shortest distance between segments `a_start`-`a_end` and
+/// `b_start`-`b_end`.
+fn segment_distance(a_start: Vec2, a_end: Vec2, b_start: Vec2, b_end: Vec2) -> f32 {
+    if segments_intersect(a_start, a_end, b_start, b_end) {
+        return 0.0;
+    }
+    [
+        a_start.distance(closest_point_on_segment(a_start, b_start, b_end)),
+        a_end.distance(closest_point_on_segment(a_end, b_start, b_end)),
+        b_start.distance(closest_point_on_segment(b_start, a_start, a_end)),
+        b_end.distance(closest_point_on_segment(b_end, a_start, a_end)),
+    ]
+    .into_iter()
+    .fold(f32::INFINITY, f32::min)
+}
+
+/// The shortest distance between a segment and a box; zero if the segment
+/// touches the inside of the box.
+fn segment_box_distance(start: Vec2, end: Vec2, min: Vec2, max: Vec2) -> f32 {
+    let inside = |p: Vec2| p.cmpgt(min).all() && p.cmplt(max).all();
+    if inside(start) || inside(end) {
+        return 0.0;
+    }
+    let corners = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+    (0..4)
+        .map(|i| segment_distance(start, end, corners[i], corners[(i + 1) % 4]))
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// Limits which colliders can hit each other. Two entities are tested
+/// only if each one's `memberships` share a bit with the other's
+/// `filter`. Entities without `CollisionGroups` are in every group and
+/// collide with everything.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionGroups {
+    pub memberships: u32,
+    pub filter: u32,
+}
+
+impl CollisionGroups {
+    pub const ALL: CollisionGroups = CollisionGroups { memberships: u32::MAX, filter: u32::MAX };
+
+    pub fn new(memberships: u32, filter: u32) -> Self {
+        Self { memberships, filter }
+    }
+
+    fn interacts(&self, other: &CollisionGroups) -> bool {
+        self.memberships & other.filter != 0 && other.memberships & self.filter != 0
+    }
+}
+
+/// Sent on every check while `a` and `b` overlap.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionEvent {
+    pub a: Entity,
+    pub b: Entity,
+}
+
+impl CollisionEvent {
+    /// If `entity` is part of this collision, the other entity.
+    pub fn other(&self, entity: Entity) -> Option<Entity> {
+        if self.a == entity {
+            Some(self.b)
+        } else if self.b == entity {
+            Some(self.a)
+        } else {
+            None
+        }
+    }
+}
+
+/// A broad-phase grid. Items are stored in every cell their bounds touch,
+/// so only items that share a cell need a precise test.
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<usize>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        Self { cell_size, cells: HashMap::new() }
+    }
+
+    fn cell(&self, point: Vec2) -> IVec2 {
+        (point / self.cell_size).floor().as_ivec2()
+    }
+
+    /// Adds item `index`, covering the box from `min` to `max`.
+    pub fn insert(&mut self, index: usize, min: Vec2, max: Vec2) {
+        let (low, high) = (self.cell(min), self.cell(max));
+        for x in low.x..=high.x {
+            for y in low.y..=high.y {
+                self.cells.entry(IVec2::new(x, y)).or_default().push(index);
+            }
+        }
+    }
+
+    /// Every pair of items that share at least one cell, each listed once
+    /// with the lower index first.
+    pub fn candidate_pairs(&self) -> HashSet<(usize, usize)> {
+        let mut pairs = HashSet::new();
+        for items in self.cells.values() {
+            for (i, a) in items.iter().enumerate() {
+                for b in items[i + 1..].iter() {
+                    pairs.insert((*a.min(b), *a.max(b)));
+                }
+            }
+        }
+        pairs
+    }
+}
+
+/// Where a collider is. Implemented for `Transform`; implement it for your
+/// own position component if the simulation doesn't live in `Transform`.
+pub trait CollisionPosition: Component {
+    fn collision_position(&self) -> Vec2;
+}
+
+impl CollisionPosition for Transform {
+    fn collision_position(&self) -> Vec2 {
+        self.translation.truncate()
+    }
+}
+
+/// Draws every collider's outline when `true`. Off by default.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CollisionDebug(pub bool);
+
+/// The collision check. Order your own systems after it to read the
+/// [`CollisionEvent`]s from the same step.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct CollisionSystems;
+
+/// `CollisionPlugin` tests every entity with a [`Collider`] against the
+/// others, and sends a [`CollisionEvent`] for each overlapping pair.
+///
+/// Positions come from component `P`, which defaults to `Transform`. The
+/// check runs in `Update` unless another schedule (such as `FixedUpdate`)
+/// is given.
+///
+/// ## Example
+///
+/// ```ignore
+/// app.add_plugins(CollisionPlugin::<Position>::new().in_schedule(FixedUpdate));
+/// ```
+pub struct CollisionPlugin<P = Transform> {
+    schedule: InternedScheduleLabel,
+    cell_size: f32,
+    _position: PhantomData<P>,
+}
+
+impl<P: CollisionPosition> CollisionPlugin<P> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self { schedule: Update.intern(), cell_size: 64.0, _position: PhantomData }
+    }
+
+    /// Runs the check in `schedule`.
+    pub fn in_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.schedule = schedule.intern();
+        self
+    }
+
+    /// Changes the size of the broad-phase grid cells. Something close to
+    /// the size of a typical collider works best.
+    pub fn with_cell_size(mut self, cell_size: f32) -> Self {
+        self.cell_size = cell_size;
+        self
+    }
+}
+
+impl<P: CollisionPosition> Plugin for CollisionPlugin<P> {
+    fn build(&self, app: &mut App) {
+        let cell_size = self.cell_size;
+        app.add_event::<CollisionEvent>()
+            .init_resource::<CollisionDebug>()
+            .add_systems(
+                self.schedule,
+                (move |colliders: ColliderQuery<P>, events: EventWriter<CollisionEvent>| {
+                    detect_collisions(cell_size, colliders, events)
+                })
+                .in_set(CollisionSystems),
+            )
+            .add_systems(Update, draw_colliders.run_if(resource_equals(CollisionDebug(true))));
+    }
+}
+
+type ColliderQuery<'w, 's, P> =
+    Query<'w, 's, (Entity, &'static Collider, &'static P, Option<&'static CollisionGroups>)>;
+
+fn detect_collisions<P: CollisionPosition>(
+    cell_size: f32,
+    colliders: ColliderQuery<P>,
+    mut events: EventWriter<CollisionEvent>,
+) {
+    let items: Vec<_> = colliders
+        .iter()
+        .map(|(entity, collider, position, groups)| {
+            (entity, *collider, position.collision_position(), groups.copied().unwrap_or(CollisionGroups::ALL))
+        })
+        .collect();
+
+    let mut grid = SpatialHash::new(cell_size);
+    for (index, (_, collider, position, _)) in items.iter().enumerate() {
+        let (min, max) = collider.bounds(*position);
+        grid.insert(index, min, max);
+    }
+
+    for (i, j) in grid.candidate_pairs() {
+        let (a, a_collider, a_position, a_groups) = &items[i];
+        let (b, b_collider, b_position, b_groups) = &items[j];
+        if a_groups.interacts(b_groups) && a_collider.overlaps(*a_position, b_collider, *b_position) {
+            events.send(CollisionEvent { a: *a, b: *b });
+        }
+    }
+}
+
+fn draw_colliders(mut gizmos: Gizmos, colliders: Query<(&Collider, &GlobalTransform)>) {
+    let color = Color::srgb(0.0, 1.0, 0.0);
+    for (collider, transform) in colliders.iter() {
+        let position = transform.translation().truncate();
+        match *collider {
+            Collider::Aabb { half_extents } => {
+                gizmos.rect_2d(position, 0.0, half_extents * 2.0, color);
+            }
+            Collider::Circle { radius } => {
+                gizmos.circle_2d(position, radius, color);
+            }
+            Collider::Capsule { start, end, radius } => {
+                let (start, end) = (position + start, position + end);
+                let side = (end - start).normalize_or_zero().perp() * radius;
+                gizmos.circle_2d(start, radius, color);
+                gizmos.circle_2d(end, radius, color);
+                gizmos.line_2d(start + side, end + side, color);
+                gizmos.line_2d(start - side, end - side, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlaps() {
+        let square = Collider::rectangle(32.0, 32.0);
+        let circle = Collider::circle(10.0);
+        let capsule = Collider::horizontal_capsule(60.0, 20.0);
+
+        assert!(square.overlaps(Vec2::ZERO, &square, Vec2::new(31.0, 31.0)));
+        assert!(!square.overlaps(Vec2::ZERO, &square, Vec2::new(32.0, 0.0)));
+
+        // The corner of the square is further away than its edge.
+        assert!(circle.overlaps(Vec2::new(25.0, 0.0), &square, Vec2::ZERO));
+        assert!(!circle.overlaps(Vec2::new(24.0, 24.0), &square, Vec2::ZERO));
+
+        assert!(capsule.overlaps(Vec2::ZERO, &circle, Vec2::new(35.0, 5.0)));
+        assert!(!capsule.overlaps(Vec2::ZERO, &circle, Vec2::new(0.0, 21.0)));
+        assert!(capsule.overlaps(Vec2::ZERO, &square, Vec2::new(0.0, 25.0)));
+    }
+
+    #[test]
+    fn test_spatial_hash() {
+        let mut grid = SpatialHash::new(10.0);
+        grid.insert(0, Vec2::new(0.0, 0.0), Vec2::new(5.0, 5.0));
+        grid.insert(1, Vec2::new(8.0, 8.0), Vec2::new(12.0, 12.0));
+        grid.insert(2, Vec2::new(50.0, 50.0), Vec2::new(55.0, 55.0));
+        assert_eq!(grid.candidate_pairs(), HashSet::from([(0, 1)]));
+    }
+}
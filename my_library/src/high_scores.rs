@@ -0,0 +1,185 @@
+use crate::persistence::DataDir;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HIGH_SCORE_FILE: &str = "high_scores.ron";
+
+/// One line of a high score table.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    pub name: String,
+    pub score: u32,
+    /// When the score was set, in seconds since the Unix epoch.
+    pub date: u64,
+    /// The random seed the game was played with.
+    pub seed: u64,
+}
+
+impl HighScoreEntry {
+    /// Formats the entry's date as `YYYY-MM-DD`.
+    pub fn date_string(&self) -> String {
+        let (year, month, day) = civil_from_days((self.date / 86_400) as i64);
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+}
+
+/// Converts a count of days since 1970-01-01 into a (year, month, day)
+/// calendar date. See Howard Hinnant's `civil_from_days` algorithm.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Seconds since the Unix epoch.
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// The high score table for a game, highest score first. It is loaded when
+/// the [`HighScorePlugin`] starts, and saved whenever an entry is added.
+#[derive(Resource, Debug, Serialize, Deserialize)]
+pub struct HighScores {
+    #[serde(skip)]
+    game: &'static str,
+    #[serde(skip)]
+    capacity: usize,
+    entries: Vec<HighScoreEntry>,
+}
+
+impl HighScores {
+    /// Creates an empty table for `game`, holding up to `capacity` entries.
+    pub fn new(game: &'static str, capacity: usize) -> Self {
+        Self { game, capacity, entries: Vec::new() }
+    }
+
+    /// The ranked entries, best first.
+    pub fn entries(&self) -> &[HighScoreEntry] {
+        &self.entries
+    }
+
+    /// Would `score` earn a place in the table? Nothing does in a table
+    /// with no room.
+    pub fn qualifies(&self, score: u32) -> bool {
+        self.capacity > 0
+            && (self.entries.len() < self.capacity
+                || self.entries.last().is_none_or(|last| score > last.score))
+    }
+
+    /// Adds `entry` to the table, returning its rank (starting at 0) or
+    /// `None` if it didn't make the cut. Ties go to the earlier score.
+    pub fn insert(&mut self, entry: HighScoreEntry) -> Option<usize> {
+        if !self.qualifies(entry.score) {
+            return None;
+        }
+        let rank = self.entries.partition_point(|e| e.score >= entry.score);
+        self.entries.insert(rank, entry);
+        self.entries.truncate(self.capacity);
+        Some(rank)
+    }
+
+    /// Writes the table to the game's folder in `dir`.
+    pub fn save(&self, dir: &DataDir) -> std::io::Result<()> {
+        dir.save(self.game, HIGH_SCORE_FILE, self)
+    }
+
+    fn load(dir: &DataDir, game: &'static str, capacity: usize) -> Self {
+        let mut table = match dir.load::<HighScores>(game, HIGH_SCORE_FILE) {
+            Ok(Some(table)) => table,
+            Ok(None) => HighScores::new(game, capacity),
+            Err(e) => {
+                warn!("Unable to load high scores: {e}");
+                HighScores::new(game, capacity)
+            }
+        };
+        table.game = game;
+        table.capacity = capacity;
+        table.entries.sort_by_key(|e| std::cmp::Reverse(e.score));
+        table.entries.truncate(capacity);
+        table
+    }
+}
+
+/// Insert `NewScore` before moving to the game over state. If the score
+/// makes the high score table, the game over screen asks the player for
+/// their name and records it.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct NewScore {
+    pub score: u32,
+    pub seed: u64,
+}
+
+/// `HighScorePlugin` loads the [`HighScores`] table for a game. When it is
+/// present, `GameStatePlugin` offers name entry on the game over screen and
+/// a leaderboard from the main menu.
+pub struct HighScorePlugin {
+    game: &'static str,
+    capacity: usize,
+}
+
+impl HighScorePlugin {
+    /// Keeps the best 10 scores for `game`.
+    pub fn new(game: &'static str) -> Self {
+        Self { game, capacity: 10 }
+    }
+
+    /// Changes how many entries the table holds.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+}
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        let dir = DataDir::from_app(app);
+        app.insert_resource(HighScores::load(&dir, self.game, self.capacity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: u32) -> HighScoreEntry {
+        HighScoreEntry { name: name.to_string(), score, date: 0, seed: 0 }
+    }
+
+    #[test]
+    fn test_ranking() {
+        let mut table = HighScores::new("test", 3);
+        assert_eq!(table.insert(entry("a", 10)), Some(0));
+        assert_eq!(table.insert(entry("b", 30)), Some(0));
+        assert_eq!(table.insert(entry("c", 10)), Some(2));
+        assert!(!table.qualifies(5));
+        assert_eq!(table.insert(entry("d", 20)), Some(1));
+        let names: Vec<&str> = table.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["b", "d", "a"]);
+    }
+
+    #[test]
+    fn test_empty_table_has_no_room() {
+        let mut table = HighScores::new("test", 0);
+        assert!(!table.qualifies(100));
+        assert_eq!(table.insert(entry("a", 100)), None);
+        assert!(table.entries().is_empty());
+    }
+
+    #[test]
+    fn test_date_string() {
+        assert_eq!(entry("a", 0).date_string(), "1970-01-01");
+        let leap_day = HighScoreEntry { date: 951_782_400, ..entry("a", 0) };
+        assert_eq!(leap_day.date_string(), "2000-02-29");
+    }
+}
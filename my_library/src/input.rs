@@ -0,0 +1,590 @@
+use bevy::{ecs::system::SystemParam, input::InputSystem, prelude::*, utils::HashMap};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fmt, hash::Hash};
+
+/// Any type can be used as a set of input actions---typically a small
+/// `enum` such as `Flap`, `Confirm` or `MoveX`---as long as it can be
+/// copied, hashed and saved to disk.
+pub trait InputAction:
+    Copy + Eq + Hash + Send + Sync + Serialize + DeserializeOwned + 'static
+{
+}
+
+impl<T> InputAction for T where
+    T: Copy + Eq + Hash + Send + Sync + Serialize + DeserializeOwned + 'static
+{
+}
+
+/// A physical input that can trigger an action.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputBinding {
+    /// A keyboard key.
+    Key(KeyCode),
+    /// A mouse button.
+    Mouse(MouseButton),
+    /// A button on any connected gamepad.
+    GamepadButton(GamepadButtonType),
+    /// A stick or trigger on any connected gamepad, reported as a value
+    /// between -1.0 and 1.0.
+    GamepadAxis(GamepadAxisType),
+    /// Two keys acting as an axis: `negative` reports -1.0 and `positive`
+    /// reports 1.0.
+    KeyAxis { negative: KeyCode, positive: KeyCode },
+}
+
+impl InputBinding {
+    /// Bindings of the same kind replace one another when rebinding, so
+    /// that choosing a new key leaves the gamepad binding alone.
+    fn same_device(&self, other: &InputBinding) -> bool {
+        use InputBinding::*;
+        matches!(
+            (self, other),
+            (Key(_), Key(_))
+                | (KeyAxis { .. }, KeyAxis { .. })
+                | (Mouse(_), Mouse(_))
+                | (GamepadButton(_), GamepadButton(_))
+                | (GamepadAxis(_), GamepadAxis(_))
+        )
+    }
+}
+
+/// Shows the binding the way a player would name it, for example "R",
+/// "Space", "A/D" or "South".
+impl fmt::Display for InputBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputBinding::Key(key) => write!(f, "{}", key_name(key)),
+            InputBinding::Mouse(button) => write!(f, "{button:?} mouse button"),
+            InputBinding::GamepadButton(button) => write!(f, "{button:?}"),
+            InputBinding::GamepadAxis(axis) => write!(f, "{axis:?}"),
+            InputBinding::KeyAxis { negative, positive } => {
+                write!(f, "{}/{}", key_name(negative), key_name(positive))
+            }
+        }
+    }
+}
+
+/// `KeyR` becomes "R" and `Digit1` becomes "1"; other keys keep their names.
+fn key_name(key: &KeyCode) -> String {
+    let name = format!("{key:?}");
+    match name.strip_prefix("Key").or_else(|| name.strip_prefix("Digit")) {
+        Some(short) => short.to_string(),
+        None => name,
+    }
+}
+
+/// How far an axis has to be pushed before it counts as "pressed".
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+
+/// `InputMap` holds the bindings for every action of type `A`. It is
+/// available as a resource, so bindings can be changed at any time.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "A: InputAction")]
+pub struct InputMap<A: InputAction> {
+    bindings: HashMap<A, Vec<InputBinding>>,
+}
+
+impl<A: InputAction> Default for InputMap<A> {
+    fn default() -> Self {
+        Self { bindings: HashMap::new() }
+    }
+}
+
+impl<A: InputAction> InputMap<A> {
+    /// Creates an empty input map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `binding` to `action`. Actions may have as many bindings as
+    /// you like.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use bevy::prelude::*;
+    /// use my_library::{InputBinding, InputMap};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// enum Action { Jump }
+    ///
+    /// let map = InputMap::new()
+    ///     .bind(Action::Jump, InputBinding::Key(KeyCode::Space))
+    ///     .bind(Action::Jump, InputBinding::Mouse(MouseButton::Left));
+    /// assert_eq!(map.bindings(Action::Jump).len(), 2);
+    /// ```
+    pub fn bind(mut self, action: A, binding: InputBinding) -> Self {
+        self.bindings.entry(action).or_default().push(binding);
+        self
+    }
+
+    /// Returns the bindings currently assigned to `action`.
+    pub fn bindings(&self, action: A) -> &[InputBinding] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Describes the bindings for `action` for on-screen prompts, for
+    /// example "R/RightTrigger". Use it instead of hardcoding key names,
+    /// which go stale as soon as the player rebinds them.
+    pub fn describe(&self, action: A) -> String {
+        let names: Vec<String> = self.bindings(action).iter().map(ToString::to_string).collect();
+        if names.is_empty() {
+            "(unbound)".to_string()
+        } else {
+            names.join("/")
+        }
+    }
+
+    /// Replaces the binding for `action` of the same kind as `binding`
+    /// (a key, a pair of keys, a mouse button, a gamepad button or a
+    /// gamepad axis), or adds it if there isn't one.
+    pub fn rebind(&mut self, action: A, binding: InputBinding) {
+        let bindings = self.bindings.entry(action).or_default();
+        match bindings.iter_mut().find(|b| b.same_device(&binding)) {
+            Some(existing) => *existing = binding,
+            None => bindings.push(binding),
+        }
+    }
+
+    /// Fills in any action that `self` doesn't mention with the bindings
+    /// from `defaults`. Used when loading a file saved by an older version
+    /// of the game.
+    fn merge_defaults(&mut self, defaults: &InputMap<A>) {
+        for (action, bindings) in defaults.bindings.iter() {
+            self.bindings
+                .entry(*action)
+                .or_insert_with(|| bindings.clone());
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct ActionData {
+    value: f32,
+    pressed: bool,
+    was_pressed: bool,
+}
+
+/// `ActionState` is updated every frame (in `PreUpdate`) from the
+/// [`InputMap`]. Query it in your systems instead of reading keys directly.
+#[derive(Resource)]
+pub struct ActionState<A: InputAction> {
+    actions: HashMap<A, ActionData>,
+}
+
+impl<A: InputAction> Default for ActionState<A> {
+    fn default() -> Self {
+        Self { actions: HashMap::new() }
+    }
+}
+
+impl<A: InputAction> ActionState<A> {
+    /// Is `action` currently held down?
+    pub fn pressed(&self, action: A) -> bool {
+        self.actions.get(&action).is_some_and(|a| a.pressed)
+    }
+
+    /// Was `action` pressed this frame?
+    pub fn just_pressed(&self, action: A) -> bool {
+        self.actions
+            .get(&action)
+            .is_some_and(|a| a.pressed && !a.was_pressed)
+    }
+
+    /// Was `action` released this frame?
+    pub fn just_released(&self, action: A) -> bool {
+        self.actions
+            .get(&action)
+            .is_some_and(|a| !a.pressed && a.was_pressed)
+    }
+
+    /// The analogue value of `action`, between -1.0 and 1.0. Buttons report
+    /// 1.0 when held.
+    pub fn value(&self, action: A) -> f32 {
+        self.actions.get(&action).map_or(0.0, |a| a.value)
+    }
+}
+
+/// Inputs that set a new binding. They are ignored by every
+/// [`ActionState`], whatever its action type, until they are released, so
+/// the press doesn't also trigger the actions it is (or was) bound to.
+#[derive(Resource, Default)]
+struct ConsumedInputs(Vec<InputBinding>);
+
+/// Set `KeyboardCaptured` to `true` while something else has the keyboard,
+/// such as a text box being typed in. Every [`ActionState`] then ignores
+/// the keys pressed, until they are released.
+#[derive(Resource, Default)]
+pub struct KeyboardCaptured(pub bool);
+
+/// Insert `Rebinding` with an action to have the next button the player
+/// presses (on keyboard, mouse or gamepad), or the next stick or trigger
+/// they push, become that action's binding. An action bound to a pair of
+/// keys takes two presses: the negative key, then the positive one. The
+/// resource returns to `None` once the new binding has been stored.
+#[derive(Resource)]
+pub struct Rebinding<A: InputAction>(pub Option<A>);
+
+impl<A: InputAction> Default for Rebinding<A> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+/// System sets used by [`ActionPlugin`], in `PreUpdate`.
+#[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ActionSystems {
+    /// Listens for new bindings.
+    Rebind,
+    /// Updates every [`ActionState`].
+    Update,
+}
+
+#[derive(Resource)]
+struct BindingsFile<A> {
+    dir: crate::persistence::DataDir,
+    game: &'static str,
+    file: &'static str,
+    _action: std::marker::PhantomData<A>,
+}
+
+/// `ActionPlugin` adds [`InputMap<A>`], [`ActionState<A>`] and
+/// [`Rebinding<A>`] resources for a set of actions.
+///
+/// Call [`ActionPlugin::persist`] to load the player's bindings at startup,
+/// and save them whenever they are changed.
+pub struct ActionPlugin<A: InputAction> {
+    defaults: InputMap<A>,
+    save_file: Option<(&'static str, &'static str)>,
+}
+
+impl<A: InputAction> ActionPlugin<A> {
+    /// Creates the plugin with a set of default bindings.
+    pub fn new(defaults: InputMap<A>) -> Self {
+        Self { defaults, save_file: None }
+    }
+
+    /// Stores the bindings as `file` in `game`'s data folder
+    /// (see [`crate::persistence`]).
+    pub fn persist(mut self, game: &'static str, file: &'static str) -> Self {
+        self.save_file = Some((game, file));
+        self
+    }
+}
+
+impl<A: InputAction> Plugin for ActionPlugin<A> {
+    fn build(&self, app: &mut App) {
+        let mut map = self.defaults.clone();
+        if let Some((game, file)) = self.save_file {
+            let dir = crate::persistence::DataDir::from_app(app);
+            match dir.load::<InputMap<A>>(game, file) {
+                Ok(Some(mut saved)) => {
+                    saved.merge_defaults(&self.defaults);
+                    map = saved;
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Unable to load input bindings from {file}: {e}"),
+            }
+            app.insert_resource(BindingsFile::<A> {
+                dir,
+                game,
+                file,
+                _action: std::marker::PhantomData,
+            });
+        }
+
+        app.insert_resource(map)
+            .init_resource::<ActionState<A>>()
+            .init_resource::<Rebinding<A>>()
+            .init_resource::<ConsumedInputs>()
+            .init_resource::<KeyboardCaptured>()
+            .configure_sets(
+                PreUpdate,
+                (ActionSystems::Rebind, ActionSystems::Update)
+                    .chain()
+                    .after(InputSystem),
+            )
+            .add_systems(
+                PreUpdate,
+                (
+                    listen_for_rebind::<A>.in_set(ActionSystems::Rebind),
+                    update_action_state::<A>.in_set(ActionSystems::Update),
+                ),
+            );
+    }
+}
+
+/// Every device an [`InputBinding`] can read.
+#[derive(SystemParam)]
+struct Inputs<'w> {
+    keyboard: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_buttons: Res<'w, ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl Inputs<'_> {
+    /// The value of `binding`, and whether it counts as pressed. Anything
+    /// in `consumed` reads as released.
+    fn read(&self, binding: InputBinding, consumed: &[InputBinding]) -> (f32, bool) {
+        let key = |key| self.keyboard.pressed(key) && !consumed.contains(&InputBinding::Key(key));
+        let live = !consumed.contains(&binding);
+        match binding {
+            InputBinding::Key(k) => button_value(key(k)),
+            InputBinding::Mouse(button) => button_value(live && self.mouse.pressed(button)),
+            InputBinding::GamepadButton(button) => button_value(
+                live && self
+                    .gamepads
+                    .iter()
+                    .any(|pad| self.gamepad_buttons.pressed(GamepadButton::new(pad, button))),
+            ),
+            InputBinding::GamepadAxis(axis) => {
+                let v = self
+                    .gamepads
+                    .iter()
+                    .filter_map(|pad| self.gamepad_axes.get(GamepadAxis::new(pad, axis)))
+                    .fold(0.0f32, |a, b| if b.abs() > a.abs() { b } else { a });
+                let v = if live { v } else { 0.0 };
+                (v, v.abs() > AXIS_PRESS_THRESHOLD)
+            }
+            InputBinding::KeyAxis { negative, positive } => {
+                let v = key(positive) as i32 - key(negative) as i32;
+                (v as f32, v != 0)
+            }
+        }
+    }
+}
+
+fn update_action_state<A: InputAction>(
+    map: Res<InputMap<A>>,
+    mut state: ResMut<ActionState<A>>,
+    mut consumed: ResMut<ConsumedInputs>,
+    captured: Res<KeyboardCaptured>,
+    inputs: Inputs,
+) {
+    consumed.0.retain(|&binding| inputs.read(binding, &[]).1);
+    if captured.0 {
+        for &key in inputs.keyboard.get_pressed() {
+            if !consumed.0.contains(&InputBinding::Key(key)) {
+                consumed.0.push(InputBinding::Key(key));
+            }
+        }
+    }
+    for (action, bindings) in map.bindings.iter() {
+        let mut value = 0.0f32;
+        let mut pressed = false;
+        for binding in bindings.iter() {
+            let (v, p) = inputs.read(*binding, &consumed.0);
+            if v.abs() > value.abs() {
+                value = v;
+            }
+            pressed |= p;
+        }
+        let data = state.actions.entry(*action).or_default();
+        data.was_pressed = data.pressed;
+        data.pressed = pressed;
+        data.value = value;
+    }
+}
+
+fn button_value(pressed: bool) -> (f32, bool) {
+    (if pressed { 1.0 } else { 0.0 }, pressed)
+}
+
+fn listen_for_rebind<A: InputAction>(
+    mut rebinding: ResMut<Rebinding<A>>,
+    mut map: ResMut<InputMap<A>>,
+    mut consumed: ResMut<ConsumedInputs>,
+    mut negative_key: Local<Option<KeyCode>>,
+    inputs: Inputs,
+    file: Option<Res<BindingsFile<A>>>,
+) {
+    let Some(action) = rebinding.0 else {
+        *negative_key = None;
+        return;
+    };
+
+    let key_axis = map.bindings(action).iter().any(|b| matches!(b, InputBinding::KeyAxis { .. }));
+    let pushed_axis = inputs
+        .gamepad_axes
+        .devices()
+        .find(|axis| inputs.gamepad_axes.get(**axis).is_some_and(|v| v.abs() > AXIS_PRESS_THRESHOLD))
+        .filter(|axis| !consumed.0.contains(&InputBinding::GamepadAxis(axis.axis_type)));
+    // The input that was pressed, and the binding it makes.
+    let (input, binding) = if let Some(&key) = inputs.keyboard.get_just_pressed().next() {
+        let binding = match negative_key.take() {
+            Some(negative) => InputBinding::KeyAxis { negative, positive: key },
+            None if key_axis => {
+                *negative_key = Some(key);
+                consumed.0.push(InputBinding::Key(key));
+                return;
+            }
+            None => InputBinding::Key(key),
+        };
+        (InputBinding::Key(key), binding)
+    } else if let Some(&button) = inputs.mouse.get_just_pressed().next() {
+        (InputBinding::Mouse(button), InputBinding::Mouse(button))
+    } else if let Some(button) = inputs.gamepad_buttons.get_just_pressed().next() {
+        let binding = InputBinding::GamepadButton(button.button_type);
+        (binding, binding)
+    } else if let Some(axis) = pushed_axis {
+        let binding = InputBinding::GamepadAxis(axis.axis_type);
+        (binding, binding)
+    } else {
+        return;
+    };
+
+    // The press that sets the binding is consumed, so it doesn't also
+    // trigger the new action, or whatever else it is bound to.
+    consumed.0.push(input);
+    *negative_key = None;
+    map.rebind(action, binding);
+    rebinding.0 = None;
+    if let Some(file) = file {
+        if let Err(e) = file.dir.save(file.game, file.file, &*map) {
+            warn!("Unable to save input bindings to {}: {e}", file.file);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+    enum TestAction {
+        Jump,
+        Run,
+    }
+
+    #[test]
+    fn test_rebind_keeps_other_devices() {
+        let mut map = InputMap::new()
+            .bind(TestAction::Jump, InputBinding::Key(KeyCode::Space))
+            .bind(TestAction::Jump, InputBinding::GamepadButton(GamepadButtonType::South))
+            .bind(TestAction::Jump, InputBinding::GamepadAxis(GamepadAxisType::LeftStickY));
+        map.rebind(TestAction::Jump, InputBinding::Key(KeyCode::KeyW));
+        map.rebind(TestAction::Jump, InputBinding::GamepadButton(GamepadButtonType::North));
+        assert_eq!(
+            map.bindings(TestAction::Jump),
+            &[
+                InputBinding::Key(KeyCode::KeyW),
+                InputBinding::GamepadButton(GamepadButtonType::North),
+                InputBinding::GamepadAxis(GamepadAxisType::LeftStickY),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bindings_are_described() {
+        let map = InputMap::new()
+            .bind(TestAction::Jump, InputBinding::Key(KeyCode::KeyR))
+            .bind(TestAction::Jump, InputBinding::GamepadButton(GamepadButtonType::South))
+            .bind(TestAction::Jump, InputBinding::KeyAxis { negative: KeyCode::Digit1, positive: KeyCode::Space });
+        assert_eq!(map.describe(TestAction::Jump), "R/South/1/Space");
+        assert_eq!(map.describe(TestAction::Run), "(unbound)");
+    }
+
+    /// An app with `map`, rebinding `action`.
+    fn rebinding_app(map: InputMap<TestAction>, action: TestAction) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, bevy::input::InputPlugin)).add_plugins(ActionPlugin::new(map));
+        app.world_mut().resource_mut::<Rebinding<TestAction>>().0 = Some(action);
+        app
+    }
+
+    /// Presses `key` for a frame, through the input events, so it counts
+    /// as just pressed.
+    fn tap(app: &mut App, key_code: KeyCode) {
+        use bevy::input::{keyboard::{Key, KeyboardInput}, ButtonState};
+        for state in [ButtonState::Pressed, ButtonState::Released] {
+            app.world_mut().send_event(KeyboardInput {
+                key_code,
+                logical_key: Key::Unidentified(bevy::input::keyboard::NativeKey::Unidentified),
+                state,
+                window: Entity::PLACEHOLDER,
+            });
+            app.update();
+        }
+    }
+
+    #[test]
+    fn test_rebinding_key_axis_takes_two_keys() {
+        let mut app = rebinding_app(
+            InputMap::new()
+                .bind(TestAction::Run, InputBinding::KeyAxis { negative: KeyCode::KeyA, positive: KeyCode::KeyD })
+                .bind(TestAction::Run, InputBinding::Key(KeyCode::ShiftLeft)),
+            TestAction::Run,
+        );
+        tap(&mut app, KeyCode::ArrowLeft);
+        assert_eq!(app.world().resource::<Rebinding<TestAction>>().0, Some(TestAction::Run));
+        tap(&mut app, KeyCode::ArrowRight);
+        assert_eq!(app.world().resource::<Rebinding<TestAction>>().0, None);
+        assert_eq!(
+            app.world().resource::<InputMap<TestAction>>().bindings(TestAction::Run),
+            &[
+                InputBinding::KeyAxis { negative: KeyCode::ArrowLeft, positive: KeyCode::ArrowRight },
+                InputBinding::Key(KeyCode::ShiftLeft),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rebinding_to_a_stick() {
+        let mut app = rebinding_app(
+            InputMap::new().bind(TestAction::Run, InputBinding::GamepadAxis(GamepadAxisType::LeftStickX)),
+            TestAction::Run,
+        );
+        let stick = GamepadAxis::new(Gamepad::new(0), GamepadAxisType::RightStickX);
+        app.world_mut().resource_mut::<Axis<GamepadAxis>>().set(stick, -0.9);
+        app.update();
+        assert_eq!(
+            app.world().resource::<InputMap<TestAction>>().bindings(TestAction::Run),
+            &[InputBinding::GamepadAxis(GamepadAxisType::RightStickX)]
+        );
+        // The push that set it doesn't count until the stick is let go.
+        assert_eq!(app.world().resource::<ActionState<TestAction>>().value(TestAction::Run), 0.0);
+    }
+
+    #[test]
+    fn test_captured_keys_are_ignored_until_released() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, bevy::input::InputPlugin))
+            .add_plugins(ActionPlugin::new(InputMap::new().bind(TestAction::Jump, InputBinding::Key(KeyCode::Space))));
+        let jumping = |app: &App| app.world().resource::<ActionState<TestAction>>().pressed(TestAction::Jump);
+        let key = |app: &mut App, pressed: bool| {
+            let mut keyboard = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            if pressed { keyboard.press(KeyCode::Space) } else { keyboard.release(KeyCode::Space) }
+        };
+
+        app.world_mut().resource_mut::<KeyboardCaptured>().0 = true;
+        key(&mut app, true);
+        app.update();
+        assert!(!jumping(&app));
+        // Still held once the text box lets go, so it still doesn't count.
+        app.world_mut().resource_mut::<KeyboardCaptured>().0 = false;
+        app.update();
+        assert!(!jumping(&app));
+
+        key(&mut app, false);
+        app.update();
+        key(&mut app, true);
+        app.update();
+        assert!(jumping(&app));
+    }
+
+    #[test]
+    fn test_saved_bindings_merge_defaults() {
+        let defaults = InputMap::new()
+            .bind(TestAction::Jump, InputBinding::Key(KeyCode::Space))
+            .bind(TestAction::Run, InputBinding::Key(KeyCode::ShiftLeft));
+        let saved = InputMap::new().bind(TestAction::Jump, InputBinding::Key(KeyCode::KeyW));
+        let text = ron::to_string(&saved).unwrap();
+        let mut loaded: InputMap<TestAction> = ron::from_str(&text).unwrap();
+        loaded.merge_defaults(&defaults);
+        assert_eq!(loaded.bindings(TestAction::Jump), &[InputBinding::Key(KeyCode::KeyW)]);
+        assert_eq!(loaded.bindings(TestAction::Run), &[InputBinding::Key(KeyCode::ShiftLeft)]);
+    }
+}
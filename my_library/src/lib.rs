@@ -5,6 +5,15 @@
 //! `my_library` includes:
 //! 
 //! * Random number generation facilities.
+//! * A game state framework with main menu and a game over screen
+//!   that shows each game's results.
+//! * Action-based input mapping, with rebinding saved to disk.
+//! * Helpers for storing game data in the user's data directory.
+//! * Persistent high score tables, with name entry and a leaderboard.
+//! * Suspend and resume support for games, via save files.
+//! * 2D collision detection with boxes, circles and capsules.
+//! * Sprite animation from texture atlases.
+//! * A headless test harness for game flows (with the `testing` feature).
 //! 
 //! ## Feature Flags
 //! 
@@ -13,13 +22,22 @@
 //! ### Random Number Generation
 //! 
 //! * The `locking` feature enables interior mutability inside
-//! [`RandomNumberGenerator`],
+//!   [`RandomNumberGenerator`],
 //!   allowing it to be used as a resource (`Res<RandomNumberGenerator>`)
-//! rather than requiring mutability (`ResMut<RandomNumberGenerator>`)
+//!   rather than requiring mutability (`ResMut<RandomNumberGenerator>`)
 //! * You can control which random number generation algorithm is used by
-//! specifying *one* of:
+//!   specifying *one* of:
 //!    * `xorshift` to use the XorShift algorithm.
 //!    * `pcg` to use the PCG algorithm.
+//!    * `chacha` to use the ChaCha algorithm behind `rand`'s `StdRng`.
+//!
+//! ### Testing
+//!
+//! * The `testing` feature adds the [`testing`] module, a harness for
+//!   running games headless in tests.
+
+#[cfg(not(any(feature = "pcg", feature = "xorshift", feature = "chacha")))]
+compile_error!("my_library needs a random number generator: enable `pcg`, `xorshift` or `chacha`");
 
 #[cfg(not(feature = "locking"))]
 mod random;
@@ -34,6 +52,26 @@ pub use random_locking::*;
 mod bevy_framework;
 pub use bevy_framework::*;
 
+mod input;
+pub use input::*;
+
+pub mod persistence;
+
+mod high_scores;
+pub use high_scores::*;
+
+mod save_game;
+pub use save_game::*;
+
+mod collision;
+pub use collision::*;
+
+mod animation;
+pub use animation::*;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
 /// [`RandomNumberGenerator`] wraps the `rand` crate. The `rand` crate
 /// is re-exported for your convenience.
 pub mod rand {
@@ -68,6 +106,27 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_saved_generator_carries_on() {
+        let mut rng = RandomNumberGenerator::seeded(7);
+        (0..10).for_each(|_| { rng.range(0..100); });
+        let text = ron::to_string(&rng).unwrap();
+        let mut restored: RandomNumberGenerator = ron::from_str(&text).unwrap();
+        assert_eq!(restored.seed(), 7);
+        (0..1000).for_each(|_| {
+            assert_eq!(rng.range(u32::MIN..u32::MAX), restored.range(u32::MIN..u32::MAX));
+        });
+    }
+
+    #[test]
+    fn test_saved_generator_shape() {
+        // With or without `locking`, a generator is saved as the same
+        // struct, so saves move between builds.
+        let text = ron::to_string(&RandomNumberGenerator::seeded(7)).unwrap();
+        assert!(text.starts_with("(rng:"), "{text}");
+        assert!(text.ends_with(",seed:7)"), "{text}");
+    }
+
     #[test]
     fn test_next_types() {
         let mut rng = RandomNumberGenerator::new();
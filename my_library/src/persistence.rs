@@ -0,0 +1,162 @@
+//! Small helpers for keeping game data (settings, scores, saves) on disk.
+//!
+//! Everything is stored as [RON](https://crates.io/crates/ron) inside a
+//! per-game folder in the user's data directory. Writes go to a temporary
+//! file that is renamed over the original, so a crash part-way through a
+//! save never leaves a truncated file behind.
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Setting this environment variable overrides the root data directory.
+/// Handy for keeping several profiles side by side.
+pub const DATA_DIR_VAR: &str = "MY_LIBRARY_DATA_DIR";
+
+/// The root folder that games keep their data in. Apps hold one as a
+/// resource, which the library's plugins add if it is missing, so each
+/// app (and each test) can have a folder of its own. The free functions
+/// in this module use the default folder.
+#[derive(Resource, Clone, Debug, PartialEq, Eq)]
+pub struct DataDir(PathBuf);
+
+impl Default for DataDir {
+    /// [`DATA_DIR_VAR`] if it is set, otherwise a folder in the user's
+    /// data directory.
+    fn default() -> Self {
+        let root = std::env::var_os(DATA_DIR_VAR)
+            .map(PathBuf::from)
+            .or_else(|| dirs::data_dir().map(|dir| dir.join("more_hands_on_rust")))
+            .unwrap_or_else(|| PathBuf::from("saves"));
+        Self(root)
+    }
+}
+
+impl DataDir {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self(root.into())
+    }
+
+    /// The app's data folder, adding the default if it has none yet.
+    pub fn from_app(app: &mut App) -> Self {
+        app.world_mut().get_resource_or_insert_with(Self::default).clone()
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.0
+    }
+
+    /// Returns the folder `game` stores its data in, creating it if needed.
+    pub fn game(&self, game: &str) -> io::Result<PathBuf> {
+        let dir = self.0.join(game);
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    /// Serializes `value` and stores it as `file` in the game's folder.
+    pub fn save<T: Serialize>(&self, game: &str, file: &str, value: &T) -> io::Result<()> {
+        save_file(&self.game(game)?.join(file), value)
+    }
+
+    /// Loads `file` from the game's folder. Returns `Ok(None)` if the file
+    /// doesn't exist yet.
+    pub fn load<T: DeserializeOwned>(&self, game: &str, file: &str) -> io::Result<Option<T>> {
+        load_file(&self.game(game)?.join(file))
+    }
+
+    /// Removes `file` from the game's folder, if it exists.
+    pub fn delete(&self, game: &str, file: &str) -> io::Result<()> {
+        match fs::remove_file(self.game(game)?.join(file)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Returns `true` if `file` exists in the game's folder.
+    pub fn exists(&self, game: &str, file: &str) -> bool {
+        self.game(game)
+            .map(|dir| dir.join(file).is_file())
+            .unwrap_or(false)
+    }
+}
+
+/// Returns the folder `game` stores its data in, creating it if needed.
+pub fn data_dir(game: &str) -> io::Result<PathBuf> {
+    DataDir::default().game(game)
+}
+
+/// Serializes `value` and stores it as `file` in the game's data folder.
+pub fn save<T: Serialize>(game: &str, file: &str, value: &T) -> io::Result<()> {
+    DataDir::default().save(game, file, value)
+}
+
+/// Loads `file` from the game's data folder. Returns `Ok(None)` if the
+/// file doesn't exist yet.
+pub fn load<T: DeserializeOwned>(game: &str, file: &str) -> io::Result<Option<T>> {
+    DataDir::default().load(game, file)
+}
+
+/// Removes `file` from the game's data folder, if it exists.
+pub fn delete(game: &str, file: &str) -> io::Result<()> {
+    DataDir::default().delete(game, file)
+}
+
+/// Returns `true` if `file` exists in the game's data folder.
+pub fn exists(game: &str, file: &str) -> bool {
+    DataDir::default().exists(game, file)
+}
+
+/// Serializes `value` to RON and writes it atomically to `path`.
+pub fn save_file<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let text = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    write_atomic(path, text.as_bytes())
+}
+
+/// Reads and deserializes a RON file. Returns `Ok(None)` if it doesn't exist.
+pub fn load_file<T: DeserializeOwned>(path: &Path) -> io::Result<Option<T>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    ron::from_str(&text)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Writes `bytes` to a temporary file next to `path`, flushes it to disk
+/// and then renames it into place.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp = PathBuf::from(tmp_name);
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = std::env::temp_dir().join(format!("my_library_persist_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("numbers.ron");
+
+        assert_eq!(load_file::<Vec<u32>>(&path).unwrap(), None);
+        save_file(&path, &vec![1u32, 2, 3]).unwrap();
+        assert_eq!(load_file::<Vec<u32>>(&path).unwrap(), Some(vec![1, 2, 3]));
+        assert!(!path.with_extension("ron.tmp").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
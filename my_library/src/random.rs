@@ -1,4 +1,5 @@
 use bevy::prelude::{App, Plugin, Resource};
+use serde::{Deserialize, Serialize};
 use rand::{
     Rng, SeedableRng,
     distributions::Standard,
@@ -6,8 +7,9 @@ use rand::{
     prelude::Distribution,
 };
 
-#[cfg(all(not(feature = "pcg"), not(feature = "xorshift")))]
-type RngCore = rand::prelude::StdRng;
+// The algorithm behind `StdRng`, which can be saved.
+#[cfg(all(feature = "chacha", not(feature = "pcg"), not(feature = "xorshift")))]
+type RngCore = rand_chacha::ChaCha12Rng;
 
 #[cfg(feature = "pcg")]
 type RngCore = rand_pcg::Pcg64Mcg;
@@ -38,18 +40,20 @@ type RngCore = rand_xorshift::XorShiftRng;
 /// let random_number = my_rng.range(1..10);
 /// println!("{random_number}");
 /// ```
-#[derive(Resource)]
+///
+/// Serializing a generator records exactly where it is in its sequence,
+/// so a deserialized copy carries on with the same numbers.
+#[derive(Resource, Serialize, Deserialize)]
 pub struct RandomNumberGenerator {
     rng: RngCore,
+    seed: u64,
 }
 
 impl RandomNumberGenerator {
     /// Creates a default `RandomNumberGenerator`, with a randomly
     /// selected starting seed.
     pub fn new() -> Self {
-        Self {
-            rng: RngCore::from_entropy(),
-        }
+        Self::seeded(rand::random())
     }
 
     /// Generates a new random number of the requested type.
@@ -100,8 +104,22 @@ impl RandomNumberGenerator {
     pub fn seeded(seed: u64) -> Self {
         Self {
             rng: RngCore::seed_from_u64(seed),
+            seed,
         }
     }
+
+    /// Returns the seed this generator started from. Storing it alongside
+    /// a result (such as a high score) lets you replay the same sequence.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restarts the generator from `seed`, as if it had been created with
+    /// [`RandomNumberGenerator::seeded`].
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = RngCore::seed_from_u64(seed);
+        self.seed = seed;
+    }
 }
 
 impl Default for RandomNumberGenerator {
@@ -1,14 +1,19 @@
 use bevy::prelude::{App, Plugin, Resource};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use rand::{
     Rng, SeedableRng,
     distributions::Standard,
     distributions::uniform::{SampleRange, SampleUniform},
     prelude::Distribution,
 };
-use std::sync::Mutex;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
 
-#[cfg(all(not(feature = "pcg"), not(feature = "xorshift")))]
-type RngCore = rand::prelude::StdRng;
+// The algorithm behind `StdRng`, which can be saved.
+#[cfg(all(feature = "chacha", not(feature = "pcg"), not(feature = "xorshift")))]
+type RngCore = rand_chacha::ChaCha12Rng;
 
 #[cfg(feature = "pcg")]
 type RngCore = rand_pcg::Pcg64Mcg;
@@ -19,13 +24,12 @@ type RngCore = rand_xorshift::XorShiftRng;
 #[derive(Resource)]
 pub struct RandomNumberGenerator {
     rng: Mutex<RngCore>,
+    seed: AtomicU64,
 }
 
 impl RandomNumberGenerator {
     pub fn new() -> Self {
-        Self {
-            rng: Mutex::new(RngCore::from_entropy()),
-        }
+        Self::seeded(rand::random())
     }
 
     pub fn next<T>(&self) -> T
@@ -46,8 +50,42 @@ impl RandomNumberGenerator {
     pub fn seeded(seed: u64) -> Self {
         Self {
             rng: Mutex::new(RngCore::seed_from_u64(seed)),
+            seed: AtomicU64::new(seed),
         }
     }
+
+    pub fn seed(&self) -> u64 {
+        self.seed.load(Ordering::Relaxed)
+    }
+
+    pub fn reseed(&self, seed: u64) {
+        let mut lock = self.rng.lock().unwrap();
+        *lock = RngCore::seed_from_u64(seed);
+        self.seed.store(seed, Ordering::Relaxed);
+    }
+}
+
+/// The generator as it is saved: the same shape as the generator without
+/// the `locking` feature, so a save works with either build.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "RandomNumberGenerator")]
+struct SavedGenerator<R> {
+    rng: R,
+    seed: u64,
+}
+
+impl Serialize for RandomNumberGenerator {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let lock = self.rng.lock().unwrap();
+        SavedGenerator { rng: &*lock, seed: self.seed() }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RandomNumberGenerator {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let SavedGenerator { rng, seed } = SavedGenerator::<RngCore>::deserialize(deserializer)?;
+        Ok(Self { rng: Mutex::new(rng), seed: AtomicU64::new(seed) })
+    }
 }
 
 impl Default for RandomNumberGenerator {
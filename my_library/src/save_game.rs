@@ -0,0 +1,329 @@
+use crate::{
+    bevy_framework::{MenuElement, MenuResource},
+    persistence::DataDir,
+    ActionState, InputMap, MenuAction, RandomNumberGenerator,
+};
+use bevy::{
+    ecs::world::EntityRef,
+    prelude::*,
+    state::state::FreelyMutableState,
+    utils::get_short_name,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::BTreeMap, marker::PhantomData};
+
+const SAVE_FILE: &str = "savegame.ron";
+
+/// Send `SaveGame` to write the current state, the registered resources
+/// and components, and the random number generator to disk. The save
+/// happens at the end of the frame.
+#[derive(Event, Default)]
+pub struct SaveGame;
+
+/// The contents of a save file. Every value is stored as a RON string,
+/// keyed by the short type name of the resource or component.
+#[derive(Serialize, Deserialize)]
+struct SaveFile {
+    version: u32,
+    state: String,
+    /// The random number generator, exactly where it was in its sequence.
+    rng: Option<String>,
+    resources: BTreeMap<String, String>,
+    entities: Vec<BTreeMap<String, String>>,
+}
+
+type LoadResult = Result<(), ron::error::SpannedError>;
+
+#[derive(Clone)]
+struct SaveableResource {
+    key: String,
+    save: fn(&World) -> Option<String>,
+    load: fn(&mut World, &str) -> LoadResult,
+}
+
+#[derive(Clone)]
+struct SaveableComponent {
+    key: String,
+    save: fn(&EntityRef) -> Option<String>,
+    load: fn(&mut EntityWorldMut, &str) -> LoadResult,
+}
+
+fn to_ron<T: Serialize>(value: &T) -> Option<String> {
+    ron::to_string(value)
+        .map_err(|e| warn!("Unable to save {}: {e}", get_short_name(std::any::type_name::<T>())))
+        .ok()
+}
+
+fn save_resource<R: Resource + Serialize>(world: &World) -> Option<String> {
+    world.get_resource::<R>().and_then(to_ron)
+}
+
+fn load_resource<R: Resource + DeserializeOwned>(
+    world: &mut World,
+    text: &str,
+) -> LoadResult {
+    world.insert_resource(ron::from_str::<R>(text)?);
+    Ok(())
+}
+
+fn save_component<C: Component + Serialize>(entity: &EntityRef) -> Option<String> {
+    entity.get::<C>().and_then(to_ron)
+}
+
+fn load_component<C: Component + DeserializeOwned>(
+    entity: &mut EntityWorldMut,
+    text: &str,
+) -> LoadResult {
+    entity.insert(ron::from_str::<C>(text)?);
+    Ok(())
+}
+
+#[derive(Resource, Clone)]
+struct SaveRegistry<S> {
+    game: &'static str,
+    version: u32,
+    resources: Vec<SaveableResource>,
+    components: Vec<SaveableComponent>,
+    _state: PhantomData<S>,
+}
+
+/// A save file that has been read from disk, waiting to be applied once
+/// the game's starting state has finished setting up.
+#[derive(Resource)]
+struct PendingLoad(SaveFile);
+
+#[derive(Component)]
+struct ContinuePrompt;
+
+/// `SaveGamePlugin` lets a game suspend and resume. Mark the resources and
+/// components that describe your game as saveable, and send [`SaveGame`]
+/// whenever there is something worth keeping.
+///
+/// When a save exists, the main menu offers "Continue" (`C`). Continuing
+/// enters the game's starting state as usual, then replaces the saveable
+/// resources, respawns saved entities (with only their saveable
+/// components---add visuals in your own systems), restores the
+/// [`RandomNumberGenerator`] and moves to the state the game was saved in.
+///
+/// The save is deleted when the game reaches its game over state.
+///
+/// `SaveGamePlugin` requires `GameStatePlugin` for the same state type.
+///
+/// ## Example
+///
+/// ```ignore
+/// app.add_plugins(
+///     SaveGamePlugin::<GamePhase>::new("pig", 1)
+///         .resource::<Scores>()
+///         .component::<HandDie>(),
+/// );
+/// ```
+pub struct SaveGamePlugin<S> {
+    registry: SaveRegistry<S>,
+}
+
+impl<S> SaveGamePlugin<S>
+where
+    S: FreelyMutableState + Serialize + DeserializeOwned,
+{
+    /// Creates the plugin. Saves written with a different `version` are
+    /// ignored, so bump it whenever the saved types change.
+    pub fn new(game: &'static str, version: u32) -> Self {
+        Self {
+            registry: SaveRegistry {
+                game,
+                version,
+                resources: Vec::new(),
+                components: Vec::new(),
+                _state: PhantomData,
+            },
+        }
+    }
+
+    /// Includes resource `R` in save games.
+    pub fn resource<R: Resource + Serialize + DeserializeOwned>(mut self) -> Self {
+        self.registry.resources.push(SaveableResource {
+            key: get_short_name(std::any::type_name::<R>()),
+            save: save_resource::<R>,
+            load: load_resource::<R>,
+        });
+        self
+    }
+
+    /// Saves every entity that has component `C`, along with any other
+    /// saveable components it has.
+    pub fn component<C: Component + Serialize + DeserializeOwned>(mut self) -> Self {
+        self.registry.components.push(SaveableComponent {
+            key: get_short_name(std::any::type_name::<C>()),
+            save: save_component::<C>,
+            load: load_component::<C>,
+        });
+        self
+    }
+}
+
+impl<S> Plugin for SaveGamePlugin<S>
+where
+    S: FreelyMutableState + Copy + Serialize + DeserializeOwned,
+{
+    fn build(&self, app: &mut App) {
+        DataDir::from_app(app);
+        app.add_event::<SaveGame>()
+            .insert_resource(self.registry.clone())
+            .add_systems(Last, save_world::<S>.run_if(on_event::<SaveGame>()))
+            .add_systems(PostUpdate, apply_pending_load::<S>.run_if(resource_exists::<PendingLoad>));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(menus) = app.world().get_resource::<MenuResource<S>>() else {
+            panic!("SaveGamePlugin requires GameStatePlugin");
+        };
+        let (menu_state, game_end_state) = (menus.menu_state, menus.game_end_state);
+        app.add_systems(OnEnter(menu_state), show_continue_prompt::<S>)
+            .add_systems(Update, continue_game::<S>.run_if(in_state(menu_state)))
+            .add_systems(OnEnter(game_end_state), delete_save::<S>);
+    }
+}
+
+fn save_world<S>(world: &mut World)
+where
+    S: FreelyMutableState + Serialize,
+{
+    world.resource_mut::<Events<SaveGame>>().clear();
+    let registry = world.resource::<SaveRegistry<S>>().clone();
+    let Some(state) = to_ron(world.resource::<State<S>>().get()) else {
+        return;
+    };
+
+    // A restored game continues with exactly the same random numbers, and
+    // the generator keeps the seed the game started from.
+    let rng = world.get_resource::<RandomNumberGenerator>().and_then(to_ron);
+
+    let resources = registry
+        .resources
+        .iter()
+        .filter_map(|r| (r.save)(world).map(|text| (r.key.clone(), text)))
+        .collect();
+    let entities = world
+        .iter_entities()
+        .map(|entity| {
+            registry
+                .components
+                .iter()
+                .filter_map(|c| (c.save)(&entity).map(|text| (c.key.clone(), text)))
+                .collect::<BTreeMap<_, _>>()
+        })
+        .filter(|components| !components.is_empty())
+        .collect();
+
+    let file = SaveFile { version: registry.version, state, rng, resources, entities };
+    if let Err(e) = world.resource::<DataDir>().save(registry.game, SAVE_FILE, &file) {
+        warn!("Unable to save the game: {e}");
+    }
+}
+
+fn read_save<S: Send + Sync + 'static>(dir: &DataDir, registry: &SaveRegistry<S>) -> Option<SaveFile> {
+    match dir.load::<SaveFile>(registry.game, SAVE_FILE) {
+        Ok(Some(file)) if file.version == registry.version => Some(file),
+        Ok(Some(file)) => {
+            warn!("Ignoring save game from version {} (expected {})", file.version, registry.version);
+            None
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Unable to read save game: {e}");
+            None
+        }
+    }
+}
+
+fn show_continue_prompt<S: Send + Sync + 'static>(
+    mut commands: Commands,
+    registry: Res<SaveRegistry<S>>,
+    dir: Res<DataDir>,
+    map: Res<InputMap<MenuAction>>,
+) {
+    if dir.exists(registry.game, SAVE_FILE) {
+        commands
+            .spawn(Text2dBundle {
+                text: Text::from_section(
+                    format!("Press {} to continue your saved game", map.describe(MenuAction::Continue)),
+                    TextStyle { font_size: 32.0, ..default() },
+                ),
+                transform: Transform::from_xyz(0.0, -340.0, 2.0),
+                ..default()
+            })
+            .insert(ContinuePrompt)
+            .insert(MenuElement);
+    }
+}
+
+fn continue_game<S>(
+    mut commands: Commands,
+    actions: Res<ActionState<MenuAction>>,
+    registry: Res<SaveRegistry<S>>,
+    dir: Res<DataDir>,
+    menus: Res<MenuResource<S>>,
+    mut state: ResMut<NextState<S>>,
+    prompt: Query<Entity, With<ContinuePrompt>>,
+) where
+    S: FreelyMutableState + Copy,
+{
+    if !actions.just_pressed(MenuAction::Continue) {
+        return;
+    }
+    if let Some(file) = read_save(&dir, &registry) {
+        commands.insert_resource(PendingLoad(file));
+        prompt.iter().for_each(|entity| commands.entity(entity).despawn());
+        state.set(menus.game_start_state);
+    }
+}
+
+fn apply_pending_load<S>(world: &mut World)
+where
+    S: FreelyMutableState + Copy + DeserializeOwned,
+{
+    let game_start_state = world.resource::<MenuResource<S>>().game_start_state;
+    if *world.resource::<State<S>>().get() != game_start_state {
+        // Wait until the starting state's setup has run.
+        return;
+    }
+    let Some(PendingLoad(file)) = world.remove_resource::<PendingLoad>() else {
+        return;
+    };
+    let registry = world.resource::<SaveRegistry<S>>().clone();
+
+    for resource in registry.resources.iter() {
+        if let Some(text) = file.resources.get(&resource.key) {
+            if let Err(e) = (resource.load)(world, text) {
+                warn!("Unable to restore {}: {e}", resource.key);
+            }
+        }
+    }
+    for saved in file.entities.iter() {
+        let mut entity = world.spawn_empty();
+        for component in registry.components.iter() {
+            if let Some(text) = saved.get(&component.key) {
+                if let Err(e) = (component.load)(&mut entity, text) {
+                    warn!("Unable to restore {}: {e}", component.key);
+                }
+            }
+        }
+    }
+    if let Some(text) = &file.rng {
+        match ron::from_str::<RandomNumberGenerator>(text) {
+            Ok(rng) => world.insert_resource(rng),
+            Err(e) => warn!("Unable to restore the random number generator: {e}"),
+        }
+    }
+    match ron::from_str::<S>(&file.state) {
+        Ok(state) => world.resource_mut::<NextState<S>>().set(state),
+        Err(e) => warn!("Unable to restore the game state: {e}"),
+    }
+}
+
+fn delete_save<S: Send + Sync + 'static>(registry: Res<SaveRegistry<S>>, dir: Res<DataDir>) {
+    if let Err(e) = dir.delete(registry.game, SAVE_FILE) {
+        warn!("Unable to delete save game: {e}");
+    }
+}
@@ -0,0 +1,228 @@
+//! A headless harness for testing game flows without a window.
+//!
+//! Enable the `testing` feature (usually as a dev-dependency) to use it.
+//!
+//! ```ignore
+//! #[test]
+//! fn crashing_ends_the_game() {
+//!     let mut game = TestApp::new(42, add_game);
+//!     game.tap(KeyCode::KeyP);
+//!     game.run_until_state(GamePhase::Flapping, 120);
+//!     game.run_until_state(GamePhase::GameOver, 600);
+//!     assert!(game.has_resource::<GameResults>());
+//! }
+//! ```
+use crate::{persistence::DataDir, RandomNumberGenerator};
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput, NativeKey, NativeKeyCode},
+        ButtonState, InputPlugin,
+    },
+    prelude::*,
+    state::{app::StatesPlugin, state::FreelyMutableState},
+    time::TimeUpdateStrategy,
+    window::ExitCondition,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How many times `FixedUpdate` has run.
+#[derive(Resource, Default)]
+struct FixedTicks(u64);
+
+fn count_fixed_ticks(mut ticks: ResMut<FixedTicks>) {
+    ticks.0 += 1;
+}
+
+/// An [`App`] with everything a game needs to run headless: no window,
+/// no rendering and no audio. Each frame advances time by the fixed
+/// timestep, so frames and fixed ticks line up and tests are repeatable.
+///
+/// Game data (controls, high scores and saves) is written to a temporary
+/// folder of the app's own rather than the player's data directory, and
+/// the folder is deleted when the app is dropped.
+pub struct TestApp {
+    pub app: App,
+}
+
+impl TestApp {
+    /// Builds a headless app, lets `add_game` add the game's plugins and
+    /// systems (including `GameStatePlugin`), and provides a
+    /// [`RandomNumberGenerator`] seeded with `seed`.
+    pub fn new(seed: u64, add_game: impl FnOnce(&mut App)) -> Self {
+        // Tests run side by side, so each app gets a folder of its own.
+        static APPS: AtomicUsize = AtomicUsize::new(0);
+        let app_id = APPS.fetch_add(1, Ordering::Relaxed);
+        let data_dir = std::env::temp_dir().join(format!("my_library_tests_{}_{app_id}", std::process::id()));
+        // Process ids get reused, so clear out anything an earlier run left.
+        let _ = std::fs::remove_dir_all(&data_dir);
+
+        let mut app = App::new();
+        app.insert_resource(DataDir::new(data_dir));
+        app.add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin::default(), InputPlugin))
+            // Window events exist, but no window is opened.
+            .add_plugins(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .init_asset::<Image>()
+            .init_asset::<TextureAtlasLayout>()
+            // Lets plugins that ship shaders, such as `bevy_egui`, load them.
+            .init_asset::<bevy::render::render_resource::Shader>()
+            .init_resource::<FixedTicks>()
+            .add_systems(FixedFirst, count_fixed_ticks);
+        add_game(&mut app);
+
+        let timestep = app.world().resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+        app.insert_resource(RandomNumberGenerator::seeded(seed));
+        app.finish();
+        app.cleanup();
+        Self { app }
+    }
+
+    /// Runs one frame.
+    pub fn update(&mut self) -> &mut Self {
+        self.app.update();
+        self
+    }
+
+    /// Runs `frames` frames.
+    pub fn advance_frames(&mut self, frames: usize) -> &mut Self {
+        for _ in 0..frames {
+            self.app.update();
+        }
+        self
+    }
+
+    /// Runs frames until `FixedUpdate` has run `ticks` more times.
+    pub fn advance_fixed(&mut self, ticks: u64) -> &mut Self {
+        let target = self.app.world().resource::<FixedTicks>().0 + ticks;
+        while self.app.world().resource::<FixedTicks>().0 < target {
+            self.app.update();
+        }
+        self
+    }
+
+    fn send_key(&mut self, key_code: KeyCode, logical_key: Key, state: ButtonState) {
+        self.app.world_mut().send_event(KeyboardInput {
+            key_code,
+            logical_key,
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+
+    /// Holds `key` down from the next frame until [`release`](Self::release)
+    /// is called.
+    pub fn press(&mut self, key: KeyCode) -> &mut Self {
+        self.send_key(key, Key::Unidentified(NativeKey::Unidentified), ButtonState::Pressed);
+        self
+    }
+
+    /// Lets go of `key` on the next frame.
+    pub fn release(&mut self, key: KeyCode) -> &mut Self {
+        self.send_key(key, Key::Unidentified(NativeKey::Unidentified), ButtonState::Released);
+        self
+    }
+
+    /// Presses `key` for one frame, then releases it.
+    pub fn tap(&mut self, key: KeyCode) -> &mut Self {
+        self.press(key).update();
+        self.release(key).update()
+    }
+
+    /// Types `text` one character at a time, as a player entering their
+    /// name would.
+    pub fn type_text(&mut self, text: &str) -> &mut Self {
+        for c in text.chars() {
+            for state in [ButtonState::Pressed, ButtonState::Released] {
+                let key_code = KeyCode::Unidentified(NativeKeyCode::Unidentified);
+                self.send_key(key_code, Key::Character(c.to_string().into()), state);
+                self.app.update();
+            }
+        }
+        self
+    }
+
+    /// Presses Enter, as logical and physical keys.
+    pub fn enter(&mut self) -> &mut Self {
+        for state in [ButtonState::Pressed, ButtonState::Released] {
+            self.send_key(KeyCode::Enter, Key::Enter, state);
+            self.app.update();
+        }
+        self
+    }
+
+    /// The current game state.
+    pub fn state<S: States>(&self) -> S {
+        self.app.world().resource::<State<S>>().get().clone()
+    }
+
+    /// Asks the game to move to `state`, as a system would.
+    pub fn set_state<S: FreelyMutableState>(&mut self, state: S) -> &mut Self {
+        self.app.world_mut().resource_mut::<NextState<S>>().set(state);
+        self
+    }
+
+    /// Panics unless the game is in `state`.
+    #[track_caller]
+    pub fn assert_state<S: States>(&self, state: S) {
+        assert_eq!(self.state::<S>(), state, "unexpected game state");
+    }
+
+    /// Runs frames until the game is in `state`. Panics if that takes more
+    /// than `max_frames` frames.
+    #[track_caller]
+    pub fn run_until_state<S: States>(&mut self, state: S, max_frames: usize) -> &mut Self {
+        for _ in 0..max_frames {
+            if self.state::<S>() == state {
+                return self;
+            }
+            self.app.update();
+        }
+        panic!("still in {:?} after {max_frames} frames, expected {state:?}", self.state::<S>());
+    }
+
+    /// Is resource `R` present?
+    pub fn has_resource<R: Resource>(&self) -> bool {
+        self.app.world().contains_resource::<R>()
+    }
+
+    /// Resource `R`. Panics if it isn't present.
+    #[track_caller]
+    pub fn resource<R: Resource>(&self) -> &R {
+        self.app.world().resource::<R>()
+    }
+
+    /// Resource `R`, to be changed by the test.
+    #[track_caller]
+    pub fn resource_mut<R: Resource>(&mut self) -> Mut<'_, R> {
+        self.app.world_mut().resource_mut::<R>()
+    }
+
+    /// How many entities have component `C`.
+    pub fn count<C: Component>(&mut self) -> usize {
+        let mut query = self.app.world_mut().query_filtered::<(), With<C>>();
+        query.iter(self.app.world()).count()
+    }
+
+    /// Panics unless exactly `expected` entities have component `C`.
+    #[track_caller]
+    pub fn assert_count<C: Component>(&mut self, expected: usize) {
+        assert_eq!(
+            self.count::<C>(),
+            expected,
+            "unexpected number of {} entities",
+            std::any::type_name::<C>()
+        );
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        if let Some(dir) = self.app.world().get_resource::<DataDir>() {
+            let _ = std::fs::remove_dir_all(dir.root());
+        }
+    }
+}
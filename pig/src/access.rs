@@ -0,0 +1,232 @@
+//! Settings for players who need more than the default look: high-contrast
+//! windows, bigger text, seat colours that colour-blind players can tell
+//! apart, and every roll read out to screen readers.
+use crate::{Game, GameHistory, Seats, Tumble, MAX_SEATS};
+use bevy::a11y::{
+    accesskit::{Live, NodeBuilder, Role},
+    AccessibilityNode,
+};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiSettings};
+use my_library::persistence::DataDir;
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_FILE: &str = "accessibility.ron";
+
+/// Each seat's dice are a different colour.
+const STANDARD_COLORS: [Color; MAX_SEATS] = [
+    Color::WHITE,
+    Color::LinearRgba(LinearRgba { red: 0.0, green: 0.0, blue: 1.0, alpha: 1.0 }),
+    Color::srgb(0.9, 0.2, 0.2),
+    Color::srgb(0.2, 0.8, 0.2),
+    Color::srgb(0.95, 0.85, 0.2),
+    Color::srgb(0.7, 0.3, 0.9),
+    Color::srgb(1.0, 0.55, 0.1),
+    Color::srgb(0.2, 0.85, 0.9),
+];
+
+/// The Okabe-Ito palette, which stays distinct with every common kind of
+/// colour blindness. White stands in for its black, which would hide the
+/// pips.
+const COLOR_BLIND_COLORS: [Color; MAX_SEATS] = [
+    Color::WHITE,
+    Color::srgb(0.9, 0.6, 0.0),
+    Color::srgb(0.34, 0.71, 0.91),
+    Color::srgb(0.0, 0.62, 0.45),
+    Color::srgb(0.94, 0.89, 0.26),
+    Color::srgb(0.0, 0.45, 0.7),
+    Color::srgb(0.84, 0.37, 0.0),
+    Color::srgb(0.8, 0.47, 0.65),
+];
+
+/// A shape for each seat, so seats can be told apart without colour.
+const SEAT_MARKERS: [&str; MAX_SEATS] = ["●", "▲", "■", "◆", "★", "✚", "▼", "✖"];
+
+/// Which colours the seats get.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Palette {
+    #[default]
+    Standard,
+    ColorBlind,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 2] = [Self::Standard, Self::ColorBlind];
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::Standard => "Standard",
+            Self::ColorBlind => "Colour-blind safe",
+        }
+    }
+
+    fn colors(self) -> &'static [Color; MAX_SEATS] {
+        match self {
+            Self::Standard => &STANDARD_COLORS,
+            Self::ColorBlind => &COLOR_BLIND_COLORS,
+        }
+    }
+}
+
+/// How the game is drawn, saved between runs.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Accessibility {
+    /// White on black windows with heavy outlines, and plain white dice.
+    pub high_contrast: bool,
+    /// How many times the normal size the windows are drawn.
+    pub text_scale: f32,
+    pub palette: Palette,
+}
+
+impl Default for Accessibility {
+    fn default() -> Self {
+        Self { high_contrast: false, text_scale: 1.0, palette: Palette::Standard }
+    }
+}
+
+impl Accessibility {
+    /// The saved settings, or the defaults if there are none.
+    pub fn load(dir: &DataDir) -> Self {
+        match dir.load("pig", SETTINGS_FILE) {
+            Ok(settings) => settings.unwrap_or_default(),
+            Err(e) => {
+                warn!("Couldn't load the accessibility settings: {e}");
+                Self::default()
+            }
+        }
+    }
+
+    /// The colour of a seat's dice. High contrast keeps the dice white so
+    /// the pips stand out.
+    pub fn dice_color(&self, seat: usize) -> Color {
+        if self.high_contrast {
+            Color::WHITE
+        } else {
+            self.palette.colors()[seat]
+        }
+    }
+
+    /// The colour of a seat, for egui.
+    pub fn seat_color(&self, seat: usize) -> egui::Color32 {
+        let [red, green, blue, _] = self.palette.colors()[seat].to_srgba().to_u8_array();
+        egui::Color32::from_rgb(red, green, blue)
+    }
+
+    /// Whether dice carry their seat's number, for when colour alone won't
+    /// tell them apart.
+    pub fn numbers_dice(&self) -> bool {
+        self.high_contrast || self.palette == Palette::ColorBlind
+    }
+}
+
+/// A seat's shape and number, as shown beside its name and on its dice.
+pub fn seat_tag(seat: usize) -> String {
+    format!("{}{}", SEAT_MARKERS[seat], seat + 1)
+}
+
+fn high_contrast_visuals() -> egui::Visuals {
+    let (black, white) = (egui::Color32::BLACK, egui::Color32::WHITE);
+    let mut visuals = egui::Visuals::dark();
+    visuals.override_text_color = Some(white);
+    visuals.window_fill = black;
+    visuals.panel_fill = black;
+    visuals.extreme_bg_color = black;
+    visuals.window_stroke = egui::Stroke::new(2.0, white);
+    visuals.selection.bg_fill = egui::Color32::YELLOW;
+    visuals.selection.stroke = egui::Stroke::new(2.0, black);
+    for widget in [
+        &mut visuals.widgets.noninteractive,
+        &mut visuals.widgets.inactive,
+        &mut visuals.widgets.hovered,
+        &mut visuals.widgets.active,
+        &mut visuals.widgets.open,
+    ] {
+        widget.bg_fill = black;
+        widget.weak_bg_fill = black;
+        widget.bg_stroke = egui::Stroke::new(2.0, white);
+        widget.fg_stroke = egui::Stroke::new(2.0, white);
+    }
+    // Hovering and pressing still have to show.
+    visuals.widgets.hovered.weak_bg_fill = egui::Color32::from_rgb(0, 0, 160);
+    visuals.widgets.active.weak_bg_fill = egui::Color32::from_rgb(0, 0, 255);
+    visuals
+}
+
+/// Restyles egui whenever the settings change, and saves them.
+pub fn apply_settings(
+    settings: Res<Accessibility>,
+    mut applied: Local<Option<Accessibility>>,
+    mut egui_settings: ResMut<EguiSettings>,
+    clear_color: Option<ResMut<ClearColor>>,
+    dir: Res<DataDir>,
+    mut egui_context: EguiContexts,
+) {
+    if *applied == Some(*settings) {
+        return;
+    }
+    // There's no context until the window opens.
+    let Some(ctx) = egui_context.try_ctx_mut() else {
+        return;
+    };
+    ctx.set_visuals(if settings.high_contrast { high_contrast_visuals() } else { egui::Visuals::dark() });
+    egui_settings.scale_factor = settings.text_scale;
+    if let Some(mut clear_color) = clear_color {
+        *clear_color = if settings.high_contrast { ClearColor(Color::BLACK) } else { ClearColor::default() };
+    }
+    // The first time through the settings have only just been loaded.
+    if applied.is_some() {
+        if let Err(e) = dir.save("pig", SETTINGS_FILE, &*settings) {
+            warn!("Couldn't save the accessibility settings: {e}");
+        }
+    }
+    *applied = Some(*settings);
+}
+
+/// A live region that screen readers read out whenever it changes.
+#[derive(Component, Default)]
+pub struct Announcer {
+    /// History events already announced.
+    events: usize,
+}
+
+pub fn announcer() -> (Announcer, AccessibilityNode) {
+    let mut node = NodeBuilder::new(Role::Status);
+    node.set_live(Live::Polite);
+    (Announcer::default(), AccessibilityNode(node))
+}
+
+/// Announces each roll, hold and win once its dice have landed, and whose
+/// turn is next.
+pub fn announce(
+    game: Res<Game>,
+    history: Res<GameHistory>,
+    seats: Res<Seats>,
+    tumbling: Query<(), With<Tumble>>,
+    mut announcers: Query<(&mut Announcer, &mut AccessibilityNode)>,
+) {
+    let events = history.0.turns().iter().flat_map(|turn| turn.events.iter().map(move |event| (turn.player, event)));
+    for (mut announcer, mut node) in announcers.iter_mut() {
+        if history.is_added() {
+            // A loaded game starts quietly where it left off.
+            announcer.events = events.clone().count();
+            continue;
+        }
+        if !tumbling.is_empty() {
+            continue;
+        }
+        let mut lines: Vec<String> = events
+            .clone()
+            .skip(announcer.events)
+            .map(|(player, event)| format!("{} {}.", seats.0[player].name, event.describe()))
+            .collect();
+        if lines.is_empty() {
+            continue;
+        }
+        announcer.events += lines.len();
+        let turn_over = history.0.turns().last().is_some_and(|turn| turn.is_finished());
+        if turn_over && !game.0.is_over() {
+            lines.push(format!("{}'s turn.", seats.0[game.0.current()].name));
+        }
+        node.set_name(lines.join(" "));
+    }
+}
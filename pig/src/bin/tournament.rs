@@ -0,0 +1,248 @@
+//! Plays Pig strategies against each other, without opening a window.
+//!
+//! ```text
+//! cargo run --release -p pig --bin tournament -- --games 100000 --csv results.csv
+//! ```
+//!
+//! Every strategy plays every other, going first in half of the games,
+//! and the win rates are reported with 95% confidence intervals.
+use my_library::persistence::DataDir;
+use my_library::RandomNumberGenerator;
+use pig::rules::{Rules, Variant};
+use pig::simulation::{Entrant, Pairing, Tournament};
+use pig::strategy::{HoldAt, KeepPace, Policy, Strategy, MAX_POLICY_TARGET};
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const USAGE: &str = "Usage: tournament [options]
+
+Options:
+  --strategies LIST  comma-separated strategies to play (default hold:20,keep-pace,
+                     and optimal where it can play)
+                     hold:N     hold once the turn total reaches N
+                     keep-pace  hold at 21, adjusted by the score, and race near the end
+                     optimal    the solved optimal policy, for classic Pig with
+                                six-sided dice up to 200 points
+  --variant NAME     pig, two-dice, big-pig, hog or piglet (default pig)
+  --target N         the score that wins (default: the variant's usual target)
+  --sides N          faces on each die (default 6)
+  --games N          games per pair of strategies (default 10000)
+  --threads N        threads to play on (default: one per core)
+  --seed N           seed for the dice (default: random)
+  --csv FILE         also write the results to FILE as CSV";
+
+struct Options {
+    strategies: Option<String>,
+    tournament: Tournament,
+    seed: Option<u64>,
+    csv: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        strategies: None,
+        tournament: Tournament::default(),
+        seed: None,
+        csv: None,
+    };
+    // The variant sets the usual target, so these are applied after it.
+    let (mut target, mut sides) = (None, None);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        let number = |text: String| text.parse::<u64>().map_err(|_| format!("{arg}: {text} isn't a number"));
+        match arg.as_str() {
+            "--strategies" => options.strategies = Some(value()?),
+            "--variant" => options.tournament.rules = Rules::new(parse_variant(&value()?)?),
+            "--target" => target = Some(number(value()?)? as u32),
+            "--sides" => sides = Some(number(value()?)? as u32),
+            "--games" => options.tournament.games = number(value()?)? as u32,
+            "--threads" => options.tournament.threads = number(value()?)? as usize,
+            "--seed" => options.seed = Some(number(value()?)?),
+            "--csv" => options.csv = Some(PathBuf::from(value()?)),
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ => return Err(format!("Unknown option {arg}\n\n{USAGE}")),
+        }
+    }
+    let rules = &mut options.tournament.rules;
+    rules.target = target.unwrap_or(rules.target);
+    rules.die_sides = sides.unwrap_or(rules.die_sides);
+    options.tournament.rules.check()?;
+    if options.tournament.games == 0 || options.tournament.threads == 0 {
+        return Err("There must be at least one game and one thread".to_string());
+    }
+    Ok(options)
+}
+
+fn parse_variant(name: &str) -> Result<Variant, String> {
+    match name {
+        "pig" => Ok(Variant::Classic),
+        "two-dice" => Ok(Variant::TwoDice),
+        "big-pig" => Ok(Variant::BigPig),
+        "hog" => Ok(Variant::Hog),
+        "piglet" => Ok(Variant::Piglet),
+        _ => Err(format!("Unknown variant {name}\n\n{USAGE}")),
+    }
+}
+
+/// The strategies to play when none are given.
+fn default_strategies(rules: &Rules) -> &'static str {
+    if Policy::solves(rules) {
+        "hold:20,keep-pace,optimal"
+    } else {
+        "hold:20,keep-pace"
+    }
+}
+
+/// Turns `hold:20,optimal` into entrants for games played by `rules`.
+fn parse_strategies(list: &str, rules: &Rules) -> Result<Vec<Entrant>, String> {
+    let entrants = list
+        .split(',')
+        .map(str::trim)
+        .map(|name| {
+            let strategy: Arc<dyn Strategy> = match name.split_once(':') {
+                Some(("hold", n)) => Arc::new(HoldAt(n.parse().map_err(|_| format!("{name}: {n} isn't a number"))?)),
+                None if name == "keep-pace" => Arc::new(KeepPace),
+                None if name == "optimal" => Arc::new(optimal_policy(rules)?),
+                _ => return Err(format!("Unknown strategy {name}\n\n{USAGE}")),
+            };
+            Ok(Entrant { name: name.to_string(), strategy })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if entrants.len() < 2 {
+        return Err("A tournament needs at least two strategies".to_string());
+    }
+    Ok(entrants)
+}
+
+/// The optimal policy for `rules` from the game's cache, solving it if
+/// needed.
+fn optimal_policy(rules: &Rules) -> Result<Policy, String> {
+    if !Policy::solves(rules) {
+        return Err(format!(
+            "optimal only plays classic Pig with six-sided dice, up to {MAX_POLICY_TARGET} points"
+        ));
+    }
+    let dir = DataDir::default();
+    if let Ok(Some(policy)) = Policy::load(&dir, rules.target) {
+        return Ok(policy);
+    }
+    println!("Solving the optimal policy to {}...", rules.target);
+    let policy = Policy::solve(rules.target);
+    if let Err(e) = policy.save(&dir) {
+        eprintln!("Couldn't cache the optimal policy: {e}");
+    }
+    Ok(policy)
+}
+
+fn percent(rate: f64) -> String {
+    format!("{:.2}%", rate * 100.0)
+}
+
+fn report(entrants: &[Entrant], results: &[Pairing]) {
+    let width = entrants.iter().map(|entrant| entrant.name.len()).max().unwrap_or(0);
+    for pairing in results {
+        let [first, second] = pairing.entrants;
+        println!("\n{} vs {}: {} games", entrants[first].name, entrants[second].name, pairing.games);
+        for (side, &entrant) in pairing.entrants.iter().enumerate() {
+            let (low, high) = pairing.confidence_interval(side);
+            println!(
+                "  {:width$}  {:>7} won  ({} - {})",
+                entrants[entrant].name,
+                percent(pairing.win_rate(side)),
+                percent(low),
+                percent(high)
+            );
+        }
+        if pairing.draws > 0 {
+            println!("  {} games abandoned", pairing.draws);
+        }
+        println!("  Games last {:.1} turns and {:.1} rolls on average", pairing.mean_turns(), pairing.mean_rolls());
+    }
+
+    println!("\nOverall:");
+    let mut standings: Vec<(usize, u32, u32)> = (0..entrants.len())
+        .map(|entrant| {
+            let (wins, games) = results
+                .iter()
+                .filter_map(|pairing| {
+                    let side = pairing.entrants.iter().position(|&side| side == entrant)?;
+                    Some((pairing.wins[side], pairing.games))
+                })
+                .fold((0, 0), |(wins, games), (won, played)| (wins + won, games + played));
+            (entrant, wins, games)
+        })
+        .collect();
+    let rate = |&(_, wins, games): &(usize, u32, u32)| wins as f64 / games as f64;
+    standings.sort_by(|a, b| rate(b).total_cmp(&rate(a)));
+    for standing @ (entrant, _, games) in standings {
+        println!("  {:width$}  {:>7} of {games} games", entrants[entrant].name, percent(rate(&standing)));
+    }
+}
+
+/// One row per entrant in each pairing.
+fn csv(entrants: &[Entrant], results: &[Pairing]) -> String {
+    let mut text = "strategy,opponent,games,wins,win_rate,ci_low,ci_high,draws,mean_turns,mean_rolls\n".to_string();
+    for pairing in results {
+        for (side, &entrant) in pairing.entrants.iter().enumerate() {
+            let (low, high) = pairing.confidence_interval(side);
+            let _ = writeln!(
+                text,
+                "{},{},{},{},{:.6},{:.6},{:.6},{},{:.3},{:.3}",
+                entrants[entrant].name,
+                entrants[pairing.entrants[1 - side]].name,
+                pairing.games,
+                pairing.wins[side],
+                pairing.win_rate(side),
+                low,
+                high,
+                pairing.draws,
+                pairing.mean_turns(),
+                pairing.mean_rolls()
+            );
+        }
+    }
+    text
+}
+
+fn main() {
+    let (options, entrants) = match parse_args().and_then(|options| {
+        let rules = &options.tournament.rules;
+        let list = options.strategies.as_deref().unwrap_or(default_strategies(rules));
+        let entrants = parse_strategies(list, rules)?;
+        Ok((options, entrants))
+    }) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("{message}");
+            std::process::exit(2);
+        }
+    };
+    let rng = match options.seed {
+        Some(seed) => RandomNumberGenerator::seeded(seed),
+        None => RandomNumberGenerator::new(),
+    };
+    let rules = options.tournament.rules;
+    println!(
+        "Playing {} games of {} to {} per pair of strategies on {} threads (seed {})",
+        options.tournament.games,
+        rules.variant.name(),
+        rules.target,
+        options.tournament.threads,
+        rng.seed()
+    );
+
+    let results = options.tournament.run(&entrants, &rng);
+    report(&entrants, &results);
+
+    if let Some(path) = &options.csv {
+        match std::fs::write(path, csv(&entrants, &results)) {
+            Ok(()) => println!("\nWrote the results to {}", path.display()),
+            Err(e) => {
+                eprintln!("Couldn't write {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Dice that look right at any window size: the sprite sheet is chosen to
+//! suit the screen, its grid is worked out from the image, and the row of
+//! dice on the table scales with the window.
+use crate::{DiceSettings, GameAssets, HandDie, Tumble};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+/// A sprite sheet of the six faces, and how many pixels across a die is
+/// on it.
+#[derive(Debug, PartialEq)]
+pub struct DiceSheet {
+    pub path: &'static str,
+    pub die_pixels: f32,
+}
+
+/// Smallest first.
+const SHEETS: [DiceSheet; 2] = [
+    DiceSheet { path: "dice.png", die_pixels: 52.0 },
+    DiceSheet { path: "dice_hd.png", die_pixels: 128.0 },
+];
+
+/// Which sprite sheet to draw the dice from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DiceQuality {
+    /// The smallest sheet that is sharp at the window's size and scale.
+    #[default]
+    Auto,
+    Small,
+    High,
+}
+
+impl DiceQuality {
+    pub const ALL: [DiceQuality; 3] = [Self::Auto, Self::Small, Self::High];
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::Auto => "Automatic",
+            Self::Small => "Small",
+            Self::High => "High",
+        }
+    }
+
+    /// The sheet to use when a die is drawn `physical_pixels` across.
+    pub fn sheet(self, physical_pixels: f32) -> &'static DiceSheet {
+        match self {
+            Self::Auto => SHEETS
+                .iter()
+                .find(|sheet| sheet.die_pixels >= physical_pixels)
+                .unwrap_or(&SHEETS[SHEETS.len() - 1]),
+            Self::Small => &SHEETS[0],
+            Self::High => &SHEETS[1],
+        }
+    }
+}
+
+/// The size of one face on a sheet of `size` pixels, and its columns and
+/// rows. The six faces are laid out in whichever grid gives the squarest
+/// cells.
+pub fn dice_grid(size: UVec2) -> (UVec2, u32, u32) {
+    let squareness = |columns: u32| {
+        let rows = 6 / columns;
+        let aspect = (size.x * rows) as f32 / (size.y * columns) as f32;
+        (aspect.ln().abs(), columns, rows)
+    };
+    let (_, columns, rows) = [1, 2, 3, 6]
+        .map(squareness)
+        .into_iter()
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .unwrap();
+    (UVec2::new(size.x / columns, size.y / rows), columns, rows)
+}
+
+/// The atlas for a sheet of `size` pixels. When the sheet doesn't divide
+/// evenly, each face starts at its own rounded-down offset rather than at a
+/// multiple of the rounded-down face size, so the faces don't drift.
+pub fn dice_atlas(size: UVec2) -> TextureAtlasLayout {
+    let (_, columns, rows) = dice_grid(size);
+    let mut layout = TextureAtlasLayout::new_empty(size);
+    for row in 0..rows {
+        for column in 0..columns {
+            let min = UVec2::new(size.x * column / columns, size.y * row / rows);
+            let max = UVec2::new(size.x * (column + 1) / columns, size.y * (row + 1) / rows);
+            layout.add_texture(URect::from_corners(min, max));
+        }
+    }
+    layout
+}
+
+/// Where the dice go, for the current window.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct TableLayout {
+    /// How many logical pixels across a die is drawn.
+    pub die: f32,
+    /// Where the first die rests.
+    pub origin: Vec3,
+    /// Physical pixels per logical pixel.
+    pub scale_factor: f32,
+}
+
+impl Default for TableLayout {
+    /// The layout for the game's starting 1024x768 window.
+    fn default() -> Self {
+        Self::for_window(Vec2::new(1024.0, 768.0), 1.0)
+    }
+}
+
+impl TableLayout {
+    fn for_window(size: Vec2, scale_factor: f32) -> Self {
+        Self {
+            die: (size.y * 52.0 / 768.0).max(16.0),
+            origin: Vec3::new(-size.x * 400.0 / 1024.0, size.y * 60.0 / 768.0, 1.0),
+            scale_factor,
+        }
+    }
+
+    /// Where the die at `position` in the row rests.
+    pub fn die_position(&self, position: usize) -> Vec3 {
+        self.origin + Vec3::X * position as f32 * self.die
+    }
+
+    /// The sheet the dice should be drawn from.
+    pub fn sheet(&self, quality: DiceQuality) -> &'static DiceSheet {
+        quality.sheet(self.die * self.scale_factor)
+    }
+}
+
+/// Follows the window's size and scale.
+pub fn lay_out_table(windows: Query<&Window, With<PrimaryWindow>>, mut table: ResMut<TableLayout>) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let layout = TableLayout::for_window(window.size(), window.scale_factor());
+    // Only touch the layout when it changes, so the dice are only refitted
+    // then.
+    if *table != layout {
+        *table = layout;
+    }
+}
+
+/// Switches sprite sheets when the layout or settings call for another,
+/// and builds the atlas once the sheet has loaded.
+pub fn load_dice_sheet(
+    table: Res<TableLayout>,
+    settings: Res<DiceSettings>,
+    mut assets: ResMut<GameAssets>,
+    asset_server: Res<AssetServer>,
+    images: Res<Assets<Image>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let sheet = table.sheet(settings.quality);
+    if sheet != assets.sheet {
+        assets.sheet = sheet;
+        assets.image = asset_server.load(sheet.path);
+        assets.tile = None;
+    }
+    if assets.tile.is_some() {
+        return;
+    }
+    let Some(image) = images.get(&assets.image) else {
+        return;
+    };
+    let (tile, columns, rows) = dice_grid(image.size());
+    if image.width() % columns != 0 || image.height() % rows != 0 {
+        warn!(
+            "{} is {}x{}, which doesn't split evenly into {columns}x{rows} faces",
+            sheet.path,
+            image.width(),
+            image.height()
+        );
+    }
+    let layout = dice_atlas(image.size());
+    if let Err(e) = assets.animations.check(&layout) {
+        warn!("{} doesn't fit the dice animations: {e}", sheet.path);
+    }
+    layouts.insert(&assets.atlas, layout);
+    assets.tile = Some(tile);
+}
+
+/// How much to scale a die to draw it `table.die` across. Coins and big
+/// dice are tiles, drawn 52 pixels across before scaling.
+pub fn die_scale(table: &TableLayout, assets: &GameAssets, tile: bool) -> Vec3 {
+    Vec3::splat(table.die / if tile { 52.0 } else { assets.sheet.die_pixels })
+}
+
+type DiceQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static HandDie,
+        &'static mut Transform,
+        &'static mut Handle<Image>,
+        &'static mut Visibility,
+        Option<&'static TextureAtlas>,
+        Option<&'static mut Tumble>,
+    ),
+>;
+
+/// Moves and resizes the dice when the window changes, and swaps their
+/// image when the sheet does. Dice stay hidden until their sheet has
+/// loaded.
+pub fn fit_dice(
+    table: Res<TableLayout>,
+    assets: Res<GameAssets>,
+    mut dice: DiceQuery,
+) {
+    if !table.is_changed() && !assets.is_changed() {
+        return;
+    }
+    for (die, mut transform, mut image, mut visibility, atlas, tumble) in dice.iter_mut() {
+        let end = table.die_position(die.0);
+        transform.scale = die_scale(&table, &assets, atlas.is_none());
+        match tumble {
+            Some(mut tumble) => tumble.retarget(end, table.die),
+            None => transform.translation = end,
+        }
+        if atlas.is_some() {
+            *image = assets.image.clone();
+            *visibility = if assets.tile.is_some() { Visibility::Inherited } else { Visibility::Hidden };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dice_grid() {
+        assert_eq!(dice_grid(UVec2::new(312, 52)), (UVec2::splat(52), 6, 1));
+        assert_eq!(dice_grid(UVec2::new(256, 128)), (UVec2::new(85, 64), 3, 2));
+        assert_eq!(dice_grid(UVec2::new(512, 354)), (UVec2::new(170, 177), 3, 2));
+    }
+
+    #[test]
+    fn test_uneven_sheets_do_not_drift() {
+        let layout = dice_atlas(UVec2::new(512, 354));
+        assert_eq!(layout.len(), 6);
+        assert_eq!(layout.textures[2], URect::new(341, 0, 512, 177));
+        assert_eq!(layout.textures[5], URect::new(341, 177, 512, 354));
+        let even = TextureAtlasLayout::from_grid(UVec2::splat(52), 6, 1, None, None);
+        assert_eq!(dice_atlas(UVec2::new(312, 52)).textures, even.textures);
+    }
+
+    #[test]
+    fn test_sheets_follow_the_window() {
+        let table = TableLayout::default();
+        assert_eq!(table.die, 52.0);
+        assert_eq!(table.die_position(1), Vec3::new(-348.0, 60.0, 1.0));
+        assert_eq!(table.sheet(DiceQuality::Auto).path, "dice.png");
+        assert_eq!(table.sheet(DiceQuality::High).path, "dice_hd.png");
+
+        let retina = TableLayout::for_window(Vec2::new(1024.0, 768.0), 2.0);
+        assert_eq!(retina.sheet(DiceQuality::Auto).path, "dice_hd.png");
+        let uhd = TableLayout::for_window(Vec2::new(3840.0, 2160.0), 1.0);
+        assert_eq!(uhd.sheet(DiceQuality::Auto).path, "dice_hd.png");
+        assert!((uhd.die - 146.25).abs() < 0.01);
+        assert_eq!(uhd.sheet(DiceQuality::Small).path, "dice.png");
+    }
+}
@@ -0,0 +1,265 @@
+//! A record of every roll, bust and hold in a game, turn by turn, with the
+//! statistics for the end-of-game summary.
+use crate::rules::{Action, IllegalMove, Outcome, PigGame};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Something that happened on a turn.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Event {
+    /// Dice that scored `points`, which went on the turn total.
+    Rolled { faces: Vec<u32>, points: u32 },
+    /// A roll with a 1 in it, which lost the turn total.
+    Bust { faces: Vec<u32> },
+    /// Double ones in Two-Dice Pig, which lost the player's whole score.
+    WipedOut { faces: Vec<u32> },
+    Held { points: u32 },
+    /// Banked `points`, reaching `score` and winning.
+    Won { points: u32, score: u32 },
+}
+
+impl Event {
+    fn ends_turn(&self) -> bool {
+        !matches!(self, Self::Rolled { .. })
+    }
+
+    pub fn describe(&self) -> String {
+        let faces = |faces: &[u32]| faces.iter().map(u32::to_string).collect::<Vec<_>>().join(" ");
+        match self {
+            Self::Rolled { faces: rolled, points } => format!("rolled {} (+{points})", faces(rolled)),
+            Self::Bust { faces: rolled } => format!("rolled {} - bust", faces(rolled)),
+            Self::WipedOut { faces: rolled } => format!("rolled {} - wiped out", faces(rolled)),
+            Self::Held { points } => format!("held {points}"),
+            Self::Won { points, score } => format!("held {points} and won with {score}"),
+        }
+    }
+}
+
+/// One player's turn, in order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Turn {
+    pub player: usize,
+    pub events: Vec<Event>,
+}
+
+impl Turn {
+    pub fn is_finished(&self) -> bool {
+        self.events.last().is_some_and(Event::ends_turn)
+    }
+
+    /// The points the turn banked.
+    pub fn banked(&self) -> u32 {
+        match self.events.last() {
+            Some(Event::Held { points } | Event::Won { points, .. }) => *points,
+            _ => 0,
+        }
+    }
+
+    /// The turn as a line of the log, for the player called `name`.
+    pub fn describe(&self, name: &str) -> String {
+        let events: Vec<String> = self.events.iter().map(Event::describe).collect();
+        format!("{name} {}", events.join(", "))
+    }
+}
+
+/// How one player's game went.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct PlayerStats {
+    /// Finished turns.
+    pub turns: u32,
+    pub rolls: u32,
+    /// Rolls that lost the turn total, including wipe-outs.
+    pub busts: u32,
+    pub banked: u32,
+    /// The most scoring rolls in a single turn.
+    pub longest_streak: u32,
+}
+
+impl PlayerStats {
+    /// The points banked in an average turn, counting busts as nothing.
+    pub fn average_turn(&self) -> f64 {
+        self.banked as f64 / self.turns.max(1) as f64
+    }
+}
+
+/// Everything that has happened in a game so far.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct History {
+    turns: Vec<Turn>,
+}
+
+impl History {
+    pub fn turns(&self) -> &[Turn] {
+        &self.turns
+    }
+
+    /// Plays `action` in `game`, like [`PigGame::play`], and records what
+    /// happened.
+    pub fn play(
+        &mut self,
+        game: &mut PigGame,
+        action: Action,
+        die: impl FnMut() -> u32,
+    ) -> Result<Outcome, IllegalMove> {
+        let player = game.current();
+        let before = game.score(player);
+        let outcome = game.play(action, die)?;
+        let faces = game.last_roll().to_vec();
+        // Hog's throws score and bank in one go.
+        let threw = matches!(action, Action::Throw(_));
+        match outcome {
+            Outcome::Rolled(points) => self.record(player, Event::Rolled { faces, points }),
+            Outcome::Bust => self.record(player, Event::Bust { faces }),
+            Outcome::WipedOut => self.record(player, Event::WipedOut { faces }),
+            Outcome::Held(points) => {
+                if threw {
+                    self.record(player, Event::Rolled { faces, points });
+                }
+                self.record(player, Event::Held { points });
+            }
+            Outcome::Won { score, .. } => {
+                let points = score - before;
+                if threw {
+                    self.record(player, Event::Rolled { faces, points });
+                }
+                self.record(player, Event::Won { points, score });
+            }
+        }
+        Ok(outcome)
+    }
+
+    fn record(&mut self, player: usize, event: Event) {
+        match self.turns.last_mut() {
+            Some(turn) if turn.player == player && !turn.is_finished() => turn.events.push(event),
+            _ => self.turns.push(Turn { player, events: vec![event] }),
+        }
+    }
+
+    /// Statistics for each of the game's `players`.
+    pub fn stats(&self, players: usize) -> Vec<PlayerStats> {
+        let mut stats = vec![PlayerStats::default(); players];
+        for turn in &self.turns {
+            let Some(player) = stats.get_mut(turn.player) else {
+                continue;
+            };
+            let streak = turn.events.iter().filter(|event| matches!(event, Event::Rolled { .. })).count() as u32;
+            let busted = turn.events.iter().any(|event| matches!(event, Event::Bust { .. } | Event::WipedOut { .. }));
+            player.rolls += streak + busted as u32;
+            player.busts += busted as u32;
+            player.banked += turn.banked();
+            player.longest_streak = player.longest_streak.max(streak);
+            if turn.is_finished() {
+                player.turns += 1;
+            }
+        }
+        stats
+    }
+
+    /// The whole log and the statistics, as plain text. `names[i]` is
+    /// player `i`'s name.
+    pub fn to_text(&self, names: &[String]) -> String {
+        let mut text = String::new();
+        for (i, turn) in self.turns.iter().enumerate() {
+            let _ = writeln!(text, "{:>4}. {}", i + 1, turn.describe(&names[turn.player]));
+        }
+        text.push('\n');
+        for (name, stats) in names.iter().zip(self.stats(names.len())) {
+            let _ = writeln!(
+                text,
+                "{name}: {} turns, {} rolls, {} busts, {:.1} points a turn, longest streak {} rolls",
+                stats.turns,
+                stats.rolls,
+                stats.busts,
+                stats.average_turn(),
+                stats.longest_streak
+            );
+        }
+        text
+    }
+
+    /// The whole log and the statistics, as JSON.
+    pub fn to_json(&self, names: &[String]) -> String {
+        #[derive(Serialize)]
+        struct Player<'a> {
+            name: &'a str,
+            #[serde(flatten)]
+            stats: PlayerStats,
+            average_turn: f64,
+        }
+        #[derive(Serialize)]
+        struct Export<'a> {
+            players: Vec<Player<'a>>,
+            turns: &'a [Turn],
+        }
+        let players = names
+            .iter()
+            .zip(self.stats(names.len()))
+            .map(|(name, stats)| Player { name, stats, average_turn: stats.average_turn() })
+            .collect();
+        serde_json::to_string_pretty(&Export { players, turns: &self.turns })
+            .expect("the history is always valid JSON")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{Rules, Variant};
+
+    /// Plays `actions`, rolling `faces` in order.
+    fn play(history: &mut History, game: &mut PigGame, actions: &[Action], faces: &[u32]) {
+        let mut faces = faces.iter().copied();
+        for &action in actions {
+            history.play(game, action, || faces.next().unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_turns_and_stats() {
+        let (mut history, mut game) = (History::default(), PigGame::new(2));
+        let (roll, hold) = (Action::Roll, Action::Hold);
+        play(&mut history, &mut game, &[roll, roll, roll, hold, roll, roll, roll], &[4, 5, 6, 3, 1, 2]);
+
+        let turns = history.turns();
+        assert_eq!(turns.len(), 3);
+        assert_eq!(turns[0].banked(), 15);
+        assert_eq!(turns[0].describe("Ann"), "Ann rolled 4 (+4), rolled 5 (+5), rolled 6 (+6), held 15");
+        assert_eq!(turns[1].events, [Event::Rolled { faces: vec![3], points: 3 }, Event::Bust { faces: vec![1] }]);
+        assert!(!turns[2].is_finished());
+
+        let stats = history.stats(2);
+        assert_eq!(stats[0], PlayerStats { turns: 1, rolls: 4, busts: 0, banked: 15, longest_streak: 3 });
+        assert_eq!(stats[1], PlayerStats { turns: 1, rolls: 2, busts: 1, banked: 0, longest_streak: 1 });
+        assert_eq!(stats[1].average_turn(), 0.0);
+    }
+
+    #[test]
+    fn test_hog_throws_are_rolls() {
+        let (mut history, mut game) = (History::default(), PigGame::with_rules(1, Rules {
+            target: 10,
+            ..Rules::new(Variant::Hog)
+        }));
+        play(&mut history, &mut game, &[Action::Throw(2), Action::Throw(2)], &[3, 3, 2, 2]);
+        assert_eq!(history.turns()[1].events, [
+            Event::Rolled { faces: vec![2, 2], points: 4 },
+            Event::Won { points: 4, score: 10 },
+        ]);
+        assert_eq!(history.stats(1)[0].rolls, 2);
+    }
+
+    #[test]
+    fn test_exports() {
+        let (mut history, mut game) = (History::default(), PigGame::new(2));
+        play(&mut history, &mut game, &[Action::Roll, Action::Hold], &[6]);
+        let names = ["Ann".to_string(), "Bob".to_string()];
+
+        let text = history.to_text(&names);
+        assert!(text.starts_with("   1. Ann rolled 6 (+6), held 6\n"), "{text}");
+        assert!(text.contains("Ann: 1 turns, 1 rolls, 0 busts, 6.0 points a turn, longest streak 1 rolls"));
+
+        let json: serde_json::Value = serde_json::from_str(&history.to_json(&names)).unwrap();
+        assert_eq!(json["players"][0]["name"], "Ann");
+        assert_eq!(json["players"][0]["banked"], 6);
+        assert_eq!(json["turns"][0]["events"][1]["Held"]["points"], 6);
+    }
+}
@@ -0,0 +1,8 @@
+//! The parts of Pig that don't need a window: the rules of the game, the
+//! computer players' strategies, a headless simulation, network play and
+//! the game's history. The game and the `tournament` tool both build on them.
+pub mod history;
+pub mod net;
+pub mod rules;
+pub mod simulation;
+pub mod strategy;
@@ -1,248 +1,973 @@
+use bevy::ecs::schedule::SystemConfigs;
 use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
 use my_library::*;
+use my_library::persistence::DataDir;
+use pig::history::{Event as HistoryEvent, History};
+use pig::rules::{Action, PigGame, Rules, Variant, HOG_MAX_DICE, MAX_DIE_SIDES};
+use pig::strategy::{HoldAt, KeepPace, Policy, Strategy, MAX_POLICY_TARGET};
+use serde::{Deserialize, Serialize};
+use access::Accessibility;
+use dice::{DiceQuality, DiceSheet, TableLayout};
+use network::{Network, NetworkForm, OfflineSeats};
+use std::sync::Arc;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default, States)]
+mod access;
+mod dice;
+mod network;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default, States, Serialize, Deserialize)]
 enum GamePhase {
-    // #[default]
-    MainMenu,
+    /// Where the game opens.
     #[default]
+    MainMenu,
+    /// Waiting for players to join a network game.
+    Lobby,
     Start,
-    Player,
+    /// A human's turn.
+    Human,
+    /// A computer player's turn.
     Cpu,
+    /// The turn of someone playing on another machine.
+    Remote,
     End,
     GameOver,
 }
 
-#[derive(Component)]
-pub struct GameElement;
+/// What a human player can do on their turn. Hog has no hold: its one
+/// throw ends the turn.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
+enum PigAction {
+    /// Roll the dice, flip the coin, or throw in Hog.
+    Roll,
+    Hold,
+}
 
 #[derive(Resource)]
 struct GameAssets {
     atlas: Handle<TextureAtlasLayout>,
     image: Handle<Image>,
+    sheet: &'static DiceSheet,
+    /// The size of a face on the sheet, once it has loaded.
+    tile: Option<UVec2>,
+    animations: Arc<AnimationLibrary>,
+}
+
+/// A thrown die tumbles through every face until it lands (see
+/// [`tumble_dice`]), then shows the one it rolled.
+fn dice_animations() -> AnimationLibrary {
+    (1..=6).fold(
+        AnimationLibrary::new().with("tumble", SpriteClip::new(0..=5, 18.0, PlayMode::Loop)),
+        |library, value| library.with(face(value), SpriteClip::still(value - 1)),
+    )
+}
+
+/// The clip showing a die's `value`.
+fn face(value: usize) -> String {
+    format!("face{value}")
+}
+
+/// The rules of the game in progress. Player `i` sits in seat `i`.
+#[derive(Resource, Serialize, Deserialize)]
+struct Game(PigGame);
+
+/// Every turn of the game in progress. It outlives the game, for the
+/// summary on the game over screen.
+#[derive(Resource, Default, Serialize, Deserialize)]
+struct GameHistory(History);
+
+/// Where the history is exported to in Pig's data folder, with `.txt` or
+/// `.json` added.
+const HISTORY_FILE: &str = "history";
+
+/// The rules the next game will be played by, chosen on the main menu.
+#[derive(Resource, Default)]
+struct TableRules(Rules);
+
+const MIN_SEATS: usize = 2;
+const MAX_SEATS: usize = 8;
+
+/// Who plays a seat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Controller {
+    Human,
+    Cpu(Difficulty),
+    /// Someone on another machine, in a network game.
+    Remote,
+}
+
+impl Controller {
+    const ALL: [Controller; 5] = [
+        Self::Human,
+        Self::Cpu(Difficulty::Easy),
+        Self::Cpu(Difficulty::Normal),
+        Self::Cpu(Difficulty::Hard),
+        Self::Cpu(Difficulty::Optimal),
+    ];
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Human => "Human",
+            Self::Cpu(difficulty) => difficulty.describe(),
+            Self::Remote => "Remote",
+        }
+    }
 }
 
-#[derive(Clone, Copy, Resource)]
-struct Scores {
-    player: usize,
-    cpu:    usize,
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Seat {
+    name: String,
+    controller: Controller,
 }
 
+/// Everyone at the table, in turn order. Chosen on the main menu; humans
+/// share the mouse and take turns in the hot seat.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+struct Seats(Vec<Seat>);
+
+impl Default for Seats {
+    fn default() -> Self {
+        Self(vec![
+            Seat { name: "Player".to_string(), controller: Controller::Human },
+            Seat { name: "CPU".to_string(), controller: Controller::Cpu(Difficulty::Normal) },
+        ])
+    }
+}
+
+impl Seats {
+    /// A network game's seats, where this machine's player sits in
+    /// `mine`.
+    fn networked(players: &[String], mine: usize) -> Self {
+        let seat = |(i, name): (usize, &String)| Seat {
+            name: name.clone(),
+            controller: if i == mine { Controller::Human } else { Controller::Remote },
+        };
+        Self(players.iter().enumerate().map(seat).collect())
+    }
+
+    /// The phase for the current turn, or the end of the game.
+    fn phase(&self, game: &PigGame) -> GamePhase {
+        match (game.winner(), self.0[game.current()].controller) {
+            (Some(_), _) => GamePhase::End,
+            (None, Controller::Human) => GamePhase::Human,
+            (None, Controller::Cpu(_)) => GamePhase::Cpu,
+            (None, Controller::Remote) => GamePhase::Remote,
+        }
+    }
+}
+
+/// A die on the table, showing one of this turn's rolls. It's this far
+/// along the row.
+#[derive(Component)]
+struct HandDie(usize);
+
+/// A die that has been thrown and hasn't come to rest. It bounces from
+/// `start` to `end`, tumbling through its faces, and lands on `value`.
 #[derive(Component)]
-struct HandDie;
+struct Tumble {
+    value: usize,
+    start: Vec3,
+    end: Vec3,
+    /// How high the first bounce goes.
+    height: f32,
+    elapsed: f32,
+    duration: f32,
+}
 
+impl Tumble {
+    /// Throws a die `die` pixels across that lands at `end`.
+    fn new(value: usize, end: Vec3, die: f32, duration: f32) -> Self {
+        let mut tumble = Self { value, start: end, end, height: 0.0, elapsed: 0.0, duration };
+        tumble.retarget(end, die);
+        tumble
+    }
+
+    /// Lands the die at `end` instead, for a table with dice `die` pixels
+    /// across. It's thrown in from the bottom right.
+    fn retarget(&mut self, end: Vec3, die: f32) {
+        self.start = end + Vec3::new(5.0, -2.7, 0.0) * die;
+        self.end = end;
+        self.height = 1.7 * die;
+    }
+}
+
+/// Sent when a thrown die lands, showing `value`.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+struct DiceSettled {
+    value: usize,
+}
+
+/// How dice are thrown.
 #[derive(Resource)]
-struct HandTimer(Timer);
+struct DiceSettings {
+    /// How long a thrown die takes to land. At 0 dice land straight away.
+    roll_seconds: f32,
+    quality: DiceQuality,
+}
+
+impl Default for DiceSettings {
+    fn default() -> Self {
+        Self { roll_seconds: 0.8, quality: DiceQuality::Auto }
+    }
+}
+
+/// What [`show_dice`] has put on the table.
+#[derive(Default)]
+struct DiceTable {
+    /// History events already shown.
+    events: usize,
+    /// The turn the dice belong to.
+    turn: usize,
+    dice: Vec<Entity>,
+}
 
 #[derive(Resource)]
-struct FinalScore(Scores);
+struct HandTimer(Timer);
+
+/// How well a computer player plays.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Optimal,
+}
+
+impl Difficulty {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Easy => "CPU: Easy - holds at 10",
+            Self::Normal => "CPU: Normal - holds at 20",
+            Self::Hard => "CPU: Hard - keeps pace, then races",
+            Self::Optimal => "CPU: Optimal - never makes a mistake",
+        }
+    }
+
+    /// The CPU's strategy for a game played by `rules`, or `None` while the
+    /// optimal policy is still being solved. Where there's no optimal policy
+    /// to solve, Optimal plays like Hard.
+    fn strategy(self, policy: &OptimalPolicy, rules: &Rules) -> Option<Arc<dyn Strategy>> {
+        match (self, policy) {
+            (Self::Easy, _) => Some(Arc::new(HoldAt(10))),
+            (Self::Normal, _) => Some(Arc::new(HoldAt(20))),
+            (Self::Hard, _) => Some(Arc::new(KeepPace)),
+            (Self::Optimal, _) if !Policy::solves(rules) => Some(Arc::new(KeepPace)),
+            (Self::Optimal, OptimalPolicy::Ready(policy)) if policy.plays(rules) => Some(policy.clone()),
+            (Self::Optimal, _) => None,
+        }
+    }
+}
 
+/// The optimal policy takes a few seconds to solve, so it is solved in the
+/// background the first time someone picks it for a target, and cached on
+/// disk after that.
+#[derive(Resource, Default)]
+enum OptimalPolicy {
+    #[default]
+    Unsolved,
+    Solving(Task<Policy>),
+    Ready(Arc<Policy>),
+}
+
+#[allow(clippy::too_many_arguments)]
 fn setup(
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    seats: Res<Seats>,
+    rules: Res<TableRules>,
+    layout: Res<TableLayout>,
+    settings: Res<DiceSettings>,
     mut commands: Commands,
 ) {
     commands
-        .spawn(Camera2dBundle::default())
-        .insert(GameElement);
-    let texture_handle = asset_server.load("dice.png");
-    let texture_atlas = TextureAtlasLayout::from_grid(
-        UVec2::splat(52),
-        6,
-        1,
-        None,
-        None
-    );
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-    commands.insert_resource(GameAssets { atlas: texture_atlas_handle, image: texture_handle });
-    commands.insert_resource(Scores { cpu: 0, player: 0 });
+        .spawn(Camera2dBundle::default());
+    // The grid comes from the image, once it has loaded.
+    let sheet = layout.sheet(settings.quality);
+    commands.insert_resource(GameAssets {
+        atlas: texture_atlases.add(TextureAtlasLayout::new_empty(UVec2::ONE)),
+        image: asset_server.load(sheet.path),
+        sheet,
+        tile: None,
+        animations: Arc::new(dice_animations()),
+    });
+    commands.insert_resource(Game(PigGame::with_rules(seats.0.len(), rules.0)));
+    commands.insert_resource(GameHistory::default());
     commands.insert_resource(HandTimer(Timer::from_seconds(0.5, TimerMode::Repeating)));
+    commands.spawn(access::announcer());
 }
 
 fn display_score(
-    scores: Res<Scores>,
+    game: Res<Game>,
+    seats: Res<Seats>,
+    policy: Res<OptimalPolicy>,
+    network: Res<Network>,
+    access: Res<Accessibility>,
     mut egui_context: EguiContexts,
 ) {
-    egui::Window::new("Total Scores").show(egui_context.ctx_mut(), |ui| {
-        ui.label(&format!("Player: {}", scores.player));
-        ui.label(&format!("CPU: {}", scores.cpu));
+    let Some(ctx) = egui_context.try_ctx_mut() else {
+        return;
+    };
+    egui::Window::new("Scoreboard").show(ctx, |ui| {
+        let rules = game.0.rules();
+        ui.label(format!("{} to {}", rules.variant.name(), rules.target));
+        egui::Grid::new("scores").show(ui, |ui| {
+            for (i, seat) in seats.0.iter().enumerate() {
+                let playing = i == game.0.current();
+                let mut name = format!("{} {}", access::seat_tag(i), seat.name);
+                if !network.is_connected(i) {
+                    name.push_str(" (disconnected)");
+                }
+                let name = egui::RichText::new(name).color(access.seat_color(i));
+                ui.label(if playing { name.strong() } else { name });
+                ui.label(game.0.score(i).to_string());
+                ui.label(if playing { format!("+{} this turn", game.0.turn_total()) } else { String::new() });
+                ui.end_row();
+            }
+        });
+        if !game.0.last_roll().is_empty() {
+            let faces: Vec<String> = game.0.last_roll().iter().map(|&value| face_label(rules, value)).collect();
+            ui.label(format!("Last roll: {}", faces.join(" ")));
+        }
+        if let Controller::Cpu(difficulty) = seats.0[game.0.current()].controller {
+            if difficulty.strategy(&policy, rules).is_none() {
+                ui.label("The CPU is working out how to play...");
+            } else if difficulty == Difficulty::Optimal && !Policy::solves(rules) {
+                ui.label(format!(
+                    "Optimal only knows classic Pig up to {MAX_POLICY_TARGET} points, so it plays like Hard."
+                ));
+            }
+        }
+    });
+}
+
+fn choose_seats(
+    mut seats: ResMut<Seats>,
+    access: Res<Accessibility>,
+    mut egui_context: EguiContexts,
+) {
+    let Some(ctx) = egui_context.try_ctx_mut() else {
+        return;
+    };
+    egui::Window::new("Players").show(ctx, |ui| {
+        let count = seats.0.len();
+        let mut remove = None;
+        for (i, seat) in seats.0.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.colored_label(access.seat_color(i), access::seat_tag(i));
+                ui.add(egui::TextEdit::singleline(&mut seat.name).desired_width(100.0));
+                egui::ComboBox::from_id_source(("controller", i))
+                    .selected_text(seat.controller.describe())
+                    .show_ui(ui, |ui| {
+                        for choice in Controller::ALL {
+                            ui.selectable_value(&mut seat.controller, choice, choice.describe());
+                        }
+                    });
+                if count > MIN_SEATS && ui.button("Remove").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            seats.0.remove(i);
+        }
+        if seats.0.len() < MAX_SEATS && ui.button("Add Player").clicked() {
+            let name = format!("CPU {}", seats.0.len() + 1);
+            seats.0.push(Seat { name, controller: Controller::Cpu(Difficulty::Normal) });
+        }
     });
 }
 
-fn spawn_die(
-    hand_query: &Query<(Entity, &TextureAtlas), With<HandDie>>,
+/// Keys typed into a text box, such as a player's name, aren't menu
+/// shortcuts.
+fn capture_keyboard(
+    mut captured: ResMut<KeyboardCaptured>,
+    mut egui_context: EguiContexts,
+) {
+    captured.0 = egui_context.try_ctx_mut().is_some_and(|ctx| ctx.wants_keyboard_input());
+}
+
+/// A line on how each variant plays, for the rules window.
+fn variant_help(variant: Variant) -> &'static str {
+    match variant {
+        Variant::Classic => "Roll one die. A 1 loses the turn's points.",
+        Variant::TwoDice => "Roll two dice. A 1 loses the turn's points; double ones lose everything.",
+        Variant::BigPig => "Roll two dice. A 1 loses the turn's points; doubles count double and double ones score 25.",
+        Variant::Hog => "Throw as many dice as you dare, once a turn. Any 1 scores nothing.",
+        Variant::Piglet => "Flip a coin. Heads scores 1; tails loses the turn's points.",
+    }
+}
+
+fn choose_rules(
+    mut rules: ResMut<TableRules>,
+    mut egui_context: EguiContexts,
+) {
+    let Some(ctx) = egui_context.try_ctx_mut() else {
+        return;
+    };
+    egui::Window::new("Rules").show(ctx, |ui| {
+        let mut variant = rules.0.variant;
+        egui::ComboBox::from_label("Variant")
+            .selected_text(variant.name())
+            .show_ui(ui, |ui| {
+                for choice in Variant::ALL {
+                    ui.selectable_value(&mut variant, choice, choice.name());
+                }
+            });
+        if variant != rules.0.variant {
+            rules.0 = Rules::new(variant);
+        }
+        ui.label(variant_help(variant));
+        ui.horizontal(|ui| {
+            ui.label("Play to:");
+            ui.add(egui::DragValue::new(&mut rules.0.target).range(1..=1000));
+        });
+        if variant != Variant::Piglet {
+            ui.horizontal(|ui| {
+                ui.label("Sides on each die:");
+                ui.add(egui::DragValue::new(&mut rules.0.die_sides).range(2..=MAX_DIE_SIDES));
+            });
+        }
+    });
+}
+
+fn choose_options(
+    mut dice: ResMut<DiceSettings>,
+    mut access: ResMut<Accessibility>,
+    mut egui_context: EguiContexts,
+) {
+    let Some(ctx) = egui_context.try_ctx_mut() else {
+        return;
+    };
+    egui::Window::new("Options").show(ctx, |ui| {
+        ui.add(egui::Slider::new(&mut dice.roll_seconds, 0.0..=2.0).text("seconds for a throw to land"));
+        egui::ComboBox::from_label("Dice images")
+            .selected_text(dice.quality.describe())
+            .show_ui(ui, |ui| {
+                for choice in DiceQuality::ALL {
+                    ui.selectable_value(&mut dice.quality, choice, choice.describe());
+                }
+            });
+        // Only touch the settings when they change, so they're only saved
+        // then.
+        let mut settings = *access;
+        ui.checkbox(&mut settings.high_contrast, "High contrast");
+        ui.add(egui::Slider::new(&mut settings.text_scale, 0.75..=2.0).text("text size"));
+        egui::ComboBox::from_label("Player colours")
+            .selected_text(settings.palette.describe())
+            .show_ui(ui, |ui| {
+                for choice in access::Palette::ALL {
+                    ui.selectable_value(&mut settings.palette, choice, choice.describe());
+                }
+            });
+        if settings != *access {
+            *access = settings;
+        }
+    });
+}
+
+/// Starts solving the optimal policy for the table's rules once it is
+/// chosen, and picks up the result when it's ready.
+fn solve_policy(
+    seats: Res<Seats>,
+    table: Res<TableRules>,
+    game: Option<Res<Game>>,
+    dir: Res<DataDir>,
+    mut policy: ResMut<OptimalPolicy>,
+) {
+    // A game in progress may have been loaded with other rules.
+    let rules = game.map_or(table.0, |game| *game.0.rules());
+    let wanted = Policy::solves(&rules)
+        && seats.0.iter().any(|seat| seat.controller == Controller::Cpu(Difficulty::Optimal));
+    let stale = match &mut *policy {
+        OptimalPolicy::Unsolved => true,
+        OptimalPolicy::Solving(task) => {
+            if let Some(solved) = block_on(future::poll_once(task)) {
+                *policy = OptimalPolicy::Ready(Arc::new(solved));
+            }
+            false
+        }
+        OptimalPolicy::Ready(solved) => !solved.plays(&rules),
+    };
+    if wanted && stale {
+        let (dir, target) = (dir.clone(), rules.target);
+        *policy = OptimalPolicy::Solving(AsyncComputeTaskPool::get().spawn(async move { load_or_solve(&dir, target) }));
+    }
+}
+
+fn load_or_solve(dir: &DataDir, target: u32) -> Policy {
+    match Policy::load(dir, target) {
+        Ok(Some(policy)) => return policy,
+        Ok(None) => {}
+        Err(e) => warn!("Couldn't load the optimal policy: {e}"),
+    }
+    let policy = Policy::solve(target);
+    if let Err(e) = policy.save(dir) {
+        warn!("Couldn't save the optimal policy: {e}");
+    }
+    policy
+}
+
+/// How a rolled `value` reads: heads or tails for a coin, otherwise the
+/// number.
+fn face_label(rules: &Rules, value: u32) -> String {
+    match (rules.variant, value) {
+        (Variant::Piglet, 1) => "T".to_string(),
+        (Variant::Piglet, _) => "H".to_string(),
+        _ => value.to_string(),
+    }
+}
+
+fn die_visuals(
     commands: &mut Commands,
     assets: &GameAssets,
-    new_roll: usize,
+    layout: &TableLayout,
+    position: usize,
+    value: usize,
     color: Color,
-) {
-    let rolled_die = hand_query.iter().count() as f32 * 52.0;
+    label: Option<String>,
+) -> Entity {
+    let transform = Transform::from_translation(layout.die_position(position))
+        .with_scale(dice::die_scale(layout, assets, label.is_some()));
+    // The dice sheet only has pips for 1 to 6, so coins and bigger dice
+    // are plain tiles with their face written on.
+    if let Some(label) = label {
+        return commands
+            .spawn((
+                HandDie(position),
+                SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(48.0)),
+                        ..default()
+                    },
+                    transform,
+                    ..default()
+                },
+            ))
+            .with_children(|tile| {
+                tile.spawn(Text2dBundle {
+                    text: Text::from_section(label, TextStyle { font_size: 32.0, color: Color::BLACK, ..default() }),
+                    transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                    ..default()
+                });
+            })
+            .id();
+    }
+    let animation = SpriteAnimation::new(assets.animations.clone(), &face(value));
     commands
         .spawn((
+            HandDie(position),
             SpriteBundle {
                 sprite: Sprite {
                     color,
                     ..default()
                 },
                 texture: assets.image.clone(),
-                transform: Transform::from_xyz(rolled_die - 400.0, 60.0, 1.0),
+                transform,
+                // Until the sheet loads there's no grid to pick a face from.
+                visibility: if assets.tile.is_some() { Visibility::Inherited } else { Visibility::Hidden },
                 ..default()
             },
             TextureAtlas {
                 layout: assets.atlas.clone(),
-                index: new_roll - 1,
+                index: animation.atlas_index(),
             },
+            animation,
         ))
-        .insert(HandDie)
-        .insert(GameElement);
+        .id()
 }
 
-fn clear_die(
-    hand_query: &Query<(Entity, &TextureAtlas), With<HandDie>>,
-    commands: &mut Commands,
-) {
-    hand_query
-        .iter()
-        .for_each(|(entity, _)| commands.entity(entity).despawn());
+/// Writes `seat`'s number under a die `size` pixels across before
+/// scaling, so it can be told apart without its colour.
+fn number_die(commands: &mut Commands, die: Entity, seat: usize, size: f32) {
+    commands.entity(die).with_children(|die| {
+        die.spawn(Text2dBundle {
+            text: Text::from_section((seat + 1).to_string(), TextStyle { font_size: 0.4 * size, color: Color::WHITE, ..default() }),
+            transform: Transform::from_xyz(0.0, -0.8 * size, 0.1),
+            ..default()
+        });
+    });
 }
 
-fn player(
-    hand_query: Query<(Entity, &TextureAtlas), With<HandDie>>,
+/// Throws every roll in the history onto the table. A turn's dice stay
+/// there until the next turn's first roll, so a bust can be seen.
+#[allow(clippy::too_many_arguments)]
+fn show_dice(
+    mut table: Local<DiceTable>,
     mut commands: Commands,
-    rng: Res<RandomNumberGenerator>,
     assets: Res<GameAssets>,
-    mut scores: ResMut<Scores>,
-    mut state: ResMut<NextState<GamePhase>>,
-    mut egui_context: EguiContexts,
+    game: Res<Game>,
+    history: Res<GameHistory>,
+    settings: Res<DiceSettings>,
+    layout: Res<TableLayout>,
+    access: Res<Accessibility>,
 ) {
-    egui::Window::new("Play Options").show(egui_context.ctx_mut(), |ui| {
-        let hand_score: usize = hand_query.iter().map(|(_, ts)| ts.index + 1).sum();
-        ui.label(&format!("Score for this hand: {hand_score}"));
-
-        if ui.button("Roll Dice").clicked() {
-            let new_roll = rng.range(1..=6);
-            if new_roll == 1 {
-                // End turn!
-                clear_die(&hand_query, &mut commands);
-                state.set(GamePhase::Cpu);
-            } else {
-                spawn_die(
-                    &hand_query,
-                    &mut commands,
-                    &assets,
-                    new_roll,
-                    Color::WHITE,
-                );
+    if history.is_added() {
+        // A new or loaded game: the old dice went with the old game.
+        *table = DiceTable::default();
+    }
+    if !history.is_changed() {
+        return;
+    }
+    let rules = game.0.rules();
+    let events = history.0.turns().iter().enumerate().flat_map(|(i, turn)| turn.events.iter().map(move |event| (i, turn, event)));
+    let mut seen = 0;
+    for (i, turn, event) in events.skip(table.events) {
+        seen += 1;
+        let (HistoryEvent::Rolled { faces, .. } | HistoryEvent::Bust { faces } | HistoryEvent::WipedOut { faces }) = event else {
+            continue;
+        };
+        if i != table.turn {
+            table.dice.drain(..).for_each(|entity| commands.entity(entity).despawn_recursive());
+            table.turn = i;
+        }
+        for &value in faces {
+            let label = (rules.variant == Variant::Piglet || value > 6).then(|| face_label(rules, value));
+            let position = table.dice.len();
+            let color = access.dice_color(turn.player);
+            let tile = label.is_some();
+            let die = die_visuals(&mut commands, &assets, &layout, position, value as usize, color, label);
+            if access.numbers_dice() {
+                let size = if tile { 52.0 } else { assets.sheet.die_pixels };
+                number_die(&mut commands, die, turn.player, size);
+            }
+            if settings.roll_seconds > 0.0 {
+                let end = layout.die_position(position);
+                commands.entity(die).insert(Tumble::new(value as usize, end, layout.die, settings.roll_seconds));
             }
+            table.dice.push(die);
+        }
+    }
+    table.events += seen;
+}
+
+/// Bounces thrown dice across the table, slowing down as they go, and
+/// lands them on their roll.
+fn tumble_dice(
+    mut dice: Query<(Entity, &mut Tumble, &mut Transform, Option<&mut SpriteAnimation>)>,
+    time: Res<Time>,
+    mut settled: EventWriter<DiceSettled>,
+    mut commands: Commands,
+) {
+    const BOUNCES: f32 = 3.0;
+    for (entity, mut tumble, mut transform, animation) in dice.iter_mut() {
+        tumble.elapsed += time.delta_seconds();
+        let t = (tumble.elapsed / tumble.duration).min(1.0);
+        // Ease out: fast off the hand, slow into place.
+        let eased = 1.0 - (1.0 - t).powi(3);
+        let hop = tumble.height * (1.0 - t).powi(2) * (std::f32::consts::PI * BOUNCES * t).sin().abs();
+        transform.translation = tumble.start.lerp(tumble.end, eased) + Vec3::Y * hop;
+        transform.rotation = Quat::from_rotation_z((1.0 - eased) * 4.0 * std::f32::consts::PI);
+
+        if let Some(mut animation) = animation {
+            animation.play(&if t < 1.0 { "tumble".to_string() } else { face(tumble.value) });
         }
-        if ui.button("Pass - Keep Hand Score").clicked() {
-            let hand_total: usize = hand_query.iter().map(|(_, ts)| ts.index + 1).sum();
-            scores.player += hand_total;
-            clear_die(&hand_query, &mut commands);
-            state.set(GamePhase::Cpu);
+        if t < 1.0 {
+            continue;
         }
-    });
+        commands.entity(entity).remove::<Tumble>();
+        settled.send(DiceSettled { value: tumble.value });
+    }
+}
+
+/// Follows the rules to the next player's turn, or to the end of the game.
+fn follow_turns(
+    game: Res<Game>,
+    seats: Res<Seats>,
+    state: Res<State<GamePhase>>,
+    mut next_state: ResMut<NextState<GamePhase>>,
+) {
+    let phase = seats.phase(&game.0);
+    if phase != *state.get() {
+        next_state.set(phase);
+    }
+}
+
+/// Rolls a die with `sides` faces.
+fn roll_die(rng: &RandomNumberGenerator, sides: u32) -> u32 {
+    rng.range(1..=sides)
+}
+
+/// Saves after every move, until someone wins. Network games aren't
+/// saved.
+fn autosave(
+    game: Res<Game>,
+    network: Res<Network>,
+    mut save: EventWriter<SaveGame>,
+) {
+    if game.is_changed() && !game.0.is_over() && network.is_offline() {
+        save.send(SaveGame);
+    }
+}
+
+fn default_bindings() -> InputMap<PigAction> {
+    InputMap::new()
+        .bind(PigAction::Roll, InputBinding::Key(KeyCode::KeyR))
+        .bind(PigAction::Roll, InputBinding::Key(KeyCode::Space))
+        .bind(PigAction::Hold, InputBinding::Key(KeyCode::KeyH))
+        .bind(PigAction::Hold, InputBinding::Key(KeyCode::Enter))
+}
+
+/// The move the bound keys ask for: Roll rolls (or throws, in Hog) and
+/// Hold holds.
+fn shortcut(actions: &ActionState<PigAction>, variant: Variant, hog_dice: u32) -> Option<Action> {
+    if actions.just_pressed(PigAction::Roll) {
+        return Some(if variant == Variant::Hog { Action::Throw(hog_dice) } else { Action::Roll });
+    }
+    let hold = variant != Variant::Hog && actions.just_pressed(PigAction::Hold);
+    hold.then_some(Action::Hold)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn human(
+    mut game: ResMut<Game>,
+    mut history: ResMut<GameHistory>,
+    seats: Res<Seats>,
+    rng: Res<RandomNumberGenerator>,
+    mut network: ResMut<Network>,
+    tumbling: Query<(), With<Tumble>>,
+    actions: Res<ActionState<PigAction>>,
+    bindings: Res<InputMap<PigAction>>,
+    access: Res<Accessibility>,
+    mut hog_dice: Local<Option<u32>>,
+    mut egui_context: EguiContexts,
+) {
+    let variant = game.0.rules().variant;
+    let ctx = egui_context.try_ctx_mut();
+    // Wait for the last throw to land before the next. Keys typed into a
+    // text box never reach the actions (see `capture_keyboard`).
+    let ready = tumbling.is_empty();
+    let mut action = None;
+    if ready {
+        action = shortcut(&actions, variant, *hog_dice.get_or_insert(5));
+    }
+    let (roll_keys, hold_keys) = (bindings.describe(PigAction::Roll), bindings.describe(PigAction::Hold));
+    if let Some(ctx) = ctx {
+        egui::Window::new("Play Options").show(ctx, |ui| {
+            let seat = game.0.current();
+            ui.colored_label(access.seat_color(seat), format!("{} {}'s turn", access::seat_tag(seat), seats.0[seat].name));
+            ui.label(format!("Score for this hand: {}", game.0.turn_total()));
+
+            ui.add_enabled_ui(ready, |ui| {
+                if variant == Variant::Hog {
+                    let dice = hog_dice.get_or_insert(5);
+                    ui.add(egui::Slider::new(dice, 1..=HOG_MAX_DICE).text("dice"));
+                    if ui.button(format!("Throw {dice} Dice ({roll_keys})")).clicked() {
+                        action = Some(Action::Throw(*dice));
+                    }
+                    return;
+                }
+                let roll = if variant == Variant::Piglet { "Flip Coin" } else { "Roll Dice" };
+                if ui.button(format!("{roll} ({roll_keys})")).clicked() {
+                    action = Some(Action::Roll);
+                }
+                if ui.button(format!("Pass - Keep Hand Score ({hold_keys})")).clicked() {
+                    action = Some(Action::Hold);
+                }
+            });
+        });
+    }
+    // A key and a click on the same frame still only make one move.
+    if let Some(action) = action {
+        network.act(action, &mut game.0, &mut history.0, &rng);
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
 fn cpu(
-    hand_query: Query<(Entity, &TextureAtlas), With<HandDie>>,
-    mut state: ResMut<NextState<GamePhase>>,
-    mut scores: ResMut<Scores>,
+    mut game: ResMut<Game>,
+    mut history: ResMut<GameHistory>,
+    seats: Res<Seats>,
     rng: Res<RandomNumberGenerator>,
-    mut commands: Commands,
-    assets: Res<GameAssets>,
+    policy: Res<OptimalPolicy>,
     mut timer: ResMut<HandTimer>,
     time: Res<Time>,
+    tumbling: Query<(), With<Tumble>>,
+    mut settled: EventReader<DiceSettled>,
 ) {
+    let Controller::Cpu(difficulty) = seats.0[game.0.current()].controller else {
+        return;
+    };
+    let Some(strategy) = difficulty.strategy(&policy, game.0.rules()) else {
+        return;
+    };
+    // Think once the dice have landed, like a person would.
+    if !tumbling.is_empty() {
+        return;
+    }
+    if settled.read().count() > 0 {
+        timer.0.reset();
+    }
     timer.0.tick(time.delta());
     if timer.0.just_finished() {
-        let hand_total: usize = hand_query.iter().map(|(_, ts)| ts.index + 1).sum();
-        if hand_total < 20 && scores.cpu + hand_total < 100 {
-            let new_roll = rng.range(1..=6);
-            if new_roll == 1 {
-                clear_die(&hand_query, &mut commands);
-                state.set(GamePhase::Player);
-            } else {
-                spawn_die(
-                    &hand_query,
-                    &mut commands,
-                    &assets,
-                    new_roll,
-                    Color::LinearRgba(LinearRgba { red: 0.0, green: 0.0, blue: 1.0, alpha: 1.0 }),
-                );
+        let action = strategy.decide(&game.0);
+        // Only fails once the game is over, and then there's nothing to do.
+        let sides = game.0.rules().faces();
+        let _ = history.0.play(&mut game.0, action, || roll_die(&rng, sides));
+    }
+}
+
+/// The log of every turn so far, newest at the bottom.
+fn show_history(
+    history: Res<GameHistory>,
+    seats: Res<Seats>,
+    access: Res<Accessibility>,
+    mut egui_context: EguiContexts,
+) {
+    let Some(ctx) = egui_context.try_ctx_mut() else {
+        return;
+    };
+    egui::Window::new("History").default_height(200.0).show(ctx, |ui| {
+        egui::ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+            for (i, turn) in history.0.turns().iter().enumerate() {
+                let line = format!("{}. {} {}", i + 1, access::seat_tag(turn.player), turn.describe(&seats.0[turn.player].name));
+                ui.colored_label(access.seat_color(turn.player), line);
+            }
+        });
+    });
+}
+
+/// Every player's statistics once the game is over, and the history to
+/// save.
+fn game_summary(
+    history: Option<Res<GameHistory>>,
+    seats: Res<Seats>,
+    access: Res<Accessibility>,
+    dir: Res<DataDir>,
+    mut exported: Local<Option<String>>,
+    mut egui_context: EguiContexts,
+) {
+    let (Some(history), Some(ctx)) = (history, egui_context.try_ctx_mut()) else {
+        return;
+    };
+    egui::Window::new("Summary").show(ctx, |ui| {
+        egui::Grid::new("stats").show(ui, |ui| {
+            for heading in ["", "Turns", "Rolls", "Busts", "Per turn", "Longest streak"] {
+                ui.strong(heading);
             }
-        } else {
-            scores.cpu += hand_total;
-            state.set(GamePhase::Player);
-            hand_query
-                .iter()
-                .for_each(|(entity, _)| commands.entity(entity).despawn());
+            ui.end_row();
+            for (i, stats) in history.0.stats(seats.0.len()).iter().enumerate() {
+                ui.colored_label(access.seat_color(i), format!("{} {}", access::seat_tag(i), seats.0[i].name));
+                ui.label(stats.turns.to_string());
+                ui.label(stats.rolls.to_string());
+                ui.label(stats.busts.to_string());
+                ui.label(format!("{:.1}", stats.average_turn()));
+                ui.label(format!("{} rolls", stats.longest_streak));
+                ui.end_row();
+            }
+        });
+        ui.horizontal(|ui| {
+            for (button, extension) in [("Export Text", "txt"), ("Export JSON", "json")] {
+                if ui.button(button).clicked() {
+                    *exported = Some(match export_history(&dir, &history.0, &seats, extension) {
+                        Ok(path) => format!("Saved to {}", path.display()),
+                        Err(e) => format!("Couldn't export the history: {e}"),
+                    });
+                }
+            }
+        });
+        if let Some(message) = &*exported {
+            ui.label(message);
         }
-    }
+    });
 }
 
-fn start_game(mut state: ResMut<NextState<GamePhase>>) {
-    state.set(GamePhase::Player);
+/// Writes the history to Pig's data folder as text or JSON.
+fn export_history(dir: &DataDir, history: &History, seats: &Seats, extension: &str) -> std::io::Result<std::path::PathBuf> {
+    let names: Vec<String> = seats.0.iter().map(|seat| seat.name.clone()).collect();
+    let contents = match extension {
+        "json" => history.to_json(&names),
+        _ => history.to_text(&names),
+    };
+    let path = dir.game("pig")?.join(format!("{HISTORY_FILE}.{extension}"));
+    persistence::write_atomic(&path, contents.as_bytes())?;
+    Ok(path)
 }
 
-fn end_game(
+fn start_game(
     mut state: ResMut<NextState<GamePhase>>,
-    scores: Res<Scores>,
-    mut commands: Commands,
+    game: Res<Game>,
+    seats: Res<Seats>,
 ) {
-    commands.insert_resource(FinalScore(*scores));
-    state.set(GamePhase::GameOver);
+    state.set(seats.phase(&game.0));
 }
 
-fn check_game_over(
+fn end_game(
     mut state: ResMut<NextState<GamePhase>>,
-    scores: Res<Scores>,
+    game: Res<Game>,
+    seats: Res<Seats>,
+    rng: Res<RandomNumberGenerator>,
+    mut commands: Commands,
 ) {
-    if scores.cpu >= 100 || scores.player >= 100 {
-        state.set(GamePhase::End);
+    let mut results = GameResults::new("Game Over");
+    for (i, seat) in seats.0.iter().enumerate() {
+        results = results.line(format!("{}: {}", seat.name, game.0.score(i)));
+    }
+    if let Some(winner) = game.0.winner() {
+        results = results.winner(seats.0[winner].name.clone());
+    }
+    commands.insert_resource(results);
+    // The high score table is for humans: the best of them gets a chance
+    // at it.
+    let best_human = (0..seats.0.len())
+        .filter(|&i| seats.0[i].controller == Controller::Human)
+        .map(|i| game.0.score(i))
+        .max();
+    if let Some(score) = best_human {
+        commands.insert_resource(NewScore { score, seed: rng.seed() });
     }
+    state.set(GamePhase::GameOver);
 }
 
-fn display_final_score(
-    scores: Res<FinalScore>,
-    mut egui_context: EguiContexts,
-) {
-    egui::Window::new("Total Scores").show(egui_context.ctx_mut(), |ui| {
-        ui.label(&format!("Player: {}", scores.0.player));
-        ui.label(&format!("CPU: {}", scores.0.cpu));
-        if scores.0.player < scores.0.cpu {
-            ui.label("CPU is the winner!")
-        } else {
-            ui.label("Player is the winner!")
-        }
-    });
+/// Throws, lands and announces the dice, after the moves (local or from
+/// the network) that rolled them.
+fn dice_systems() -> SystemConfigs {
+    (show_dice, tumble_dice, access::announce).chain().after(network::sync)
+}
+
+fn phases() -> PhaseGraph<GamePhase> {
+    PhaseGraph::new()
+        .phase(GamePhase::Start, |phase| phase
+            .on_enter(setup)
+            .update(start_game))
+        .phase(GamePhase::Lobby, |phase| phase
+            .update(network::lobby))
+        .phase(GamePhase::Human, |phase| phase
+            .update(((human, follow_turns, dice_systems()).chain(), display_score, show_history, autosave)))
+        .phase(GamePhase::Cpu, |phase| phase
+            .update(((cpu, follow_turns, dice_systems()).chain(), display_score, show_history, autosave)))
+        .phase(GamePhase::Remote, |phase| phase
+            .update(((follow_turns, dice_systems()).chain(), display_score, show_history)))
+        .phase(GamePhase::End, |phase| phase
+            .update(end_game))
+        // The board, dice and scores last for the whole game.
+        .group("game", [GamePhase::Start, GamePhase::Human, GamePhase::Cpu, GamePhase::Remote, GamePhase::End])
+        .transition(GamePhase::MainMenu, GamePhase::Lobby)
+        .transition(GamePhase::Lobby, GamePhase::MainMenu)
+        .transition(GamePhase::Lobby, GamePhase::Start)
+        .transition(GamePhase::Start, GamePhase::Human)
+        // The first seat may be a CPU, and a saved game may resume on a
+        // CPU's turn.
+        .transition(GamePhase::Start, GamePhase::Cpu)
+        .transition(GamePhase::Human, GamePhase::Cpu)
+        .transition(GamePhase::Cpu, GamePhase::Human)
+        .transition(GamePhase::Start, GamePhase::Remote)
+        .transition(GamePhase::Human, GamePhase::Remote)
+        .transition(GamePhase::Remote, GamePhase::Human)
+        .transition(GamePhase::Remote, GamePhase::End)
+        .transition(GamePhase::Human, GamePhase::End)
+        .transition(GamePhase::Cpu, GamePhase::End)
+        .transition(GamePhase::End, GamePhase::GameOver)
+        // The history stays for the summary on the game over screen.
+        .scope_resource::<Game>()
+        .scope_resource::<GameAssets>()
+        .scope_resource::<HandTimer>()
 }
 
 fn main() {
     let mut app = App::new();
-
-    add_phase!(app, GamePhase, GamePhase::Start,
-        start => [ setup ], run => [ start_game ], exit => [ ]
-    );
-
-    add_phase!(app, GamePhase, GamePhase::Player,
-        start => [ ], run => [ player, check_game_over, display_score ], exit => [ ]
-    );
-
-    add_phase!(app, GamePhase, GamePhase::Cpu,
-        start => [ ], run => [ cpu, check_game_over, display_score ], exit => [ ]
-    );
-
-    add_phase!(app, GamePhase, GamePhase::End,
-        start => [ ], run => [ end_game ], exit => [ cleanup::<GameElement> ]
-    );
-
-    add_phase!(app, GamePhase, GamePhase::GameOver,
-        start => [ ], run => [ display_final_score ], exit => [ ]
-    );
-
     app.add_plugins(DefaultPlugins.set(WindowPlugin {
         primary_window: Some(Window {
             title: "Pig - Bevy Edition".to_string(),
@@ -250,13 +975,225 @@ fn main() {
             ..default()
         }),
         ..default()
-        }))
-        .add_plugins(GameStatePlugin::new(
+        }));
+    add_game(&mut app);
+    app.run();
+}
+
+/// Adds everything but the window and renderer, so tests can run the game
+/// headless.
+fn add_game(app: &mut App) {
+    let dir = DataDir::from_app(app);
+    app.add_systems(Update, (choose_seats, choose_rules, choose_options, network::network_menu).run_if(in_state(GamePhase::MainMenu)))
+        .add_systems(OnEnter(GamePhase::MainMenu), network::go_offline)
+        .add_systems(Update, game_summary.run_if(in_state(GamePhase::GameOver)))
+        .add_systems(Update, (solve_policy, network::sync, access::apply_settings))
+        .add_systems(PreUpdate, capture_keyboard.before(ActionSystems::Update))
+        .add_systems(
+            Update,
+            (dice::lay_out_table, (dice::load_dice_sheet, dice::fit_dice).chain().run_if(resource_exists::<GameAssets>))
+                .chain(),
+        )
+        .init_resource::<TableLayout>()
+        .init_resource::<Seats>()
+        .init_resource::<TableRules>()
+        .init_resource::<DiceSettings>()
+        .insert_resource(Accessibility::load(&dir))
+        .add_event::<DiceSettled>()
+        .init_resource::<Network>()
+        .init_resource::<NetworkForm>()
+        .init_resource::<OfflineSeats>()
+        .init_resource::<OptimalPolicy>();
+    app.add_plugins(GameStatePlugin::new(
+            GamePhase::MainMenu,
+            GamePhase::Start,
+            GamePhase::GameOver,
+        )
+        // Turns change state too, so only the menus get transitions.
+        .with_transition_between(
             GamePhase::MainMenu,
             GamePhase::Start,
+            Transition::Wipe { duration: 0.6 },
+        )
+        .with_transition_between(
+            GamePhase::End,
             GamePhase::GameOver,
+            Transition::Crossfade { duration: 0.8 },
+        )
+        .with_transition_between(
+            GamePhase::GameOver,
+            GamePhase::MainMenu,
+            Transition::FadeToBlack { duration: 0.6 },
         ))
+        .add_phase_graph(phases())
+        .add_plugins(ActionPlugin::new(default_bindings()).persist("pig", "controls.ron"))
         .add_plugins(EguiPlugin)
         .add_plugins(RandomPlugin)
-        .run();
+        .add_plugins(SpriteAnimationPlugin)
+        .add_plugins(HighScorePlugin::new("pig"))
+        .add_plugins(
+            SaveGamePlugin::<GamePhase>::new("pig", 5)
+                .resource::<Game>()
+                .resource::<GameHistory>()
+                .resource::<Seats>(),
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use my_library::testing::TestApp;
+
+    #[test]
+    fn test_menu_flow() {
+        let mut game = TestApp::new(1, add_game);
+        game.update().assert_state(GamePhase::MainMenu);
+        game.tap(KeyCode::KeyP).run_until_state(GamePhase::Human, 120);
+        assert_eq!(game.resource::<Game>().0.scores(), [0, 0]);
+
+        let mut rules = game.resource_mut::<Game>();
+        for _ in 0..17 {
+            rules.0.roll(6).unwrap();
+        }
+        rules.0.hold().unwrap();
+        game.run_until_state(GamePhase::GameOver, 10);
+        let results = game.resource::<GameResults>();
+        assert_eq!(results.winner.as_deref(), Some("Player"));
+        // Autosaving doesn't disturb the seed the game is recorded under.
+        assert_eq!(game.resource::<RandomNumberGenerator>().seed(), 1);
+
+        // The first score always makes the empty high score table.
+        game.type_text("Tester").enter();
+        assert_eq!(game.resource::<HighScores>().entries()[0].name, "Tester");
+
+        game.tap(KeyCode::KeyM).run_until_state(GamePhase::MainMenu, 120);
+        assert!(!game.has_resource::<Game>());
+        assert!(!game.has_resource::<GameResults>());
+    }
+
+    #[test]
+    fn test_seats_take_turns() {
+        let mut game = TestApp::new(2, add_game);
+        game.update();
+        let seat = |name: &str, controller| Seat { name: name.to_string(), controller };
+        game.resource_mut::<Seats>().0 = vec![
+            seat("Ann", Controller::Human),
+            seat("Bob", Controller::Human),
+            seat("Easy", Controller::Cpu(Difficulty::Easy)),
+        ];
+        game.tap(KeyCode::KeyP).run_until_state(GamePhase::Human, 120);
+        assert_eq!(game.resource::<Game>().0.players(), 3);
+
+        // Ann passes to Bob in the hot seat, then Bob passes to the CPU.
+        game.resource_mut::<Game>().0.hold().unwrap();
+        game.advance_frames(2).assert_state(GamePhase::Human);
+        assert_eq!(game.resource::<Game>().0.current(), 1);
+        game.resource_mut::<Game>().0.hold().unwrap();
+        game.run_until_state(GamePhase::Cpu, 10).run_until_state(GamePhase::Human, 600);
+
+        let rules = &game.resource::<Game>().0;
+        assert_eq!(rules.current(), 0);
+        let cpu = rules.score(2);
+        assert!(cpu == 0 || (10..16).contains(&cpu), "the CPU banked {cpu}");
+        // Only the CPU's moves went through the game's systems.
+        let turns = game.resource::<GameHistory>().0.turns().to_vec();
+        assert_eq!(turns.len(), 1);
+        assert_eq!((turns[0].player, turns[0].banked()), (2, cpu));
+    }
+
+    #[test]
+    fn test_thrown_dice_land_on_their_roll() {
+        let mut game = TestApp::new(4, add_game);
+        game.update();
+        game.resource_mut::<Seats>().0[1].controller = Controller::Human;
+        game.tap(KeyCode::KeyP).run_until_state(GamePhase::Human, 120);
+        game.app.world_mut().resource_scope(|world, mut history: Mut<GameHistory>| {
+            let mut rules = world.resource_mut::<Game>();
+            history.0.play(&mut rules.0, Action::Roll, || 4).unwrap();
+        });
+        game.advance_frames(2).assert_count::<Tumble>(1);
+        let mut dice = game.app.world_mut().query_filtered::<&SpriteAnimation, With<HandDie>>();
+        assert_eq!(dice.single(game.app.world()).clip(), "tumble");
+
+        // 0.8 seconds at 64 frames a second.
+        game.advance_frames(60).assert_count::<Tumble>(0);
+        let mut dice = game.app.world_mut().query_filtered::<(&SpriteAnimation, &Transform), With<HandDie>>();
+        let (animation, transform) = dice.single(game.app.world());
+        assert_eq!(animation.clip(), "face4");
+        assert_eq!(transform.translation, TableLayout::default().die_position(0));
+    }
+
+    #[test]
+    fn test_keys_play_and_rolls_are_announced() {
+        let mut game = TestApp::new(4, add_game);
+        game.update();
+        game.resource_mut::<Seats>().0[1].controller = Controller::Human;
+        game.tap(KeyCode::KeyP).run_until_state(GamePhase::Human, 120);
+        let announcement = |game: &mut TestApp| {
+            let mut node = game.app.world_mut().query::<&bevy::a11y::AccessibilityNode>();
+            node.single(game.app.world()).name().map(str::to_string)
+        };
+
+        game.tap(KeyCode::KeyR);
+        let rolled = game.resource::<GameHistory>().0.turns()[0].events.clone();
+        assert_eq!(rolled.len(), 1);
+        // Nothing is read out, and no more keys work, until the die lands.
+        game.tap(KeyCode::Space);
+        assert_eq!(announcement(&mut game), None);
+        game.advance_frames(60);
+        assert_eq!(game.resource::<GameHistory>().0.turns()[0].events, rolled);
+        assert_eq!(announcement(&mut game), Some(format!("Player {}.", rolled[0].describe())));
+
+        // Seed 4 rolls a 5, so there's something to keep.
+        assert_eq!(rolled[0], HistoryEvent::Rolled { faces: vec![5], points: 5 });
+        game.tap(KeyCode::Enter);
+        assert_eq!(game.resource::<Game>().0.score(0), 5);
+        game.advance_frames(2);
+        assert_eq!(announcement(&mut game), Some("Player held 5. CPU's turn.".to_string()));
+    }
+
+    #[test]
+    fn test_offline_seats_return_after_a_network_game() {
+        let mut game = TestApp::new(5, add_game);
+        game.update();
+        let offline = game.resource::<Seats>().0.clone();
+        let host = pig::net::Host::bind("127.0.0.1:0", "Host").unwrap();
+        *game.resource_mut::<Network>() = Network::Hosting(host);
+        game.app.world_mut().resource_scope(|world, mut seats: Mut<Seats>| {
+            let players = ["Host".to_string(), "Ann".to_string()];
+            network::seat_players(&mut seats, &mut world.resource_mut::<OfflineSeats>(), &players, 0);
+        });
+        game.set_state(GamePhase::Start).run_until_state(GamePhase::Human, 120);
+        assert_eq!(game.resource::<Seats>().0[1].controller, Controller::Remote);
+
+        let mut rules = game.resource_mut::<Game>();
+        for _ in 0..17 {
+            rules.0.roll(6).unwrap();
+        }
+        rules.0.hold().unwrap();
+        game.run_until_state(GamePhase::GameOver, 10);
+        game.type_text("Host").enter();
+        game.tap(KeyCode::KeyM).run_until_state(GamePhase::MainMenu, 120);
+        assert!(game.resource::<Network>().is_offline());
+        assert_eq!(game.resource::<Seats>().0, offline);
+
+        // The CPU is back in the second seat, and takes its turn.
+        game.tap(KeyCode::KeyP).run_until_state(GamePhase::Human, 120);
+        game.resource_mut::<Game>().0.hold().unwrap();
+        game.run_until_state(GamePhase::Cpu, 10);
+    }
+
+    #[test]
+    fn test_table_rules_pick_the_variant() {
+        let mut game = TestApp::new(3, add_game);
+        game.update();
+        game.resource_mut::<TableRules>().0 = Rules { target: 30, ..Rules::new(Variant::BigPig) };
+        game.tap(KeyCode::KeyP).run_until_state(GamePhase::Human, 120);
+
+        let mut rules = game.resource_mut::<Game>();
+        assert_eq!(rules.0.rules().variant, Variant::BigPig);
+        rules.0.roll_dice(&[6, 6]).unwrap();
+        rules.0.roll_dice(&[4, 4]).unwrap();
+        assert_eq!(rules.0.hold(), Ok(pig::rules::Outcome::Won { player: 0, score: 40 }));
+    }
 }
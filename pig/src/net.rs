@@ -0,0 +1,439 @@
+//! Pig between machines. One player hosts: their copy of the game runs
+//! the rules and rolls the dice. Everyone else joins over TCP, sends the
+//! host their moves and is sent the game after every change.
+//!
+//! Messages are RON, one per line. Every connection starts with the
+//! client saying [`ClientMessage::Hello`]. The host answers with
+//! [`HostMessage::Welcome`], which includes a token: a client that loses
+//! its connection can say hello with the token to take its seat back.
+use crate::history::History;
+use crate::rules::{Action, PigGame};
+use my_library::RandomNumberGenerator;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The port games are hosted on, unless the address says otherwise.
+pub const DEFAULT_PORT: u16 = 4242;
+/// The most players at one table, the host included.
+pub const MAX_PLAYERS: usize = 8;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// The first message on every connection. `token` comes from an
+    /// earlier welcome, to take back a seat.
+    Hello { name: String, token: Option<u64> },
+    /// The client's move, on their turn.
+    Act(Action),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HostMessage {
+    /// The client sits in `seat`. Keep `token` to reconnect.
+    Welcome { seat: usize, token: u64 },
+    /// Everyone waiting to play, by seat.
+    Lobby { players: Vec<String> },
+    /// The game has started, or moved on.
+    State { players: Vec<String>, game: PigGame, history: History },
+    /// The host turned the connection away.
+    Refused(String),
+}
+
+/// Something that happened to one of the host's players.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HostEvent {
+    Joined(usize),
+    /// A player's connection dropped. Before the game starts they lose
+    /// their seat, and everyone after them moves up one.
+    Left(usize),
+    /// A player came back to their seat after losing the connection.
+    Rejoined(usize),
+    /// A player wants to make a move. It's up to the host to check that
+    /// it's their turn.
+    Acted { seat: usize, action: Action },
+}
+
+/// Writes `message` as one line.
+fn send<T: Serialize>(stream: &mut TcpStream, message: &T) -> io::Result<()> {
+    let mut line = ron::to_string(message).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())
+}
+
+/// Reads messages from `stream` on another thread, and passes them on
+/// tagged with `connection`. `None` means the connection closed.
+fn listen<T: DeserializeOwned + Send + 'static>(
+    stream: TcpStream,
+    connection: usize,
+    sender: Sender<(usize, Option<T>)>,
+) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            // Lines that don't parse are ignored, rather than trusted.
+            if let Ok(message) = ron::from_str(&line) {
+                if sender.send((connection, Some(message))).is_err() {
+                    return;
+                }
+            }
+        }
+        let _ = sender.send((connection, None));
+    });
+}
+
+/// `address`, with the default port added if it doesn't have one.
+fn with_port(address: &str) -> String {
+    match address.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_ok() => address.to_string(),
+        _ => format!("{address}:{DEFAULT_PORT}"),
+    }
+}
+
+struct Remote {
+    name: String,
+    token: u64,
+    /// The connection id and stream, while connected.
+    connection: Option<(usize, TcpStream)>,
+}
+
+/// Hosts a game. The host's own player sits in seat 0, and players who
+/// join take the following seats.
+pub struct Host {
+    name: String,
+    listener: TcpListener,
+    sender: Sender<(usize, Option<ClientMessage>)>,
+    /// Behind a mutex only so the host can be shared between threads.
+    incoming: Mutex<Receiver<(usize, Option<ClientMessage>)>>,
+    next_connection: usize,
+    /// Connections that haven't said hello yet.
+    greeting: Vec<(usize, TcpStream)>,
+    remotes: Vec<Remote>,
+    started: bool,
+    rng: RandomNumberGenerator,
+}
+
+impl Host {
+    /// Opens a lobby on `address`. Use port 0 to let the system pick one.
+    pub fn bind(address: impl ToSocketAddrs, name: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let (sender, incoming) = channel();
+        Ok(Self {
+            name: name.to_string(),
+            listener,
+            sender,
+            incoming: Mutex::new(incoming),
+            next_connection: 0,
+            greeting: Vec::new(),
+            remotes: Vec::new(),
+            started: false,
+            rng: RandomNumberGenerator::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Everyone's names, by seat.
+    pub fn players(&self) -> Vec<String> {
+        std::iter::once(self.name.clone())
+            .chain(self.remotes.iter().map(|remote| remote.name.clone()))
+            .collect()
+    }
+
+    /// Is the player in `seat` connected? The host always is.
+    pub fn is_connected(&self, seat: usize) -> bool {
+        seat == 0 || self.remotes.get(seat - 1).is_some_and(|remote| remote.connection.is_some())
+    }
+
+    /// Closes the lobby. From now on only players coming back to their
+    /// seats can join.
+    pub fn start(&mut self) {
+        self.started = true;
+    }
+
+    /// Accepts new connections and reads what players have sent.
+    pub fn poll(&mut self) -> Vec<HostEvent> {
+        while let Ok((stream, _)) = self.listener.accept() {
+            let connection = self.next_connection;
+            self.next_connection += 1;
+            let reader = stream
+                .set_nonblocking(false)
+                .and_then(|_| stream.set_nodelay(true))
+                .and_then(|_| stream.try_clone());
+            if let Ok(reader) = reader {
+                listen(reader, connection, self.sender.clone());
+                self.greeting.push((connection, stream));
+            }
+        }
+
+        let mut events = Vec::new();
+        let received: Vec<_> = self.incoming.get_mut().unwrap().try_iter().collect();
+        for (connection, message) in received {
+            match message {
+                Some(ClientMessage::Hello { name, token }) => self.greet(connection, name, token, &mut events),
+                Some(ClientMessage::Act(action)) => {
+                    if let Some(seat) = self.seat_of(connection) {
+                        events.push(HostEvent::Acted { seat, action });
+                    }
+                }
+                None => self.disconnect(connection, &mut events),
+            }
+        }
+        events
+    }
+
+    /// Sends `message` to every connected player.
+    pub fn broadcast(&mut self, message: &HostMessage) {
+        for remote in self.remotes.iter_mut() {
+            if let Some((_, stream)) = remote.connection.as_mut() {
+                // A failed write means the connection is going, and
+                // its reader will say so.
+                let _ = send(stream, message);
+            }
+        }
+    }
+
+    fn seat_of(&self, connection: usize) -> Option<usize> {
+        self.remotes
+            .iter()
+            .position(|remote| remote.connection.as_ref().is_some_and(|(id, _)| *id == connection))
+            .map(|index| index + 1)
+    }
+
+    fn greet(&mut self, connection: usize, name: String, token: Option<u64>, events: &mut Vec<HostEvent>) {
+        let Some(index) = self.greeting.iter().position(|(id, _)| *id == connection) else {
+            return;
+        };
+        let (_, mut stream) = self.greeting.swap_remove(index);
+        let returning = token.and_then(|token| {
+            self.remotes.iter().position(|remote| remote.token == token && remote.connection.is_none())
+        });
+        let seat = match returning {
+            Some(index) => {
+                self.remotes[index].connection = Some((connection, stream));
+                events.push(HostEvent::Rejoined(index + 1));
+                index + 1
+            }
+            None if !self.started && self.remotes.len() + 1 < MAX_PLAYERS => {
+                let token = self.rng.next();
+                self.remotes.push(Remote { name, token, connection: Some((connection, stream)) });
+                events.push(HostEvent::Joined(self.remotes.len()));
+                self.remotes.len()
+            }
+            None => {
+                let reason = if self.started { "The game has already started" } else { "The table is full" };
+                let _ = send(&mut stream, &HostMessage::Refused(reason.to_string()));
+                let _ = stream.shutdown(Shutdown::Both);
+                return;
+            }
+        };
+        self.welcome(seat);
+        if !self.started {
+            self.broadcast(&HostMessage::Lobby { players: self.players() });
+        }
+    }
+
+    fn welcome(&mut self, seat: usize) {
+        let remote = &mut self.remotes[seat - 1];
+        if let Some((_, stream)) = remote.connection.as_mut() {
+            let _ = send(stream, &HostMessage::Welcome { seat, token: remote.token });
+        }
+    }
+
+    fn disconnect(&mut self, connection: usize, events: &mut Vec<HostEvent>) {
+        self.greeting.retain(|(id, _)| *id != connection);
+        let Some(seat) = self.seat_of(connection) else {
+            return;
+        };
+        events.push(HostEvent::Left(seat));
+        if self.started {
+            self.remotes[seat - 1].connection = None;
+        } else {
+            self.remotes.remove(seat - 1);
+            for later in seat..=self.remotes.len() {
+                self.welcome(later);
+            }
+            self.broadcast(&HostMessage::Lobby { players: self.players() });
+        }
+    }
+}
+
+impl Drop for Host {
+    fn drop(&mut self) {
+        let streams = self.greeting.iter().map(|(_, stream)| stream);
+        let remotes = self.remotes.iter().filter_map(|remote| remote.connection.as_ref().map(|(_, stream)| stream));
+        for stream in streams.chain(remotes) {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// A connection to a host.
+pub struct Client {
+    stream: TcpStream,
+    incoming: Mutex<Receiver<(usize, Option<HostMessage>)>>,
+    connected: bool,
+}
+
+impl Client {
+    /// Joins the game hosted at `address`. Pass the token from an earlier
+    /// welcome to take back a seat.
+    pub fn connect(address: &str, name: &str, token: Option<u64>) -> io::Result<Self> {
+        let address = with_port(address)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address to connect to"))?;
+        let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        stream.set_nodelay(true)?;
+        let (sender, incoming) = channel();
+        listen(stream.try_clone()?, 0, sender);
+        send(&mut stream, &ClientMessage::Hello { name: name.to_string(), token })?;
+        Ok(Self { stream, incoming: Mutex::new(incoming), connected: true })
+    }
+
+    /// Everything the host has sent since the last poll.
+    pub fn poll(&mut self) -> Vec<HostMessage> {
+        let mut messages = Vec::new();
+        let received: Vec<_> = self.incoming.get_mut().unwrap().try_iter().collect();
+        for (_, message) in received {
+            match message {
+                Some(message) => messages.push(message),
+                None => self.connected = false,
+            }
+        }
+        messages
+    }
+
+    /// Asks the host to make a move.
+    pub fn act(&mut self, action: Action) -> io::Result<()> {
+        send(&mut self.stream, &ClientMessage::Act(action))
+    }
+
+    /// Has the host closed the connection?
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// Polls until `done` returns something, for up to five seconds.
+    fn wait_for<T>(mut done: impl FnMut() -> Option<T>) -> T {
+        let start = Instant::now();
+        loop {
+            if let Some(result) = done() {
+                return result;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn host() -> (Host, String) {
+        let host = Host::bind("127.0.0.1:0", "Host").unwrap();
+        let address = host.local_addr().unwrap().to_string();
+        (host, address)
+    }
+
+    /// Waits for the client's welcome, returning its seat and token.
+    fn welcome(host: &mut Host, client: &mut Client) -> (usize, u64) {
+        wait_for(|| {
+            host.poll();
+            client.poll().into_iter().find_map(|message| match message {
+                HostMessage::Welcome { seat, token } => Some((seat, token)),
+                _ => None,
+            })
+        })
+    }
+
+    #[test]
+    fn test_join_and_play() {
+        let (mut host, address) = host();
+        let mut ann = Client::connect(&address, "Ann", None).unwrap();
+        assert_eq!(welcome(&mut host, &mut ann).0, 1);
+        let mut bob = Client::connect(&address, "Bob", None).unwrap();
+        assert_eq!(welcome(&mut host, &mut bob).0, 2);
+        assert_eq!(host.players(), ["Host", "Ann", "Bob"]);
+        let lobby = HostMessage::Lobby { players: host.players() };
+        wait_for(|| ann.poll().contains(&lobby).then_some(()));
+
+        host.start();
+        let state = HostMessage::State { players: host.players(), game: PigGame::new(3), history: History::default() };
+        host.broadcast(&state);
+        wait_for(|| bob.poll().contains(&state).then_some(()));
+
+        bob.act(Action::Roll).unwrap();
+        let event = wait_for(|| host.poll().pop());
+        assert_eq!(event, HostEvent::Acted { seat: 2, action: Action::Roll });
+
+        // The lobby is closed to newcomers.
+        let mut late = Client::connect(&address, "Late", None).unwrap();
+        let refused = wait_for(|| {
+            host.poll();
+            late.poll().pop()
+        });
+        assert!(matches!(refused, HostMessage::Refused(_)));
+        assert_eq!(host.players().len(), 3);
+    }
+
+    #[test]
+    fn test_reconnect_to_the_same_seat() {
+        let (mut host, address) = host();
+        let mut ann = Client::connect(&address, "Ann", None).unwrap();
+        let (seat, token) = welcome(&mut host, &mut ann);
+        host.start();
+
+        drop(ann);
+        assert_eq!(wait_for(|| host.poll().pop()), HostEvent::Left(seat));
+        assert!(!host.is_connected(seat));
+
+        let mut ann = Client::connect(&address, "Ann", Some(token)).unwrap();
+        let rejoined = wait_for(|| host.poll().pop());
+        assert_eq!(rejoined, HostEvent::Rejoined(seat));
+        assert_eq!(welcome(&mut host, &mut ann), (seat, token));
+        assert!(host.is_connected(seat));
+    }
+
+    #[test]
+    fn test_leaving_the_lobby_frees_the_seat() {
+        let (mut host, address) = host();
+        let mut ann = Client::connect(&address, "Ann", None).unwrap();
+        welcome(&mut host, &mut ann);
+        let mut bob = Client::connect(&address, "Bob", None).unwrap();
+        welcome(&mut host, &mut bob);
+
+        drop(ann);
+        wait_for(|| host.poll().contains(&HostEvent::Left(1)).then_some(()));
+        assert_eq!(host.players(), ["Host", "Bob"]);
+        // Bob moves up to Ann's seat.
+        assert_eq!(welcome(&mut host, &mut bob).0, 1);
+    }
+
+    #[test]
+    fn test_clients_notice_the_host_leaving() {
+        let (host, address) = host();
+        let mut ann = Client::connect(&address, "Ann", None).unwrap();
+        drop(host);
+        wait_for(|| {
+            ann.poll();
+            (!ann.is_connected()).then_some(())
+        });
+    }
+}
@@ -0,0 +1,324 @@
+//! Network play: hosting and joining from the main menu, the lobby, and
+//! keeping everyone's table in step with the host, who runs the rules.
+use crate::{roll_die, Game, GameHistory, GamePhase, Seats, TableRules};
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use bevy_egui::{egui, EguiContexts};
+use my_library::RandomNumberGenerator;
+use pig::history::History;
+use pig::net::{Client, Host, HostEvent, HostMessage, DEFAULT_PORT};
+use pig::rules::{Action, PigGame};
+use std::io;
+
+/// How long a client waits between attempts to reconnect.
+const RETRY_SECONDS: f32 = 2.0;
+
+/// Whether this copy of the game is hosting, has joined a host, or is
+/// playing on its own.
+#[derive(Resource, Default)]
+pub enum Network {
+    #[default]
+    Offline,
+    Hosting(Host),
+    Joined(Connection),
+}
+
+impl Network {
+    pub fn is_offline(&self) -> bool {
+        matches!(self, Self::Offline)
+    }
+
+    /// Plays a human's move: locally when hosting or offline, otherwise
+    /// by asking the host.
+    pub fn act(&mut self, action: Action, game: &mut PigGame, history: &mut History, rng: &RandomNumberGenerator) {
+        match self {
+            Self::Joined(connection) => {
+                if let Err(e) = connection.client.act(action) {
+                    warn!("Couldn't send a move to the host: {e}");
+                }
+            }
+            // Only fails once the game is over, and then there's nothing
+            // to do.
+            _ => {
+                let sides = game.rules().faces();
+                let _ = history.play(game, action, || roll_die(rng, sides));
+            }
+        }
+    }
+
+    /// Is the player in `seat` connected? Clients only know whether they
+    /// can reach the host, in seat 0.
+    pub fn is_connected(&self, seat: usize) -> bool {
+        match self {
+            Self::Offline => true,
+            Self::Hosting(host) => host.is_connected(seat),
+            Self::Joined(connection) => seat != 0 || connection.client.is_connected(),
+        }
+    }
+}
+
+/// A client's link to the host.
+pub struct Connection {
+    client: Client,
+    address: String,
+    name: String,
+    /// Our seat and the token to reconnect to it, once welcomed.
+    seat: Option<usize>,
+    token: Option<u64>,
+    /// Everyone in the lobby.
+    players: Vec<String>,
+    /// The latest game from the host, waiting to be shown.
+    update: Option<(PigGame, History)>,
+    retry: Timer,
+    reconnecting: Option<Task<io::Result<Client>>>,
+}
+
+impl Connection {
+    pub fn new(client: Client, address: &str, name: &str) -> Self {
+        Self {
+            client,
+            address: address.to_string(),
+            name: name.to_string(),
+            seat: None,
+            token: None,
+            players: Vec::new(),
+            update: None,
+            retry: Timer::from_seconds(RETRY_SECONDS, TimerMode::Repeating),
+            reconnecting: None,
+        }
+    }
+}
+
+/// Connecting can take a few seconds when the host doesn't answer, so it
+/// happens in the background.
+fn connect(address: &str, name: &str, token: Option<u64>) -> Task<io::Result<Client>> {
+    let (address, name) = (address.to_string(), name.to_string());
+    AsyncComputeTaskPool::get().spawn(async move { Client::connect(&address, &name, token) })
+}
+
+/// What's been typed into the network window on the main menu.
+#[derive(Resource)]
+pub struct NetworkForm {
+    name: String,
+    address: String,
+    error: Option<String>,
+    joining: Option<Task<io::Result<Client>>>,
+}
+
+impl Default for NetworkForm {
+    fn default() -> Self {
+        Self { name: "Player".to_string(), address: "127.0.0.1".to_string(), error: None, joining: None }
+    }
+}
+
+/// The seats chosen on the main menu, put aside during a network game.
+#[derive(Resource, Default)]
+pub struct OfflineSeats(Option<Seats>);
+
+/// Seats a network game's `players`, with this machine's player in
+/// `mine`, and puts the offline seats aside.
+pub fn seat_players(seats: &mut Seats, offline: &mut OfflineSeats, players: &[String], mine: usize) {
+    let previous = std::mem::replace(seats, Seats::networked(players, mine));
+    offline.0.get_or_insert(previous);
+}
+
+/// Back on the main menu, any network game is over and the offline seats
+/// come back. A join left behind on the menu is abandoned.
+pub fn go_offline(
+    mut network: ResMut<Network>,
+    mut seats: ResMut<Seats>,
+    mut offline: ResMut<OfflineSeats>,
+    mut form: ResMut<NetworkForm>,
+) {
+    *network = Network::Offline;
+    form.joining = None;
+    if let Some(previous) = offline.0.take() {
+        *seats = previous;
+    }
+}
+
+pub fn network_menu(
+    mut form: ResMut<NetworkForm>,
+    mut network: ResMut<Network>,
+    mut state: ResMut<NextState<GamePhase>>,
+    mut egui_context: EguiContexts,
+) {
+    if let Some(task) = &mut form.joining {
+        if let Some(joined) = block_on(future::poll_once(task)) {
+            form.joining = None;
+            match joined {
+                Ok(client) => {
+                    *network = Network::Joined(Connection::new(client, &form.address, &form.name));
+                    state.set(GamePhase::Lobby);
+                }
+                Err(e) => form.error = Some(format!("Couldn't join {}: {e}", form.address)),
+            }
+        }
+    }
+    let Some(ctx) = egui_context.try_ctx_mut() else {
+        return;
+    };
+    egui::Window::new("Network Play").show(ctx, |ui| {
+        // The form stays as it was until the join succeeds or fails.
+        let joining = form.joining.is_some();
+        ui.add_enabled_ui(!joining, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Your name:");
+                ui.text_edit_singleline(&mut form.name);
+            });
+            if ui.button(format!("Host a Game (port {DEFAULT_PORT})")).clicked() {
+                match Host::bind(("0.0.0.0", DEFAULT_PORT), &form.name) {
+                    Ok(host) => {
+                        *network = Network::Hosting(host);
+                        state.set(GamePhase::Lobby);
+                    }
+                    Err(e) => form.error = Some(format!("Couldn't host: {e}")),
+                }
+            }
+            ui.horizontal(|ui| {
+                ui.label("Host address:");
+                ui.text_edit_singleline(&mut form.address);
+            });
+        });
+        if joining {
+            ui.label(format!("Connecting to {}...", form.address));
+        } else if ui.button("Join a Game").clicked() {
+            form.joining = Some(connect(&form.address, &form.name, None));
+        }
+        if let Some(error) = &form.error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+    });
+}
+
+pub fn lobby(
+    mut network: ResMut<Network>,
+    mut seats: ResMut<Seats>,
+    mut offline: ResMut<OfflineSeats>,
+    rules: Res<TableRules>,
+    mut state: ResMut<NextState<GamePhase>>,
+    mut egui_context: EguiContexts,
+) {
+    let Some(ctx) = egui_context.try_ctx_mut() else {
+        return;
+    };
+    egui::Window::new("Lobby").show(ctx, |ui| {
+        let players = match &*network {
+            Network::Hosting(host) => host.players(),
+            Network::Joined(connection) => connection.players.clone(),
+            Network::Offline => Vec::new(),
+        };
+        for (seat, name) in players.iter().enumerate() {
+            ui.label(format!("{}. {name}", seat + 1));
+        }
+        match &mut *network {
+            Network::Hosting(host) => {
+                ui.label(format!("Playing {} to {}", rules.0.variant.name(), rules.0.target));
+                if players.len() < 2 {
+                    ui.label("Waiting for players to join...");
+                } else if ui.button("Start Game").clicked() {
+                    host.start();
+                    seat_players(&mut seats, &mut offline, &players, 0);
+                    state.set(GamePhase::Start);
+                }
+            }
+            _ => {
+                ui.label("Waiting for the host to start the game...");
+            }
+        }
+        if ui.button("Leave").clicked() {
+            state.set(GamePhase::MainMenu);
+        }
+    });
+}
+
+/// Hosts apply remote players' moves and send everyone the game whenever
+/// it changes. Clients follow what the host sends, and try to reconnect
+/// if they lose the host.
+#[allow(clippy::too_many_arguments)]
+pub fn sync(
+    mut network: ResMut<Network>,
+    mut form: ResMut<NetworkForm>,
+    mut seats: ResMut<Seats>,
+    mut offline: ResMut<OfflineSeats>,
+    game: Option<ResMut<Game>>,
+    history: Option<ResMut<GameHistory>>,
+    rng: Res<RandomNumberGenerator>,
+    state: Res<State<GamePhase>>,
+    mut next_state: ResMut<NextState<GamePhase>>,
+    time: Res<Time>,
+) {
+    match &mut *network {
+        Network::Offline => {}
+        Network::Hosting(host) => {
+            let (Some(mut game), Some(mut history)) = (game, history) else {
+                host.poll();
+                return;
+            };
+            let mut resend = false;
+            for event in host.poll() {
+                match event {
+                    HostEvent::Acted { seat, action } if seat == game.0.current() => {
+                        let sides = game.0.rules().faces();
+                        let _ = history.0.play(&mut game.0, action, || roll_die(&rng, sides));
+                    }
+                    HostEvent::Rejoined(_) => resend = true,
+                    _ => {}
+                }
+            }
+            if game.is_changed() || resend {
+                host.broadcast(&HostMessage::State {
+                    players: host.players(),
+                    game: game.0.clone(),
+                    history: history.0.clone(),
+                });
+            }
+        }
+        Network::Joined(connection) => {
+            for message in connection.client.poll() {
+                match message {
+                    HostMessage::Welcome { seat, token } => {
+                        connection.seat = Some(seat);
+                        connection.token = Some(token);
+                    }
+                    HostMessage::Lobby { players } => connection.players = players,
+                    HostMessage::State { players, game, history } => {
+                        if *state.get() == GamePhase::Lobby {
+                            seat_players(&mut seats, &mut offline, &players, connection.seat.unwrap_or(0));
+                            next_state.set(GamePhase::Start);
+                        }
+                        connection.update = Some((game, history));
+                    }
+                    HostMessage::Refused(reason) => {
+                        form.error = Some(reason);
+                        next_state.set(GamePhase::MainMenu);
+                    }
+                }
+            }
+            let playing = game.as_ref().is_some_and(|game| !game.0.is_over());
+            if let (Some(mut game), Some(mut history)) = (game, history) {
+                if let Some((update, log)) = connection.update.take() {
+                    game.0 = update;
+                    history.0 = log;
+                }
+            }
+
+            if !connection.client.is_connected() {
+                if *state.get() == GamePhase::Lobby {
+                    form.error = Some("Lost the connection to the host".to_string());
+                    next_state.set(GamePhase::MainMenu);
+                } else if let Some(task) = &mut connection.reconnecting {
+                    if let Some(reconnected) = block_on(future::poll_once(task)) {
+                        connection.reconnecting = None;
+                        match reconnected {
+                            Ok(client) => connection.client = client,
+                            Err(e) => info!("Still can't reach the host: {e}"),
+                        }
+                    }
+                } else if playing && connection.retry.tick(time.delta()).just_finished() {
+                    connection.reconnecting = Some(connect(&connection.address, &connection.name, connection.token));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,523 @@
+//! The rules of Pig, with no engine attached.
+//!
+//! Players take turns rolling a die. Every roll adds to their turn total,
+//! unless it is a 1: then the turn total is lost and the next player goes.
+//! Instead of rolling, a player may hold, banking the turn total. The
+//! first player to bank [`TARGET`] points wins.
+//!
+//! [`Variant`] covers the common variations on the theme: more dice, coins
+//! instead of a die, and a single throw per turn.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The score that wins the game.
+pub const TARGET: u32 = 100;
+/// How many faces the die has.
+pub const DIE_SIDES: u32 = 6;
+/// The biggest die on offer.
+pub const MAX_DIE_SIDES: u32 = 20;
+/// The most dice a player may throw at once in Hog.
+pub const HOG_MAX_DICE: u32 = 10;
+/// Double ones in Big Pig.
+const BIG_PIG_SNAKE_EYES: u32 = 25;
+
+/// Which game of Pig is being played.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Variant {
+    /// One die; a 1 loses the turn total.
+    #[default]
+    Classic,
+    /// Two dice. A single 1 loses the turn total, and double ones lose
+    /// the player's whole score.
+    TwoDice,
+    /// Two dice. A single 1 loses the turn total, doubles count double
+    /// and double ones are worth 25.
+    BigPig,
+    /// One throw of as many dice as the player likes. Any 1 scores
+    /// nothing, otherwise the dice are banked straight away.
+    Hog,
+    /// A coin instead of a die: heads scores 1 and tails loses the turn
+    /// total.
+    Piglet,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 5] = [Self::Classic, Self::TwoDice, Self::BigPig, Self::Hog, Self::Piglet];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Classic => "Pig",
+            Self::TwoDice => "Two-Dice Pig",
+            Self::BigPig => "Big Pig",
+            Self::Hog => "Hog",
+            Self::Piglet => "Piglet",
+        }
+    }
+
+    /// How many dice a roll uses, or `None` if the player chooses.
+    pub fn dice(self) -> Option<u32> {
+        match self {
+            Self::Classic | Self::Piglet => Some(1),
+            Self::TwoDice | Self::BigPig => Some(2),
+            Self::Hog => None,
+        }
+    }
+}
+
+/// The variant being played, and what it's played to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Rules {
+    pub variant: Variant,
+    /// The score that wins.
+    pub target: u32,
+    /// How many faces each die has. Piglet always flips a coin.
+    pub die_sides: u32,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self::new(Variant::Classic)
+    }
+}
+
+impl Rules {
+    /// `variant` with its usual target and a six-sided die.
+    pub fn new(variant: Variant) -> Self {
+        let target = if variant == Variant::Piglet { 10 } else { TARGET };
+        Self { variant, target, die_sides: DIE_SIDES }
+    }
+
+    /// The number of faces on the dice, counting a coin as a die with two.
+    pub fn faces(&self) -> u32 {
+        if self.variant == Variant::Piglet {
+            2
+        } else {
+            self.die_sides
+        }
+    }
+
+    /// Why these rules can't be played, if they can't.
+    pub fn check(&self) -> Result<(), String> {
+        if self.target == 0 {
+            return Err("The target must be at least 1".to_string());
+        }
+        if !(2..=MAX_DIE_SIDES).contains(&self.die_sides) {
+            return Err(format!("Dice must have between 2 and {MAX_DIE_SIDES} sides"));
+        }
+        Ok(())
+    }
+
+    /// What a roll of `faces` is worth, before any 1s are counted.
+    fn points(&self, faces: &[u32]) -> u32 {
+        let sum = faces.iter().sum::<u32>();
+        match self.variant {
+            Variant::Piglet => 1,
+            Variant::BigPig if faces[0] == faces[1] => 2 * sum,
+            _ => sum,
+        }
+    }
+}
+
+/// What a player can do on their turn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Roll,
+    Hold,
+    /// Hog's one throw of the turn, with this many dice.
+    Throw(u32),
+}
+
+/// What an action led to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// The roll was worth this many points, and they were added to the
+    /// turn total.
+    Rolled(u32),
+    /// The roll had a 1 in it. The turn total is lost and the turn passes
+    /// on.
+    Bust,
+    /// Double ones in Two-Dice Pig: the player's score goes back to 0 and
+    /// the turn passes on.
+    WipedOut,
+    /// The player banked this many points and the turn passes on.
+    Held(u32),
+    /// The player banked enough to reach the target.
+    Won { player: usize, score: u32 },
+}
+
+/// Why an action was refused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IllegalMove {
+    /// Someone has already won.
+    GameOver,
+    /// The die can't show this value.
+    NoSuchFace(u32),
+    /// The variant doesn't roll this many dice at once.
+    WrongNumberOfDice(usize),
+    /// The variant has no such move.
+    NotInVariant(Variant),
+}
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GameOver => write!(f, "the game is over"),
+            Self::NoSuchFace(value) => write!(f, "a die can't roll {value}"),
+            Self::WrongNumberOfDice(dice) => write!(f, "can't roll {dice} dice at once"),
+            Self::NotInVariant(variant) => write!(f, "{} has no such move", variant.name()),
+        }
+    }
+}
+
+impl std::error::Error for IllegalMove {}
+
+/// A game of Pig in progress.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PigGame {
+    rules: Rules,
+    scores: Vec<u32>,
+    current: usize,
+    /// The dice rolled so far this turn.
+    rolls: Vec<u32>,
+    turn_total: u32,
+    /// The dice of the latest roll, even if it ended the turn.
+    last_roll: Vec<u32>,
+    winner: Option<usize>,
+}
+
+impl PigGame {
+    /// A new game of classic Pig for `players` players. Player 0 goes
+    /// first.
+    pub fn new(players: usize) -> Self {
+        Self::with_rules(players, Rules::default())
+    }
+
+    /// A new game for `players` players, played by `rules`.
+    pub fn with_rules(players: usize, rules: Rules) -> Self {
+        assert!(players > 0, "Pig needs at least one player");
+        Self {
+            rules,
+            scores: vec![0; players],
+            current: 0,
+            rolls: Vec::new(),
+            turn_total: 0,
+            last_roll: Vec::new(),
+            winner: None,
+        }
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn players(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Every player's banked score.
+    pub fn scores(&self) -> &[u32] {
+        &self.scores
+    }
+
+    pub fn score(&self, player: usize) -> u32 {
+        self.scores[player]
+    }
+
+    /// Whose turn it is.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// The dice rolled so far this turn.
+    pub fn rolls(&self) -> &[u32] {
+        &self.rolls
+    }
+
+    /// The dice of the latest roll, including one that ended a turn.
+    pub fn last_roll(&self) -> &[u32] {
+        &self.last_roll
+    }
+
+    /// The points the current player would bank by holding.
+    pub fn turn_total(&self) -> u32 {
+        self.turn_total
+    }
+
+    pub fn winner(&self) -> Option<usize> {
+        self.winner
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.winner.is_some()
+    }
+
+    /// What the current player may do. Nothing, once the game is over.
+    pub fn legal_actions(&self) -> Vec<Action> {
+        if self.is_over() {
+            Vec::new()
+        } else if self.rules.variant == Variant::Hog {
+            (1..=HOG_MAX_DICE).map(Action::Throw).collect()
+        } else {
+            vec![Action::Roll, Action::Hold]
+        }
+    }
+
+    /// Plays `action` for the current player. `die` is called once for
+    /// every die rolled.
+    pub fn play(&mut self, action: Action, mut die: impl FnMut() -> u32) -> Result<Outcome, IllegalMove> {
+        match action {
+            Action::Roll => {
+                let dice = self.rules.variant.dice().ok_or(IllegalMove::NotInVariant(self.rules.variant))?;
+                self.roll_dice(&(0..dice).map(|_| die()).collect::<Vec<_>>())
+            }
+            Action::Hold => self.hold(),
+            Action::Throw(dice) => {
+                if self.rules.variant != Variant::Hog {
+                    return Err(IllegalMove::NotInVariant(self.rules.variant));
+                }
+                if !(1..=HOG_MAX_DICE).contains(&dice) {
+                    return Err(IllegalMove::WrongNumberOfDice(dice as usize));
+                }
+                self.roll_dice(&(0..dice).map(|_| die()).collect::<Vec<_>>())
+            }
+        }
+    }
+
+    /// The current player rolled a single die showing `value`.
+    pub fn roll(&mut self, value: u32) -> Result<Outcome, IllegalMove> {
+        self.roll_dice(&[value])
+    }
+
+    /// The current player rolled `faces`: as many dice as the variant
+    /// rolls at once, or in Hog, their throw for the turn.
+    pub fn roll_dice(&mut self, faces: &[u32]) -> Result<Outcome, IllegalMove> {
+        if self.is_over() {
+            return Err(IllegalMove::GameOver);
+        }
+        let dice = faces.len();
+        match self.rules.variant.dice() {
+            Some(wanted) if dice != wanted as usize => return Err(IllegalMove::WrongNumberOfDice(dice)),
+            None if !(1..=HOG_MAX_DICE as usize).contains(&dice) => {
+                return Err(IllegalMove::WrongNumberOfDice(dice))
+            }
+            _ => {}
+        }
+        if let Some(&value) = faces.iter().find(|&&value| !(1..=self.rules.faces()).contains(&value)) {
+            return Err(IllegalMove::NoSuchFace(value));
+        }
+
+        self.last_roll = faces.to_vec();
+        let ones = faces.iter().filter(|&&value| value == 1).count();
+        let points = match (self.rules.variant, ones) {
+            (Variant::TwoDice, 2) => {
+                self.scores[self.current] = 0;
+                self.end_turn();
+                return Ok(Outcome::WipedOut);
+            }
+            (Variant::BigPig, 2) => BIG_PIG_SNAKE_EYES,
+            (_, 0) => self.rules.points(faces),
+            _ => {
+                self.end_turn();
+                return Ok(Outcome::Bust);
+            }
+        };
+        self.rolls.extend_from_slice(faces);
+        self.turn_total += points;
+        if self.rules.variant == Variant::Hog {
+            // There's only the one throw, so it's banked straight away.
+            return Ok(self.bank());
+        }
+        Ok(Outcome::Rolled(points))
+    }
+
+    /// The current player banks their turn total. Hog's throws bank
+    /// themselves, so there's no holding.
+    pub fn hold(&mut self) -> Result<Outcome, IllegalMove> {
+        if self.is_over() {
+            return Err(IllegalMove::GameOver);
+        }
+        if self.rules.variant == Variant::Hog {
+            return Err(IllegalMove::NotInVariant(Variant::Hog));
+        }
+        Ok(self.bank())
+    }
+
+    fn bank(&mut self) -> Outcome {
+        let points = self.turn_total;
+        let player = self.current;
+        self.scores[player] += points;
+        if self.scores[player] >= self.rules.target {
+            self.rolls.clear();
+            self.turn_total = 0;
+            self.winner = Some(player);
+            return Outcome::Won { player, score: self.scores[player] };
+        }
+        self.end_turn();
+        Outcome::Held(points)
+    }
+
+    fn end_turn(&mut self) {
+        self.rolls.clear();
+        self.turn_total = 0;
+        self.current = (self.current + 1) % self.players();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_busting_loses_the_turn_total() {
+        let mut game = PigGame::new(2);
+        assert_eq!(game.roll(5), Ok(Outcome::Rolled(5)));
+        assert_eq!(game.roll(4), Ok(Outcome::Rolled(4)));
+        assert_eq!(game.turn_total(), 9);
+        assert_eq!(game.roll(1), Ok(Outcome::Bust));
+        assert_eq!(game.scores(), [0, 0]);
+        assert_eq!(game.current(), 1);
+        assert!(game.rolls().is_empty());
+    }
+
+    #[test]
+    fn test_holding_banks_the_turn_total() {
+        let mut game = PigGame::new(2);
+        game.roll(6).unwrap();
+        assert_eq!(game.hold(), Ok(Outcome::Held(6)));
+        assert_eq!(game.scores(), [6, 0]);
+        assert_eq!(game.current(), 1);
+        assert_eq!(game.hold(), Ok(Outcome::Held(0)));
+        assert_eq!(game.current(), 0);
+    }
+
+    #[test]
+    fn test_holding_at_the_target_wins() {
+        let mut game = PigGame::new(2);
+        for _ in 0..17 {
+            game.roll(6).unwrap();
+        }
+        // Rolling past the target doesn't win; only holding does.
+        assert!(!game.is_over());
+        assert_eq!(game.hold(), Ok(Outcome::Won { player: 0, score: 102 }));
+        assert_eq!(game.winner(), Some(0));
+        assert!(game.legal_actions().is_empty());
+        assert_eq!(game.roll(3), Err(IllegalMove::GameOver));
+        assert_eq!(game.hold(), Err(IllegalMove::GameOver));
+    }
+
+    #[test]
+    fn test_dice_only_have_six_faces() {
+        let mut game = PigGame::new(2);
+        assert_eq!(game.roll(0), Err(IllegalMove::NoSuchFace(0)));
+        assert_eq!(game.roll(7), Err(IllegalMove::NoSuchFace(7)));
+        assert_eq!(game, PigGame::new(2));
+    }
+
+    #[test]
+    fn test_two_dice_pig() {
+        let mut game = PigGame::with_rules(2, Rules::new(Variant::TwoDice));
+        assert_eq!(game.roll(6), Err(IllegalMove::WrongNumberOfDice(1)));
+        assert_eq!(game.roll_dice(&[6, 5]), Ok(Outcome::Rolled(11)));
+        game.hold().unwrap();
+        assert_eq!(game.roll_dice(&[3, 1]), Ok(Outcome::Bust));
+        assert_eq!(game.last_roll(), [3, 1]);
+        game.roll_dice(&[2, 2]).unwrap();
+        assert_eq!(game.roll_dice(&[1, 1]), Ok(Outcome::WipedOut));
+        assert_eq!(game.scores(), [0, 0]);
+        assert_eq!(game.current(), 1);
+    }
+
+    #[test]
+    fn test_big_pig_doubles() {
+        let mut game = PigGame::with_rules(2, Rules::new(Variant::BigPig));
+        assert_eq!(game.roll_dice(&[4, 3]), Ok(Outcome::Rolled(7)));
+        assert_eq!(game.roll_dice(&[4, 4]), Ok(Outcome::Rolled(16)));
+        assert_eq!(game.roll_dice(&[1, 1]), Ok(Outcome::Rolled(25)));
+        assert_eq!(game.hold(), Ok(Outcome::Held(48)));
+        assert_eq!(game.roll_dice(&[1, 6]), Ok(Outcome::Bust));
+    }
+
+    #[test]
+    fn test_hog_banks_one_throw() {
+        let rules = Rules { target: 20, ..Rules::new(Variant::Hog) };
+        let mut game = PigGame::with_rules(2, rules);
+        assert_eq!(game.legal_actions().len(), HOG_MAX_DICE as usize);
+        assert_eq!(game.hold(), Err(IllegalMove::NotInVariant(Variant::Hog)));
+        assert_eq!(game.play(Action::Roll, || 3), Err(IllegalMove::NotInVariant(Variant::Hog)));
+        assert_eq!(game.play(Action::Throw(3), || 4), Ok(Outcome::Held(12)));
+        assert_eq!(game.roll_dice(&[6, 6, 1]), Ok(Outcome::Bust));
+        assert_eq!(game.current(), 0);
+        assert_eq!(game.roll_dice(&[5, 5]), Ok(Outcome::Won { player: 0, score: 22 }));
+    }
+
+    #[test]
+    fn test_piglet_flips_coins() {
+        let rules = Rules::new(Variant::Piglet);
+        assert_eq!((rules.target, rules.faces()), (10, 2));
+        let mut game = PigGame::with_rules(2, rules);
+        assert_eq!(game.roll(3), Err(IllegalMove::NoSuchFace(3)));
+        assert_eq!(game.roll(2), Ok(Outcome::Rolled(1)));
+        assert_eq!(game.roll(2), Ok(Outcome::Rolled(1)));
+        assert_eq!(game.hold(), Ok(Outcome::Held(2)));
+    }
+
+    #[test]
+    fn test_custom_dice_and_targets() {
+        let rules = Rules { target: 15, die_sides: 12, ..Rules::default() };
+        assert_eq!(rules.check(), Ok(()));
+        let mut game = PigGame::with_rules(2, rules);
+        assert_eq!(game.roll(12), Ok(Outcome::Rolled(12)));
+        assert_eq!(game.roll(13), Err(IllegalMove::NoSuchFace(13)));
+        game.roll(3).unwrap();
+        assert_eq!(game.hold(), Ok(Outcome::Won { player: 0, score: 15 }));
+        assert!(Rules { die_sides: 1, ..rules }.check().is_err());
+        assert!(Rules { target: 0, ..rules }.check().is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_any_game_follows_the_rules(
+            players in 1..=4usize,
+            moves in prop::collection::vec((any::<bool>(), 1..=DIE_SIDES), 0..600),
+        ) {
+            let mut game = PigGame::new(players);
+            for (hold, value) in moves {
+                let before = game.clone();
+                let action = if hold { Action::Hold } else { Action::Roll };
+                let Ok(outcome) = game.play(action, || value) else {
+                    prop_assert!(before.is_over());
+                    prop_assert_eq!(&game, &before);
+                    continue;
+                };
+                let player = before.current();
+                prop_assert_eq!(game.turn_total(), game.rolls().iter().sum::<u32>());
+                prop_assert!(!game.rolls().contains(&1));
+                for other in (0..players).filter(|&other| other != player) {
+                    prop_assert_eq!(game.score(other), before.score(other));
+                }
+                match outcome {
+                    Outcome::Rolled(rolled) => {
+                        prop_assert_eq!(game.current(), player);
+                        prop_assert_eq!(game.turn_total(), before.turn_total() + rolled);
+                        prop_assert_eq!(game.score(player), before.score(player));
+                    }
+                    Outcome::WipedOut => prop_assert!(false, "one die can't roll double ones"),
+                    Outcome::Bust | Outcome::Held(_) => {
+                        let banked = if let Outcome::Held(points) = outcome { points } else { 0 };
+                        prop_assert_eq!(game.score(player), before.score(player) + banked);
+                        prop_assert!(game.score(player) < TARGET);
+                        prop_assert_eq!(game.current(), (player + 1) % players);
+                        prop_assert_eq!(game.turn_total(), 0);
+                    }
+                    Outcome::Won { player: winner, score } => {
+                        prop_assert_eq!(winner, player);
+                        prop_assert_eq!(score, before.score(player) + before.turn_total());
+                        prop_assert!(score >= TARGET);
+                        prop_assert_eq!(game.winner(), Some(player));
+                    }
+                }
+            }
+        }
+    }
+}
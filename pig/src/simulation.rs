@@ -0,0 +1,256 @@
+//! Pig without Bevy: whole games between strategies, fast enough to play
+//! millions of them.
+use crate::rules::{Outcome, PigGame, Rules, Variant};
+use crate::strategy::Strategy;
+use my_library::RandomNumberGenerator;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A game that lasts this many turns is abandoned as a draw, in case
+/// neither strategy ever holds.
+pub const MAX_TURNS: u32 = 10_000;
+/// Games are handed to threads in blocks this big. It's even, so every
+/// block starts with the same player going first.
+const BLOCK: u32 = 1000;
+/// For 95% confidence intervals.
+const Z: f64 = 1.96;
+
+/// How one game went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GameRecord {
+    /// The seat that won, or `None` if the game was abandoned.
+    pub winner: Option<usize>,
+    pub turns: u32,
+    pub rolls: u32,
+}
+
+/// Plays one game by `rules`. `seats[i]` plays for player `i`, and player
+/// 0 goes first.
+pub fn play_game(rules: Rules, seats: &[&dyn Strategy], rng: &RandomNumberGenerator) -> GameRecord {
+    let mut game = PigGame::with_rules(seats.len(), rules);
+    let mut record = GameRecord { winner: None, turns: 1, rolls: 0 };
+    while record.turns <= MAX_TURNS {
+        let action = seats[game.current()].decide(&game);
+        match game.play(action, || rng.range(1..=rules.faces())) {
+            Ok(Outcome::Rolled(_)) => record.rolls += 1,
+            Ok(Outcome::Bust | Outcome::WipedOut) => {
+                record.rolls += 1;
+                record.turns += 1;
+            }
+            // Hog's throws bank themselves.
+            Ok(Outcome::Held(_)) if rules.variant == Variant::Hog => {
+                record.rolls += 1;
+                record.turns += 1;
+            }
+            Ok(Outcome::Held(_)) => record.turns += 1,
+            Ok(Outcome::Won { player, .. }) => {
+                if rules.variant == Variant::Hog {
+                    record.rolls += 1;
+                }
+                record.winner = Some(player);
+                break;
+            }
+            Err(_) => break,
+        }
+    }
+    record
+}
+
+/// A strategy with a name to report it by.
+#[derive(Clone)]
+pub struct Entrant {
+    pub name: String,
+    pub strategy: Arc<dyn Strategy>,
+}
+
+/// The results of every game between two entrants. Each goes first in
+/// half of the games.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pairing {
+    /// Indices into the tournament's entrants.
+    pub entrants: [usize; 2],
+    pub games: u32,
+    pub wins: [u32; 2],
+    pub draws: u32,
+    pub turns: u64,
+    pub rolls: u64,
+}
+
+impl Pairing {
+    fn add(&mut self, other: &Pairing) {
+        self.games += other.games;
+        self.wins[0] += other.wins[0];
+        self.wins[1] += other.wins[1];
+        self.draws += other.draws;
+        self.turns += other.turns;
+        self.rolls += other.rolls;
+    }
+
+    /// The share of games that `side` (0 or 1) won.
+    pub fn win_rate(&self, side: usize) -> f64 {
+        self.wins[side] as f64 / self.games.max(1) as f64
+    }
+
+    /// A 95% confidence interval for `side`'s win rate.
+    pub fn confidence_interval(&self, side: usize) -> (f64, f64) {
+        wilson_interval(self.wins[side], self.games)
+    }
+
+    pub fn mean_turns(&self) -> f64 {
+        self.turns as f64 / self.games.max(1) as f64
+    }
+
+    pub fn mean_rolls(&self) -> f64 {
+        self.rolls as f64 / self.games.max(1) as f64
+    }
+}
+
+/// The Wilson score interval for `wins` out of `games`, at 95% confidence.
+/// Unlike the textbook interval, it stays within 0 to 1 and behaves near
+/// the ends.
+pub fn wilson_interval(wins: u32, games: u32) -> (f64, f64) {
+    if games == 0 {
+        return (0.0, 1.0);
+    }
+    let n = games as f64;
+    let p = wins as f64 / n;
+    let z2 = Z * Z;
+    let middle = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let spread = Z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    ((middle - spread).max(0.0), (middle + spread).min(1.0))
+}
+
+/// Round-robin matches between strategies.
+#[derive(Clone, Debug)]
+pub struct Tournament {
+    /// Games played by each pair of entrants.
+    pub games: u32,
+    pub threads: usize,
+    pub rules: Rules,
+}
+
+impl Default for Tournament {
+    fn default() -> Self {
+        Self {
+            games: 10_000,
+            threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+            rules: Rules::default(),
+        }
+    }
+}
+
+impl Tournament {
+    /// Plays every entrant against every other. The results depend only
+    /// on `rng`, not on the number of threads.
+    pub fn run(&self, entrants: &[Entrant], rng: &RandomNumberGenerator) -> Vec<Pairing> {
+        let mut pairings = Vec::new();
+        for first in 0..entrants.len() {
+            for second in first + 1..entrants.len() {
+                pairings.push(Pairing { entrants: [first, second], ..Pairing::default() });
+            }
+        }
+
+        // (pairing, games, seed) for every block of games.
+        let mut blocks = Vec::new();
+        for pairing in 0..pairings.len() {
+            let mut left = self.games;
+            while left > 0 {
+                blocks.push((pairing, left.min(BLOCK), rng.next()));
+                left = left.saturating_sub(BLOCK);
+            }
+        }
+
+        let next_block = AtomicUsize::new(0);
+        let results = Mutex::new(pairings);
+        std::thread::scope(|scope| {
+            for _ in 0..self.threads.max(1) {
+                scope.spawn(|| {
+                    while let Some(&(pairing, games, seed)) = blocks.get(next_block.fetch_add(1, Ordering::Relaxed)) {
+                        let sides = results.lock().unwrap()[pairing].entrants;
+                        let played = play_block(self.rules, entrants, sides, games, seed);
+                        results.lock().unwrap()[pairing].add(&played);
+                    }
+                });
+            }
+        });
+        results.into_inner().unwrap()
+    }
+}
+
+/// Plays `games` games between two entrants, taking turns to go first.
+fn play_block(rules: Rules, entrants: &[Entrant], sides: [usize; 2], games: u32, seed: u64) -> Pairing {
+    let rng = RandomNumberGenerator::seeded(seed);
+    let strategies = sides.map(|side| entrants[side].strategy.as_ref());
+    let mut result = Pairing { entrants: sides, ..Pairing::default() };
+    for game in 0..games {
+        // On odd games the second entrant sits in seat 0 and goes first.
+        let swap = game % 2 == 1;
+        let seats = if swap { [strategies[1], strategies[0]] } else { strategies };
+        let record = play_game(rules, &seats, &rng);
+        result.games += 1;
+        result.turns += record.turns as u64;
+        result.rolls += record.rolls as u64;
+        match record.winner {
+            Some(seat) => result.wins[seat ^ swap as usize] += 1,
+            None => result.draws += 1,
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::{HoldAt, KeepPace};
+
+    fn entrants() -> Vec<Entrant> {
+        vec![
+            Entrant { name: "hold:20".to_string(), strategy: Arc::new(HoldAt(20)) },
+            Entrant { name: "hold:5".to_string(), strategy: Arc::new(HoldAt(5)) },
+            Entrant { name: "keep-pace".to_string(), strategy: Arc::new(KeepPace) },
+        ]
+    }
+
+    #[test]
+    fn test_results_dont_depend_on_threads() {
+        let run = |threads| {
+            let tournament = Tournament { games: 2500, threads, rules: Rules::default() };
+            tournament.run(&entrants(), &RandomNumberGenerator::seeded(7))
+        };
+        let results = run(1);
+        assert_eq!(results, run(4));
+        assert_eq!(results.len(), 3);
+        for pairing in &results {
+            assert_eq!(pairing.games, 2500);
+            assert_eq!(pairing.wins[0] + pairing.wins[1] + pairing.draws, 2500);
+        }
+        // Banking 5 at a time is far too timid.
+        let (low, _) = results[0].confidence_interval(0);
+        assert!(low > 0.6, "hold:20 won {:.1}%", results[0].win_rate(0) * 100.0);
+    }
+
+    #[test]
+    fn test_wilson_interval() {
+        let (low, high) = wilson_interval(50, 100);
+        assert!((low - 0.4038).abs() < 0.0001 && (high - 0.5962).abs() < 0.0001);
+        assert_eq!(wilson_interval(0, 10).0, 0.0);
+        assert_eq!(wilson_interval(10, 10).1, 1.0);
+    }
+
+    #[test]
+    fn test_timid_players_draw() {
+        let never = HoldAt(0);
+        let record = play_game(Rules::default(), &[&never, &never], &RandomNumberGenerator::seeded(1));
+        assert_eq!(record, GameRecord { winner: None, turns: MAX_TURNS + 1, rolls: 0 });
+    }
+
+    #[test]
+    fn test_every_variant_finishes() {
+        let rng = RandomNumberGenerator::seeded(3);
+        for variant in Variant::ALL {
+            let record = play_game(Rules::new(variant), &[&KeepPace, &HoldAt(20)], &rng);
+            assert!(record.winner.is_some(), "{} was abandoned", variant.name());
+            assert!(record.rolls >= record.turns - 1);
+        }
+    }
+}
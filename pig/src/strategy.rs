@@ -0,0 +1,311 @@
+//! How computer players decide between rolling and holding.
+//!
+//! [`HoldAt`] is the classic rule of thumb, [`KeepPace`] also watches the
+//! scoreboard, and [`Policy`] is the optimal strategy, solved by value
+//! iteration.
+//!
+//! They were all written for classic Pig, and only [`Policy`] insists on
+//! it. In Hog every strategy throws the same way: see [`hog_throw`].
+use crate::rules::{Action, PigGame, Rules, Variant, DIE_SIDES, HOG_MAX_DICE, TARGET};
+use my_library::persistence::DataDir;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// The highest target [`Policy`] will solve for. Solving takes memory and
+/// time in proportion to the target cubed.
+pub const MAX_POLICY_TARGET: u32 = 200;
+
+/// Chooses the current player's action.
+pub trait Strategy: Send + Sync {
+    fn decide(&self, game: &PigGame) -> Action;
+}
+
+/// The best score among the current player's opponents.
+fn leading_opponent(game: &PigGame) -> u32 {
+    (0..game.players())
+        .filter(|&player| player != game.current())
+        .map(|player| game.score(player))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Does holding now win the game?
+fn holding_wins(game: &PigGame) -> bool {
+    game.score(game.current()) + game.turn_total() >= game.rules().target
+}
+
+/// Hog's throw: the number of dice that scores the most on average, or
+/// fewer if fewer would probably be enough to win.
+pub fn hog_throw(game: &PigGame) -> Action {
+    let sides = game.rules().faces() as f64;
+    // A die that doesn't show a 1 shows (sides + 2) / 2 on average.
+    let average = (sides + 2.0) / 2.0;
+    let expected = |dice: u32| dice as f64 * average * ((sides - 1.0) / sides).powi(dice as i32);
+    let best = (1..=HOG_MAX_DICE).max_by(|&a, &b| expected(a).total_cmp(&expected(b))).unwrap_or(1);
+    let needed = game.rules().target.saturating_sub(game.score(game.current())) as f64;
+    Action::Throw(best.min((needed / average).ceil().max(1.0) as u32))
+}
+
+/// Rolls until the turn total reaches the threshold, or holding would win.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HoldAt(pub u32);
+
+impl Strategy for HoldAt {
+    fn decide(&self, game: &PigGame) -> Action {
+        if game.rules().variant == Variant::Hog {
+            hog_throw(game)
+        } else if game.turn_total() >= self.0 || holding_wins(game) {
+            Action::Hold
+        } else {
+            Action::Roll
+        }
+    }
+}
+
+/// "Keep pace and end race" (Neller and Presser): hold at 21, adjusted by
+/// how far behind or ahead it is. Once anyone is within 29 points of the
+/// target, keep rolling until holding wins. The numbers are for a target
+/// of 100, and scale with the target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeepPace;
+
+impl Strategy for KeepPace {
+    fn decide(&self, game: &PigGame) -> Action {
+        if game.rules().variant == Variant::Hog {
+            return hog_throw(game);
+        }
+        let scale = game.rules().target as f32 / TARGET as f32;
+        let mine = game.score(game.current()) as f32;
+        let theirs = leading_opponent(game) as f32;
+        let end_race = 71.0 * scale;
+        let hold_at = (21.0 * scale + (theirs - mine) / 8.0).round();
+        if holding_wins(game) {
+            Action::Hold
+        } else if mine >= end_race || theirs >= end_race || (game.turn_total() as f32) < hold_at {
+            Action::Roll
+        } else {
+            Action::Hold
+        }
+    }
+}
+
+/// The strategy that wins most often against a perfect opponent, for every
+/// combination of (my score, their score, turn total). With more than two
+/// players it plays against the leader. It's solved for classic Pig with a
+/// six-sided die, to one target; see [`Policy::solves`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Policy {
+    target: u32,
+    /// One bit per state, set if rolling is best.
+    rolls: Vec<u64>,
+}
+
+impl Policy {
+    /// Solves the game to `target` points. Scores of `target` or more have
+    /// already won, so there are `target³` states.
+    pub fn solve(target: u32) -> Self {
+        Self::solve_with_chances(target).0
+    }
+
+    /// Solves the game, also returning the chance of winning from every
+    /// state when both players play the policy.
+    fn solve_with_chances(target: u32) -> (Self, Vec<f64>) {
+        const TOLERANCE: f64 = 1e-9;
+        let t = target as usize;
+        let index = |mine: usize, theirs: usize, turn: usize| (mine * t + theirs) * t + turn;
+        let mut chances = vec![0.0; t * t * t];
+        let mut policy = Self { target, rolls: vec![0; (t * t * t).div_ceil(64)] };
+
+        // A state only depends on states with higher combined scores, or on
+        // the same scores with the players swapped. Solving the highest
+        // combined scores first leaves a small loop to iterate each time.
+        for sum in (0..=2 * (t - 1)).rev() {
+            let first = sum.saturating_sub(t - 1);
+            loop {
+                let mut change: f64 = 0.0;
+                for mine in first..=sum.min(t - 1) {
+                    let theirs = sum - mine;
+                    for turn in (0..t - mine).rev() {
+                        let hold = match mine + turn {
+                            banked if banked >= t => 1.0,
+                            banked => 1.0 - chances[index(theirs, banked, 0)],
+                        };
+                        let bust = 1.0 - chances[index(theirs, mine, 0)];
+                        let roll = (2..=DIE_SIDES as usize)
+                            .map(|face| match turn + face {
+                                total if mine + total >= t => 1.0,
+                                total => chances[index(mine, theirs, total)],
+                            })
+                            .sum::<f64>();
+                        let roll = (bust + roll) / DIE_SIDES as f64;
+
+                        let state = index(mine, theirs, turn);
+                        let best = roll.max(hold);
+                        change = change.max((best - chances[state]).abs());
+                        chances[state] = best;
+                        policy.set_roll(state, roll > hold);
+                    }
+                }
+                if change < TOLERANCE {
+                    break;
+                }
+            }
+        }
+        (policy, chances)
+    }
+
+    fn set_roll(&mut self, state: usize, roll: bool) {
+        let bit = 1 << (state % 64);
+        if roll {
+            self.rolls[state / 64] |= bit;
+        } else {
+            self.rolls[state / 64] &= !bit;
+        }
+    }
+
+    /// Should a player with `mine` points, against `theirs`, roll again
+    /// with `turn` points at stake?
+    pub fn should_roll(&self, mine: u32, theirs: u32, turn: u32) -> bool {
+        if mine + turn >= self.target {
+            return false;
+        }
+        let t = self.target as usize;
+        let state = (mine as usize * t + theirs.min(self.target - 1) as usize) * t + turn as usize;
+        self.rolls[state / 64] & (1 << (state % 64)) != 0
+    }
+
+    /// The score the policy was solved to.
+    pub fn target(&self) -> u32 {
+        self.target
+    }
+
+    /// Can a policy be solved for games played by `rules`?
+    pub fn solves(rules: &Rules) -> bool {
+        *rules == Rules { target: rules.target, ..Rules::default() } && rules.target <= MAX_POLICY_TARGET
+    }
+
+    /// Was the policy solved for games played by `rules`?
+    pub fn plays(&self, rules: &Rules) -> bool {
+        *rules == Rules { target: self.target, ..Rules::default() }
+    }
+
+    /// Where the policy for `target` is cached, in Pig's data folder.
+    fn file(target: u32) -> String {
+        format!("optimal_policy_{target}.ron")
+    }
+
+    /// Loads the cached policy for `target`, if there is one.
+    pub fn load(dir: &DataDir, target: u32) -> io::Result<Option<Self>> {
+        let t = target as usize;
+        Ok(dir.load::<Self>("pig", &Self::file(target))?
+            .filter(|policy| policy.target == target && policy.rolls.len() == (t * t * t).div_ceil(64)))
+    }
+
+    /// Caches the policy, so each target only has to be solved once.
+    pub fn save(&self, dir: &DataDir) -> io::Result<()> {
+        dir.save("pig", &Self::file(self.target), self)
+    }
+}
+
+impl Strategy for Policy {
+    /// Only call this for games the policy [`plays`](Policy::plays).
+    fn decide(&self, game: &PigGame) -> Action {
+        debug_assert!(self.plays(game.rules()), "a policy for {} can't play {:?}", self.target, game.rules());
+        let mine = game.score(game.current());
+        if self.should_roll(mine, leading_opponent(game), game.turn_total()) {
+            Action::Roll
+        } else {
+            Action::Hold
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A two player game where the current player has `mine` points, the
+    /// other `theirs`, and `rolls` have been rolled this turn.
+    fn game(mine: u32, theirs: u32, rolls: &[u32]) -> PigGame {
+        let mut game = PigGame::new(2);
+        for mut left in [mine, theirs] {
+            assert_ne!(left, 1, "no roll scores 1");
+            while left > 0 {
+                // Leave at least 2, which a single roll can make.
+                let face = if left == 7 { 4 } else { left.min(DIE_SIDES) };
+                game.roll(face).unwrap();
+                left -= face;
+            }
+            game.hold().unwrap();
+        }
+        for &roll in rolls {
+            game.roll(roll).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn test_hold_at() {
+        assert_eq!(HoldAt(20).decide(&game(0, 0, &[6, 6, 6])), Action::Roll);
+        assert_eq!(HoldAt(20).decide(&game(0, 0, &[6, 6, 6, 2])), Action::Hold);
+        assert_eq!(HoldAt(20).decide(&game(96, 0, &[4])), Action::Hold);
+    }
+
+    #[test]
+    fn test_keep_pace() {
+        // Level scores: hold at 21.
+        assert_eq!(KeepPace.decide(&game(30, 30, &[6, 6, 6])), Action::Roll);
+        assert_eq!(KeepPace.decide(&game(30, 30, &[6, 6, 6, 3])), Action::Hold);
+        // Far behind: push on.
+        assert_eq!(KeepPace.decide(&game(0, 60, &[6, 6, 6, 3])), Action::Roll);
+        // The opponent is close to winning: race.
+        assert_eq!(KeepPace.decide(&game(30, 80, &[6, 6, 6, 6, 6, 6])), Action::Roll);
+    }
+
+    #[test]
+    fn test_optimal_policy() {
+        let (policy, chances) = Policy::solve_with_chances(TARGET);
+        // Neller and Presser: the first player wins 53.06% of games
+        // between optimal players.
+        assert!((chances[0] - 0.5306).abs() < 0.0001, "{}", chances[0]);
+        // Never hold on nothing, always hold to win.
+        assert!(policy.should_roll(0, 0, 0));
+        assert!(!policy.should_roll(90, 50, 10));
+        // Go for it when the opponent is about to win.
+        assert!(policy.should_roll(50, 99, 30));
+        assert_eq!(policy.decide(&game(0, 0, &[6, 6, 6, 6, 6, 6])), Action::Hold);
+    }
+
+    #[test]
+    fn test_policy_for_other_targets() {
+        let rules = Rules { target: 20, ..Rules::default() };
+        assert!(Policy::solves(&rules));
+        let policy = Policy::solve(20);
+        assert!(policy.plays(&rules));
+        assert!(!policy.plays(&Rules::default()));
+        // To 20 points, 18 at stake is worth one more roll: only a 1 stops
+        // it winning.
+        let mut game = PigGame::with_rules(2, rules);
+        for face in [6, 6, 6] {
+            game.roll(face).unwrap();
+        }
+        assert_eq!(policy.decide(&game), Action::Roll);
+
+        assert!(!Policy::solves(&Rules::new(Variant::Hog)));
+        assert!(!Policy::solves(&Rules { die_sides: 8, ..Rules::default() }));
+        assert!(!Policy::solves(&Rules { target: MAX_POLICY_TARGET + 1, ..Rules::default() }));
+    }
+
+    #[test]
+    fn test_hog_throw() {
+        let game = PigGame::with_rules(2, Rules::new(Variant::Hog));
+        // Five and six dice both average a little over 8 points.
+        assert!(matches!(hog_throw(&game), Action::Throw(5 | 6)), "{:?}", hog_throw(&game));
+        assert_eq!(HoldAt(20).decide(&game), hog_throw(&game));
+
+        let mut game = PigGame::with_rules(2, Rules { target: 10, ..Rules::new(Variant::Hog) });
+        game.roll_dice(&[6]).unwrap();
+        game.roll_dice(&[6]).unwrap();
+        assert_eq!(hog_throw(&game), Action::Throw(1));
+    }
+}